@@ -13,7 +13,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Seam Carver
     let (w, h) = (img.get_width(), img.get_height());
     println!("original = w: {}, h: {}", w, h);
-    let angles = vec![
+    let angles = [
         60.0,  //   60.0 = q1:60.0
         135.0, //  135.0 = q2:45.0
         562.5, //  517.5 = q3:22.5
@@ -21,9 +21,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     ];
     let operations = angles.len();
     let mut results = Vec::new();
-    for i in 0..operations {
-        let angle = angles[i];
-        let result = photon_rs::transform::rotate(&img, angles[i]);
+    for angle in angles {
+        let result = photon_rs::transform::rotate(&img, angle);
         println!(
             "after rotate({}) = w: {}, h: {}",
             angle,
@@ -37,7 +36,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     for i in (0..operations).rev() {
         photon_rs::native::save_image(
             results.remove(i),
-            &format!("output_rotate_{}.jpg", i + 1),
+            format!("output_rotate_{}.jpg", i + 1),
         )?;
     }
     let end = Instant::now();
@@ -0,0 +1,643 @@
+//! Animated images: decode and encode animated GIF and APNG, decode animated WebP, and
+//! apply photon functions to every frame.
+
+use crate::dither::{palette_indices, DitherAlgorithm, DitherOptions};
+use crate::quantize::Palette;
+use crate::PhotonImage;
+use image::AnimationDecoder;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use thiserror::Error;
+
+#[cfg(feature = "enable_wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Speed of the palette quantizer used when writing GIFs, from 1 (best) to 30 (fastest).
+const GIF_QUANTIZE_SPEED: i32 = 10;
+
+/// Largest canvas, in pixels, that GIF and APNG files are decoded onto. Every frame is a
+/// full copy of the canvas, so the header's size is checked before anything is allocated.
+const MAX_CANVAS_PIXELS: usize = 1 << 25;
+
+#[derive(Debug, Error)]
+pub enum AnimationError {
+    #[error(transparent)]
+    GifDecodingError(#[from] gif::DecodingError),
+
+    #[error(transparent)]
+    GifEncodingError(#[from] gif::EncodingError),
+
+    #[error(transparent)]
+    PngDecodingError(#[from] png::DecodingError),
+
+    #[error(transparent)]
+    PngEncodingError(#[from] png::EncodingError),
+
+    #[error(transparent)]
+    ImageError(#[from] image::ImageError),
+
+    #[error("the animation has no frames")]
+    NoFrames,
+
+    #[error("every frame must be {0}x{1} pixels, like the first frame")]
+    FrameSize(u32, u32),
+
+    #[error("GIF palettes must have from 1 to 256 colours")]
+    PaletteSize,
+
+    #[error("GIF images can be at most 65535 pixels wide and high")]
+    GifSize,
+
+    #[error("a {0}x{1} animation is too large to decode")]
+    CanvasSize(u32, u32),
+
+    #[error("only GIF, PNG and WebP animations can be decoded")]
+    UnsupportedFormat,
+}
+
+#[cfg(feature = "enable_wasm")]
+impl From<AnimationError> for JsValue {
+    fn from(err: AnimationError) -> JsValue {
+        JsError::new(&err.to_string()).into()
+    }
+}
+
+/// What happens to a frame's area once it has been shown, before the next frame is drawn.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameDisposal {
+    /// Leave the frame in place.
+    Keep = 1,
+    /// Clear the frame's area to transparent.
+    Background = 2,
+    /// Restore the area to how it was before the frame was drawn.
+    Previous = 3,
+}
+
+/// A single frame of an animation, with how long it is shown for.
+///
+/// Frames always cover the whole animation.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AnimationFrame {
+    image: PhotonImage,
+    delay_ms: u32,
+    disposal: FrameDisposal,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl AnimationFrame {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create a frame shown for `delay_ms` milliseconds, cleared before the next frame.
+    pub fn new(image: PhotonImage, delay_ms: u32) -> AnimationFrame {
+        AnimationFrame {
+            image,
+            delay_ms,
+            disposal: FrameDisposal::Background,
+        }
+    }
+
+    /// Get the frame's image.
+    pub fn get_image(&self) -> PhotonImage {
+        self.image.clone()
+    }
+
+    /// Replace the frame's image.
+    pub fn set_image(&mut self, image: PhotonImage) {
+        self.image = image;
+    }
+
+    /// Get how long the frame is shown for, in milliseconds.
+    pub fn get_delay(&self) -> u32 {
+        self.delay_ms
+    }
+
+    /// Set how long the frame is shown for, in milliseconds.
+    pub fn set_delay(&mut self, delay_ms: u32) {
+        self.delay_ms = delay_ms;
+    }
+
+    /// Get what happens to the frame once it has been shown.
+    pub fn get_disposal(&self) -> FrameDisposal {
+        self.disposal
+    }
+
+    /// Set what happens to the frame once it has been shown.
+    pub fn set_disposal(&mut self, disposal: FrameDisposal) {
+        self.disposal = disposal;
+    }
+}
+
+/// An animation: a sequence of frames of the same size, played a number of times.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Animation {
+    frames: Vec<AnimationFrame>,
+    loop_count: u32,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl Animation {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create an empty animation which loops forever.
+    pub fn new() -> Animation {
+        Animation {
+            frames: Vec::new(),
+            loop_count: 0,
+        }
+    }
+
+    /// Get the width of the animation, which is the width of its frames.
+    pub fn get_width(&self) -> u32 {
+        self.frames.first().map_or(0, |frame| frame.image.width)
+    }
+
+    /// Get the height of the animation, which is the height of its frames.
+    pub fn get_height(&self) -> u32 {
+        self.frames.first().map_or(0, |frame| frame.image.height)
+    }
+
+    /// Get the number of frames.
+    pub fn get_frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Get a copy of the frame at `index`.
+    pub fn get_frame(&self, index: usize) -> Option<AnimationFrame> {
+        self.frames.get(index).cloned()
+    }
+
+    /// Replace the frame at `index`. Out of range indices are ignored.
+    pub fn set_frame(&mut self, index: usize, frame: AnimationFrame) {
+        if let Some(existing) = self.frames.get_mut(index) {
+            *existing = frame;
+        }
+    }
+
+    /// Add a frame to the end of the animation.
+    pub fn add_frame(&mut self, frame: AnimationFrame) {
+        self.frames.push(frame);
+    }
+
+    /// Get the number of times the animation plays, where 0 means forever.
+    pub fn get_loop_count(&self) -> u32 {
+        self.loop_count
+    }
+
+    /// Set the number of times the animation plays, where 0 means forever.
+    pub fn set_loop_count(&mut self, loop_count: u32) {
+        self.loop_count = loop_count;
+    }
+
+    /// Decode an animated GIF, PNG or WebP. Still images give a single frame.
+    ///
+    /// # Arguments
+    /// * `bytes` - The encoded animation.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use photon_rs::animation::Animation;
+    ///
+    /// let bytes = std::fs::read("animation.gif").expect("File should open");
+    /// let animation = Animation::decode(&bytes).expect("Animation should decode");
+    /// println!("{} frames", animation.get_frame_count());
+    /// ```
+    pub fn decode(bytes: &[u8]) -> Result<Animation, AnimationError> {
+        match image::guess_format(bytes)? {
+            image::ImageFormat::Gif => Animation::from_gif(bytes),
+            image::ImageFormat::Png => Animation::from_apng(bytes),
+            image::ImageFormat::WebP => Animation::from_webp(bytes),
+            _ => Err(AnimationError::UnsupportedFormat),
+        }
+    }
+
+    /// Encode the animation as a GIF. Each frame is quantized to its own palette of up to
+    /// 256 colours, and delays are rounded down to hundredths of a second.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use photon_rs::animation::Animation;
+    /// use photon_rs::monochrome::grayscale;
+    ///
+    /// let bytes = std::fs::read("animation.gif").expect("File should open");
+    /// let mut animation = Animation::decode(&bytes).expect("Animation should decode");
+    /// animation.map_frames(grayscale);
+    /// std::fs::write("grayscale.gif", animation.to_gif().expect("GIF should encode"))
+    ///     .expect("File should save");
+    /// ```
+    pub fn to_gif(&self) -> Result<Vec<u8>, AnimationError> {
+        let (width, height) = self.gif_size()?;
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut bytes, width, height, &[])?;
+            encoder.set_repeat(self.gif_repeat())?;
+            for frame in &self.frames {
+                let mut pixels = frame.image.raw_pixels.clone();
+                let mut gif_frame = gif::Frame::from_rgba_speed(
+                    width,
+                    height,
+                    &mut pixels,
+                    GIF_QUANTIZE_SPEED,
+                );
+                Animation::set_gif_timing(&mut gif_frame, frame);
+                encoder.write_frame(&gif_frame)?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Encode the animation as a GIF, with every frame remapped to a shared palette.
+    ///
+    /// # Arguments
+    /// * `palette` - The palette, of up to 256 colours.
+    /// * `dither` - The dithering algorithm used to remap the frames.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use photon_rs::animation::Animation;
+    /// use photon_rs::dither::DitherAlgorithm;
+    /// use photon_rs::quantize::Palette;
+    ///
+    /// let bytes = std::fs::read("animation.gif").expect("File should open");
+    /// let animation = Animation::decode(&bytes).expect("Animation should decode");
+    /// let gif = animation
+    ///     .to_gif_with_palette(&Palette::game_boy(), DitherAlgorithm::Bayer4)
+    ///     .expect("GIF should encode");
+    /// ```
+    pub fn to_gif_with_palette(
+        &self,
+        palette: &Palette,
+        dither: DitherAlgorithm,
+    ) -> Result<Vec<u8>, AnimationError> {
+        let (width, height) = self.gif_size()?;
+        if palette.is_empty() || palette.len() > 256 {
+            return Err(AnimationError::PaletteSize);
+        }
+        let global_palette: Vec<u8> =
+            palette.entries().iter().flatten().copied().collect();
+
+        let mut bytes = Vec::new();
+        {
+            let mut encoder =
+                gif::Encoder::new(&mut bytes, width, height, &global_palette)?;
+            encoder.set_repeat(self.gif_repeat())?;
+            let options = DitherOptions::new(dither);
+            for frame in &self.frames {
+                // The palette has at most 256 colours, so every index fits in a byte.
+                let indices: Vec<u8> = palette_indices(&frame.image, palette, &options)
+                    .into_iter()
+                    .map(|idx| idx as u8)
+                    .collect();
+                let mut gif_frame =
+                    gif::Frame::from_indexed_pixels(width, height, indices, None);
+                Animation::set_gif_timing(&mut gif_frame, frame);
+                encoder.write_frame(&gif_frame)?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Encode the animation as an animated PNG (APNG), without any loss of quality.
+    pub fn to_apng(&self) -> Result<Vec<u8>, AnimationError> {
+        let (width, height) = self.check_frames()?;
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(self.frames.len() as u32, self.loop_count)?;
+            let mut writer = encoder.write_header()?;
+            for frame in &self.frames {
+                writer
+                    .set_frame_delay(frame.delay_ms.min(u16::MAX as u32) as u16, 1000)?;
+                writer.set_dispose_op(match frame.disposal {
+                    FrameDisposal::Keep => png::DisposeOp::None,
+                    FrameDisposal::Background => png::DisposeOp::Background,
+                    FrameDisposal::Previous => png::DisposeOp::Previous,
+                })?;
+                writer.set_blend_op(png::BlendOp::Source)?;
+                writer.write_image_data(&frame.image.raw_pixels)?;
+            }
+            writer.finish()?;
+        }
+        Ok(bytes)
+    }
+}
+
+impl Animation {
+    /// Decode an animated GIF.
+    pub fn from_gif(bytes: &[u8]) -> Result<Animation, AnimationError> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(bytes)?;
+        let mut canvas = Canvas::new(decoder.width() as u32, decoder.height() as u32)?;
+
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame()? {
+            let disposal = match frame.dispose {
+                gif::DisposalMethod::Background => FrameDisposal::Background,
+                gif::DisposalMethod::Previous => FrameDisposal::Previous,
+                _ => FrameDisposal::Keep,
+            };
+            let region = Region {
+                x: frame.left as u32,
+                y: frame.top as u32,
+                width: frame.width as u32,
+                height: frame.height as u32,
+            };
+            // GIF transparency is all or nothing, so transparent pixels are skipped.
+            let image = canvas.draw(&frame.buffer, region, true, disposal);
+            frames.push(AnimationFrame {
+                image,
+                delay_ms: frame.delay as u32 * 10,
+                disposal,
+            });
+        }
+
+        let loop_count = match decoder.repeat() {
+            gif::Repeat::Infinite => 0,
+            gif::Repeat::Finite(repeats) => repeats as u32 + 1,
+        };
+        Ok(Animation { frames, loop_count })
+    }
+
+    /// Decode an animated PNG (APNG).
+    pub fn from_apng(bytes: &[u8]) -> Result<Animation, AnimationError> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let (width, height) = (reader.info().width, reader.info().height);
+        let mut canvas = Canvas::new(width, height)?;
+
+        let (frame_count, loop_count) = match reader.info().animation_control {
+            Some(control) => (control.num_frames as usize, control.num_plays),
+            None => (1, 0),
+        };
+        // Without a frame control before the image data, the default image isn't part
+        // of the animation.
+        let skip_default = reader.info().animation_control.is_some()
+            && reader.info().frame_control.is_none();
+
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let mut frames = Vec::new();
+        for idx in 0..frame_count + skip_default as usize {
+            let output = reader.next_frame(&mut buffer)?;
+            if idx == 0 && skip_default {
+                continue;
+            }
+            let pixels =
+                rgba_from_png(&buffer[..output.buffer_size()], output.color_type);
+
+            let control = reader.info().frame_control;
+            let (region, delay_ms, disposal, blend) = match control {
+                Some(control) => {
+                    let denominator = if control.delay_den == 0 {
+                        100
+                    } else {
+                        control.delay_den as u32
+                    };
+                    let disposal = match control.dispose_op {
+                        png::DisposeOp::None => FrameDisposal::Keep,
+                        png::DisposeOp::Background => FrameDisposal::Background,
+                        png::DisposeOp::Previous => FrameDisposal::Previous,
+                    };
+                    let region = Region {
+                        x: control.x_offset,
+                        y: control.y_offset,
+                        width: control.width,
+                        height: control.height,
+                    };
+                    let blend = control.blend_op == png::BlendOp::Over;
+                    let delay_ms = control.delay_num as u32 * 1000 / denominator;
+                    (region, delay_ms, disposal, blend)
+                }
+                None => {
+                    let region = Region {
+                        x: 0,
+                        y: 0,
+                        width,
+                        height,
+                    };
+                    (region, 0, FrameDisposal::Keep, false)
+                }
+            };
+
+            let image = canvas.draw(&pixels, region, blend, disposal);
+            frames.push(AnimationFrame {
+                image,
+                delay_ms,
+                disposal,
+            });
+        }
+
+        Ok(Animation { frames, loop_count })
+    }
+
+    /// Decode an animated WebP. WebP files don't give a loop count, so the animation
+    /// loops forever.
+    pub fn from_webp(bytes: &[u8]) -> Result<Animation, AnimationError> {
+        let decoder = image::codecs::webp::WebPDecoder::new(Cursor::new(bytes))?;
+        let frames = decoder
+            .into_frames()
+            .collect_frames()?
+            .into_iter()
+            .map(|frame| {
+                let (numerator, denominator) = frame.delay().numer_denom_ms();
+                let buffer = frame.into_buffer();
+                let (width, height) = buffer.dimensions();
+                AnimationFrame {
+                    image: PhotonImage::new(buffer.into_raw(), width, height),
+                    delay_ms: numerator / denominator.max(1),
+                    disposal: FrameDisposal::Background,
+                }
+            })
+            .collect();
+
+        Ok(Animation {
+            frames,
+            loop_count: 0,
+        })
+    }
+
+    /// Apply a function to the image of every frame, such as a filter or effect.
+    ///
+    /// Functions which resize the image must resize every frame the same way.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use photon_rs::animation::Animation;
+    /// use photon_rs::filters::filter;
+    ///
+    /// let bytes = std::fs::read("animation.gif").expect("File should open");
+    /// let mut animation = Animation::decode(&bytes).expect("Animation should decode");
+    /// animation.map_frames(|img| filter(img, "oceanic"));
+    /// ```
+    pub fn map_frames<F: FnMut(&mut PhotonImage)>(&mut self, mut func: F) {
+        for frame in &mut self.frames {
+            func(&mut frame.image);
+        }
+    }
+
+    /// Get the frames of the animation.
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    /// The GIF repetition for the loop count. GIFs count repeats after the first play.
+    fn gif_repeat(&self) -> gif::Repeat {
+        match self.loop_count {
+            0 => gif::Repeat::Infinite,
+            plays => gif::Repeat::Finite((plays - 1).min(u16::MAX as u32) as u16),
+        }
+    }
+
+    /// Copy a frame's delay and disposal to a GIF frame.
+    fn set_gif_timing(gif_frame: &mut gif::Frame, frame: &AnimationFrame) {
+        gif_frame.delay = (frame.delay_ms / 10).min(u16::MAX as u32) as u16;
+        gif_frame.dispose = match frame.disposal {
+            FrameDisposal::Keep => gif::DisposalMethod::Keep,
+            FrameDisposal::Background => gif::DisposalMethod::Background,
+            FrameDisposal::Previous => gif::DisposalMethod::Previous,
+        };
+    }
+
+    /// The size of the animation, checking that it fits in a GIF.
+    fn gif_size(&self) -> Result<(u16, u16), AnimationError> {
+        let (width, height) = self.check_frames()?;
+        match (u16::try_from(width), u16::try_from(height)) {
+            (Ok(width), Ok(height)) => Ok((width, height)),
+            _ => Err(AnimationError::GifSize),
+        }
+    }
+
+    /// The size of the animation, checking that every frame has the same size.
+    fn check_frames(&self) -> Result<(u32, u32), AnimationError> {
+        let first = self.frames.first().ok_or(AnimationError::NoFrames)?;
+        let (width, height) = (first.image.width, first.image.height);
+        for frame in &self.frames {
+            if (frame.image.width, frame.image.height) != (width, height) {
+                return Err(AnimationError::FrameSize(width, height));
+            }
+        }
+        Ok((width, height))
+    }
+}
+
+/// Area of the canvas a frame is drawn to.
+#[derive(Clone, Copy)]
+struct Region {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+/// The composited state of an animation as its frames are drawn.
+struct Canvas {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Result<Canvas, AnimationError> {
+        let pixels = (width as usize)
+            .checked_mul(height as usize)
+            .filter(|pixels| *pixels <= MAX_CANVAS_PIXELS)
+            .ok_or(AnimationError::CanvasSize(width, height))?;
+        Ok(Canvas {
+            pixels: vec![0; pixels * 4],
+            width,
+            height,
+        })
+    }
+
+    /// Draw a frame's RGBA pixels into a region, returning the whole composited frame and
+    /// then applying the frame's disposal.
+    fn draw(
+        &mut self,
+        frame: &[u8],
+        region: Region,
+        blend: bool,
+        disposal: FrameDisposal,
+    ) -> PhotonImage {
+        let previous =
+            (disposal == FrameDisposal::Previous).then(|| self.pixels.clone());
+
+        // Only the part of the region inside the canvas, and covered by the frame's
+        // pixels, is drawn.
+        let width = self.width as usize;
+        let (left, top) = (region.x as usize, region.y as usize);
+        let region_width = region.width as usize;
+        let cols = region_width.min(width.saturating_sub(left));
+        let rows = (region.height as usize)
+            .min((self.height as usize).saturating_sub(top))
+            .min(frame.len() / region_width.saturating_mul(4).max(1));
+
+        for row in 0..rows {
+            for col in 0..cols {
+                let src_idx = (row * region_width + col) * 4;
+                let src = &frame[src_idx..src_idx + 4];
+                let dst_idx = ((top + row) * width + left + col) * 4;
+                let dst = &mut self.pixels[dst_idx..dst_idx + 4];
+                if !blend || src[3] == 255 {
+                    dst.copy_from_slice(src);
+                } else if src[3] > 0 {
+                    // Alpha compositing of the frame over the canvas.
+                    let src_alpha = src[3] as f32 / 255.0;
+                    let dst_alpha = dst[3] as f32 / 255.0;
+                    let out_alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+                    for chan in 0..3 {
+                        let val = (src[chan] as f32 * src_alpha
+                            + dst[chan] as f32 * dst_alpha * (1.0 - src_alpha))
+                            / out_alpha;
+                        dst[chan] = val.round() as u8;
+                    }
+                    dst[3] = (out_alpha * 255.0).round() as u8;
+                }
+            }
+        }
+        let image = PhotonImage::new(self.pixels.clone(), self.width, self.height);
+
+        match disposal {
+            FrameDisposal::Keep => {}
+            FrameDisposal::Background => {
+                let rows = (region.height as usize)
+                    .min((self.height as usize).saturating_sub(top));
+                for y in top..top + rows {
+                    let start = (y * width + left) * 4;
+                    self.pixels[start..start + cols * 4].fill(0);
+                }
+            }
+            FrameDisposal::Previous => {
+                if let Some(previous) = previous {
+                    self.pixels = previous;
+                }
+            }
+        }
+        image
+    }
+}
+
+/// Expand 8-bit PNG pixels of any colour type to RGBA.
+fn rgba_from_png(pixels: &[u8], color_type: png::ColorType) -> Vec<u8> {
+    match color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|px| [px[0], px[1], px[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|px| [px[0], px[0], px[0], px[1]])
+            .collect(),
+        _ => pixels
+            .iter()
+            .flat_map(|val| [*val, *val, *val, 255])
+            .collect(),
+    }
+}
@@ -1,11 +1,15 @@
 //! Image manipulation effects in HSL, HSLuv, LCh and HSV.
 
 use crate::iter::ImageIterator;
+use crate::storage::PhotonImageF32;
 use crate::{helpers, PhotonImage, Rgb};
 use image::GenericImageView;
 use image::Pixel as ImagePixel;
+use palette::convert::FromColorUnclamped;
 use palette::{FromColor, IntoColor};
 use palette::{Hsla, Hsluva, Hsva, Hue, Lcha, Saturate, Shade, Srgba};
+use palette::{LinSrgb, Srgb, Xyz};
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "enable_wasm")]
 use wasm_bindgen::prelude::*;
@@ -49,9 +53,11 @@ pub fn gamma_correction(
     // Set values within gamma arrays
     for i in 0..256 {
         let input = (i as f32) / 255.0;
-        gamma_r[i] = (255.0 * input.powf(inv_red) + 0.5).clamp(0.0, 255.0) as u8;
-        gamma_g[i] = (255.0 * input.powf(inv_green) + 0.5).clamp(0.0, 255.0) as u8;
-        gamma_b[i] = (255.0 * input.powf(inv_blue) + 0.5).clamp(0.0, 255.0) as u8;
+        gamma_r[i] = (255.0 * gamma_curve(input, inv_red) + 0.5).clamp(0.0, 255.0) as u8;
+        gamma_g[i] =
+            (255.0 * gamma_curve(input, inv_green) + 0.5).clamp(0.0, 255.0) as u8;
+        gamma_b[i] =
+            (255.0 * gamma_curve(input, inv_blue) + 0.5).clamp(0.0, 255.0) as u8;
     }
 
     // Apply gamma correction
@@ -66,6 +72,77 @@ pub fn gamma_correction(
     }
 }
 
+/// Applies gamma correction to a floating-point image.
+///
+/// # Arguments
+/// * `img` - A PhotonImageF32.
+/// * `red` - Gamma value for red channel.
+/// * `green` - Gamma value for green channel.
+/// * `blue` - Gamma value for blue channel.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::native::open_image_f32;
+/// use photon_rs::colour_spaces::gamma_correction_f32;
+///
+/// let mut img = open_image_f32("img.png").expect("File should open");
+/// gamma_correction_f32(&mut img, 2.2, 2.2, 2.2);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn gamma_correction_f32(img: &mut PhotonImageF32, red: f32, green: f32, blue: f32) {
+    let gammas = [1.0 / red, 1.0 / green, 1.0 / blue];
+    for px in img.raw_pixels.chunks_mut(4) {
+        for (val, gamma) in px.iter_mut().zip(gammas) {
+            *val = gamma_curve(*val, gamma);
+        }
+    }
+}
+
+/// Raise a channel value from 0 to 1 to the power of `inv_gamma`.
+fn gamma_curve(input: f32, inv_gamma: f32) -> f32 {
+    input.max(0.0).powf(inv_gamma)
+}
+
+/// Apply one of the modes accepted by `hsl` to a colour.
+fn hsl_adjust(hsl_colour: Hsla, mode: &str, amt: f32) -> Hsla {
+    match mode {
+        // Match a single value
+        "desaturate" => hsl_colour.desaturate(amt),
+        "saturate" => hsl_colour.saturate(amt),
+        "lighten" => hsl_colour.lighten(amt),
+        "darken" => hsl_colour.darken(amt),
+        "shift_hue" => hsl_colour.shift_hue(amt * 360.0),
+        _ => hsl_colour.saturate(amt),
+    }
+}
+
+/// Apply one of the modes accepted by `lch` to a colour.
+fn lch_adjust(lch_colour: Lcha, mode: &str, amt: f32) -> Lcha {
+    match mode {
+        // Match a single value
+        "desaturate" => lch_colour.desaturate(amt),
+        "saturate" => lch_colour.saturate(amt),
+        "lighten" => lch_colour.lighten(amt),
+        "darken" => lch_colour.darken(amt),
+        "shift_hue" => lch_colour.shift_hue(amt * 360.0),
+        _ => lch_colour.saturate(amt),
+    }
+}
+
+/// Apply one of the modes accepted by `hsv` to a colour.
+fn hsv_adjust(hsv_colour: Hsva, mode: &str, amt: f32) -> Hsva {
+    match mode {
+        // Match a single value
+        "desaturate" => hsv_colour.desaturate(amt),
+        "saturate" => hsv_colour.saturate(amt),
+        "lighten" => hsv_colour.lighten(amt),
+        "darken" => hsv_colour.darken(amt),
+        "shift_hue" => hsv_colour.shift_hue(amt * 360.0),
+        _ => hsv_colour.saturate(amt),
+    }
+}
+
 /// Image manipulation effects in the HSLuv colour space
 ///
 /// Effects include:
@@ -174,15 +251,7 @@ pub fn lch(photon_image: &mut PhotonImage, mode: &str, amt: f32) {
         .into_linear()
         .into_color();
 
-        let new_color = match mode {
-            // Match a single value
-            "desaturate" => lch_colour.desaturate(amt),
-            "saturate" => lch_colour.saturate(amt),
-            "lighten" => lch_colour.lighten(amt),
-            "darken" => lch_colour.darken(amt),
-            "shift_hue" => lch_colour.shift_hue(amt * 360.0),
-            _ => lch_colour.saturate(amt),
-        };
+        let new_color = lch_adjust(lch_colour, mode, amt);
         let final_color: Srgba =
             Srgba::from_linear(new_color.into_color()).into_format();
 
@@ -242,15 +311,7 @@ pub fn hsl(photon_image: &mut PhotonImage, mode: &str, amt: f32) {
 
         let hsl_colour = Hsla::from_color(colour);
 
-        let new_color = match mode {
-            // Match a single value
-            "desaturate" => hsl_colour.desaturate(amt),
-            "saturate" => hsl_colour.saturate(amt),
-            "lighten" => hsl_colour.lighten(amt),
-            "darken" => hsl_colour.darken(amt),
-            "shift_hue" => hsl_colour.shift_hue(amt * 360.0),
-            _ => hsl_colour.saturate(amt),
-        };
+        let new_color = hsl_adjust(hsl_colour, mode, amt);
         let final_color = Srgba::from_color(new_color);
 
         let components = final_color.into_components();
@@ -270,6 +331,84 @@ pub fn hsl(photon_image: &mut PhotonImage, mode: &str, amt: f32) {
     photon_image.raw_pixels = img.to_vec();
 }
 
+/// Image manipulation in the HSL colour space on a floating-point image.
+/// Accepts the same modes as `colour_spaces::hsl`.
+///
+/// # Arguments
+/// * `img` - A PhotonImageF32.
+/// * `mode` - The effect desired to be applied. Choose from: `saturate`, `desaturate`,
+/// `shift_hue`, `darken`, `lighten`
+/// * `amt` - A float value from 0 to 1 which represents the amount the effect should be
+/// increased by.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::native::open_image_f32;
+/// use photon_rs::colour_spaces::hsl_f32;
+///
+/// let mut img = open_image_f32("img.png").expect("File should open");
+/// hsl_f32(&mut img, "saturate", 0.1_f32);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn hsl_f32(img: &mut PhotonImageF32, mode: &str, amt: f32) {
+    for px in img.raw_pixels.chunks_mut(4) {
+        let hsl_colour: Hsla = Srgba::new(
+            px[0].clamp(0.0, 1.0),
+            px[1].clamp(0.0, 1.0),
+            px[2].clamp(0.0, 1.0),
+            px[3],
+        )
+        .into_color();
+
+        let final_colour: Srgba = hsl_adjust(hsl_colour, mode, amt).into_color();
+
+        px[0] = final_colour.red;
+        px[1] = final_colour.green;
+        px[2] = final_colour.blue;
+    }
+}
+
+/// Image manipulation in the LCh colour space on a floating-point image.
+/// Accepts the same modes as `lch`.
+///
+/// # Arguments
+/// * `img` - A PhotonImageF32.
+/// * `mode` - The effect desired to be applied. Choose from: `saturate`, `desaturate`,
+///   `shift_hue`, `darken`, `lighten`
+/// * `amt` - A float value from 0 to 1 which represents the amount the effect should be
+///   increased by.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::colour_spaces::lch_f32;
+/// use photon_rs::native::open_image_f32;
+///
+/// let mut img = open_image_f32("img.png").expect("File should open");
+/// lch_f32(&mut img, "saturate", 0.1_f32);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn lch_f32(img: &mut PhotonImageF32, mode: &str, amt: f32) {
+    for px in img.raw_pixels.chunks_mut(4) {
+        let lch_colour: Lcha = Srgba::new(
+            px[0].clamp(0.0, 1.0),
+            px[1].clamp(0.0, 1.0),
+            px[2].clamp(0.0, 1.0),
+            px[3],
+        )
+        .into_linear()
+        .into_color();
+
+        let final_colour: Srgba =
+            Srgba::from_linear(lch_adjust(lch_colour, mode, amt).into_color());
+
+        px[0] = final_colour.red;
+        px[1] = final_colour.green;
+        px[2] = final_colour.blue;
+    }
+}
+
 /// Image manipulation in the HSV colour space.
 ///
 /// Effects include:
@@ -312,15 +451,7 @@ pub fn hsv(photon_image: &mut PhotonImage, mode: &str, amt: f32) {
 
         let hsv_colour = Hsva::from_color(color);
 
-        let new_color = match mode {
-            // Match a single value
-            "desaturate" => hsv_colour.desaturate(amt),
-            "saturate" => hsv_colour.saturate(amt),
-            "lighten" => hsv_colour.lighten(amt),
-            "darken" => hsv_colour.darken(amt),
-            "shift_hue" => hsv_colour.shift_hue(amt * 360.0),
-            _ => hsv_colour.saturate(amt),
-        };
+        let new_color = hsv_adjust(hsv_colour, mode, amt);
 
         let srgba_new_color = Srgba::from_color(new_color);
         // let final_color: Srgba = Srgba::from_linear(srgba_new_color).into_format();
@@ -341,6 +472,43 @@ pub fn hsv(photon_image: &mut PhotonImage, mode: &str, amt: f32) {
     photon_image.raw_pixels = img.to_vec();
 }
 
+/// Image manipulation in the HSV colour space on a floating-point image.
+/// Accepts the same modes as `hsv`.
+///
+/// # Arguments
+/// * `img` - A PhotonImageF32.
+/// * `mode` - The effect desired to be applied. Choose from: `saturate`, `desaturate`,
+///   `shift_hue`, `darken`, `lighten`
+/// * `amt` - A float value from 0 to 1 which represents the amount the effect should be
+///   increased by.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::colour_spaces::hsv_f32;
+/// use photon_rs::native::open_image_f32;
+///
+/// let mut img = open_image_f32("img.png").expect("File should open");
+/// hsv_f32(&mut img, "saturate", 0.1_f32);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn hsv_f32(img: &mut PhotonImageF32, mode: &str, amt: f32) {
+    for px in img.raw_pixels.chunks_mut(4) {
+        let hsv_colour = Hsva::from_color(Srgba::new(
+            px[0].clamp(0.0, 1.0),
+            px[1].clamp(0.0, 1.0),
+            px[2].clamp(0.0, 1.0),
+            px[3],
+        ));
+
+        let final_colour = Srgba::from_color(hsv_adjust(hsv_colour, mode, amt));
+
+        px[0] = final_colour.red;
+        px[1] = final_colour.green;
+        px[2] = final_colour.blue;
+    }
+}
+
 /// Shift hue by a specified number of degrees in the HSL colour space.
 /// # Arguments
 /// * `img` - A PhotonImage.
@@ -814,19 +982,13 @@ pub fn mix_with_colour(photon_image: &mut PhotonImage, mix_colour: Rgb, opacity:
     let (width, height) = img.dimensions();
     let mut img = img.to_rgba8();
 
-    // cache (mix_color_value * opacity) and (1 - opacity) so we dont need to calculate them each time during loop.
-    let mix_red_offset = mix_colour.r as f32 * opacity;
-    let mix_green_offset = mix_colour.g as f32 * opacity;
-    let mix_blue_offset = mix_colour.b as f32 * opacity;
-    let factor = 1.0 - opacity;
-
     for (x, y) in ImageIterator::new(width, height) {
         let px = img.get_pixel(x, y);
         let channels = px.channels();
 
-        let r_value = mix_red_offset + (channels[0] as f32) * factor;
-        let g_value = mix_green_offset + (channels[1] as f32) * factor;
-        let b_value = mix_blue_offset + (channels[2] as f32) * factor;
+        let r_value = mix_channel(channels[0] as f32, mix_colour.r as f32, opacity);
+        let g_value = mix_channel(channels[1] as f32, mix_colour.g as f32, opacity);
+        let b_value = mix_channel(channels[2] as f32, mix_colour.b as f32, opacity);
         let alpha = channels[3];
         img.put_pixel(
             x,
@@ -836,3 +998,581 @@ pub fn mix_with_colour(photon_image: &mut PhotonImage, mix_colour: Rgb, opacity:
     }
     photon_image.raw_pixels = img.to_vec();
 }
+
+/// Mix an image with a single colour, on a floating-point image.
+///
+/// # Arguments
+/// * `img` - A PhotonImageF32.
+/// * `mix_colour` - the color to be mixed in, as an RGB value.
+/// * `opacity` - the opacity of color when mixed to image. Float value from 0 to 1.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::colour_spaces::mix_with_colour_f32;
+/// use photon_rs::native::open_image_f32;
+/// use photon_rs::Rgb;
+///
+/// let mut img = open_image_f32("img.png").expect("File should open");
+/// mix_with_colour_f32(&mut img, Rgb::new(50_u8, 255_u8, 254_u8), 0.4_f32);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn mix_with_colour_f32(img: &mut PhotonImageF32, mix_colour: Rgb, opacity: f32) {
+    let mix = [mix_colour.r, mix_colour.g, mix_colour.b];
+    for px in img.raw_pixels.chunks_mut(4) {
+        for (val, mix) in px.iter_mut().zip(mix) {
+            *val = mix_channel(*val, mix as f32 / 255.0, opacity);
+        }
+    }
+}
+
+/// Blend a channel value towards the value of the mixed-in colour.
+fn mix_channel(val: f32, mix: f32, opacity: f32) -> f32 {
+    mix * opacity + val * (1.0 - opacity)
+}
+
+/// Increase or decrease the saturation of an image, favouring colours which are not
+/// already saturated.
+///
+/// Unlike `saturate_*`, vibrance leaves strong colours mostly alone, and protects skin tones
+/// so portraits don't turn orange.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `amount` - Strength of the effect, from -1 (muted) to 1 (vivid).
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::colour_spaces::vibrance;
+/// use photon_rs::native::open_image;
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// vibrance(&mut img, 0.5_f32);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn vibrance(photon_image: &mut PhotonImage, amount: f32) {
+    // Chroma of the most saturated sRGB colours in LCh.
+    const MAX_CHROMA: f32 = 130.0;
+    // Skin tones sit around this LCh hue, in degrees.
+    const SKIN_HUE: f32 = 55.0;
+    const SKIN_HUE_RANGE: f32 = 30.0;
+
+    let amount = amount.clamp(-1.0, 1.0);
+
+    for px in photon_image.raw_pixels.chunks_mut(4) {
+        let lch_colour: Lcha = Srgba::new(
+            px[0] as f32 / 255.0,
+            px[1] as f32 / 255.0,
+            px[2] as f32 / 255.0,
+            px[3] as f32 / 255.0,
+        )
+        .into_linear()
+        .into_color();
+
+        let saturation = (lch_colour.chroma / MAX_CHROMA).min(1.0);
+        let hue_distance = (lch_colour.hue.to_positive_degrees() - SKIN_HUE).abs();
+        let skin = (1.0 - hue_distance / SKIN_HUE_RANGE).max(0.0);
+        let weight = amount * (1.0 - saturation) * (1.0 - 0.7 * skin);
+
+        let mut new_colour = lch_colour;
+        new_colour.chroma = (lch_colour.chroma * (1.0 + weight)).max(0.0);
+
+        let final_colour: Srgba =
+            Srgba::from_linear(new_colour.into_color()).into_format();
+        let components = final_colour.into_components();
+        px[0] = (components.0 * 255.0).round() as u8;
+        px[1] = (components.1 * 255.0).round() as u8;
+        px[2] = (components.2 * 255.0).round() as u8;
+    }
+}
+
+/// One of the eight hue bands adjusted by an `HslMixer`.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HueBand {
+    Red = 1,
+    Orange = 2,
+    Yellow = 3,
+    Green = 4,
+    Aqua = 5,
+    Blue = 6,
+    Purple = 7,
+    Magenta = 8,
+}
+
+/// Colour space in which an `HslMixer` finds hues and applies its adjustments.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MixerSpace {
+    /// Fast, but lightness changes look uneven across hues.
+    Hsl = 1,
+    /// Perceptual, so equal adjustments look equal on every hue.
+    Lch = 2,
+}
+
+/// Centre of each hue band in HSL, in degrees.
+const HSL_BAND_CENTRES: [f32; 8] = [0.0, 30.0, 60.0, 120.0, 180.0, 240.0, 270.0, 300.0];
+
+/// Centre of each hue band in LCh, in degrees.
+const LCH_BAND_CENTRES: [f32; 8] = [40.0, 62.0, 95.0, 135.0, 195.0, 270.0, 305.0, 335.0];
+
+/// Per-hue-band hue, saturation and luminance adjustments, like the colour mixer in
+/// photo editors. Apply it with `hsl_mixer`.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HslMixer {
+    space: MixerSpace,
+    hue: [f32; 8],
+    saturation: [f32; 8],
+    luminance: [f32; 8],
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl HslMixer {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create a mixer which leaves every band unchanged.
+    pub fn new(space: MixerSpace) -> HslMixer {
+        HslMixer {
+            space,
+            hue: [0.0; 8],
+            saturation: [0.0; 8],
+            luminance: [0.0; 8],
+        }
+    }
+
+    /// Set the hue shift of a band in degrees, from -60 to 60.
+    pub fn set_hue(&mut self, band: HueBand, degrees: f32) {
+        self.hue[band as usize - 1] = degrees.clamp(-60.0, 60.0);
+    }
+
+    /// Set the saturation change of a band, from -1 (grey) to 1.
+    pub fn set_saturation(&mut self, band: HueBand, amount: f32) {
+        self.saturation[band as usize - 1] = amount.clamp(-1.0, 1.0);
+    }
+
+    /// Set the luminance change of a band, from -1 (darker) to 1 (lighter).
+    pub fn set_luminance(&mut self, band: HueBand, amount: f32) {
+        self.luminance[band as usize - 1] = amount.clamp(-1.0, 1.0);
+    }
+
+    /// Get the hue shift of a band in degrees.
+    pub fn get_hue(&self, band: HueBand) -> f32 {
+        self.hue[band as usize - 1]
+    }
+
+    /// Get the saturation change of a band.
+    pub fn get_saturation(&self, band: HueBand) -> f32 {
+        self.saturation[band as usize - 1]
+    }
+
+    /// Get the luminance change of a band.
+    pub fn get_luminance(&self, band: HueBand) -> f32 {
+        self.luminance[band as usize - 1]
+    }
+
+    /// Get the colour space the mixer works in.
+    pub fn get_space(&self) -> MixerSpace {
+        self.space
+    }
+}
+
+impl HslMixer {
+    /// Blend the adjustments of the two bands either side of `hue`, so that colours change
+    /// smoothly from one band to the next. Returns the hue, saturation and luminance changes.
+    fn adjustments(&self, hue: f32) -> (f32, f32, f32) {
+        let centres = match self.space {
+            MixerSpace::Hsl => &HSL_BAND_CENTRES,
+            MixerSpace::Lch => &LCH_BAND_CENTRES,
+        };
+
+        let hue = hue.rem_euclid(360.0);
+        let mut lower = centres.len() - 1;
+        for (band, centre) in centres.iter().enumerate() {
+            if *centre <= hue {
+                lower = band;
+            }
+        }
+        let upper = (lower + 1) % centres.len();
+        let span = (centres[upper] - centres[lower]).rem_euclid(360.0);
+        let offset = (hue - centres[lower]).rem_euclid(360.0);
+
+        // Cosine falloff keeps the transition between bands free of visible steps.
+        let t = offset / span;
+        let lower_weight = 0.5 + 0.5 * (std::f32::consts::PI * t).cos();
+        let upper_weight = 1.0 - lower_weight;
+
+        let blend = |values: &[f32; 8]| {
+            values[lower] * lower_weight + values[upper] * upper_weight
+        };
+        (
+            blend(&self.hue),
+            blend(&self.saturation),
+            blend(&self.luminance),
+        )
+    }
+}
+
+/// Adjust the hue, saturation and luminance of each hue band of an image independently.
+///
+/// Greys are left untouched, and colours are adjusted in proportion to how saturated they
+/// are.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `mixer` - The per-band adjustments.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to turn a blue sky deeper and darker while keeping greens natural:
+/// use photon_rs::colour_spaces::{hsl_mixer, HslMixer, HueBand, MixerSpace};
+/// use photon_rs::native::open_image;
+///
+/// let mut mixer = HslMixer::new(MixerSpace::Lch);
+/// mixer.set_saturation(HueBand::Blue, 0.4);
+/// mixer.set_luminance(HueBand::Blue, -0.3);
+/// mixer.set_hue(HueBand::Green, -10.0);
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// hsl_mixer(&mut img, &mixer);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn hsl_mixer(photon_image: &mut PhotonImage, mixer: &HslMixer) {
+    // Chroma at which an LCh colour counts as fully saturated for weighting purposes.
+    const FULL_CHROMA: f32 = 40.0;
+
+    for px in photon_image.raw_pixels.chunks_mut(4) {
+        let colour = Srgba::new(
+            px[0] as f32 / 255.0,
+            px[1] as f32 / 255.0,
+            px[2] as f32 / 255.0,
+            px[3] as f32 / 255.0,
+        );
+
+        let final_colour: Srgba = match mixer.space {
+            MixerSpace::Hsl => {
+                let mut hsl_colour = Hsla::from_color(colour);
+                let weight = hsl_colour.saturation;
+                let (hue, sat, lum) =
+                    mixer.adjustments(hsl_colour.hue.to_positive_degrees());
+
+                hsl_colour = hsl_colour.shift_hue(hue * weight);
+                hsl_colour.saturation =
+                    (hsl_colour.saturation * (1.0 + sat)).clamp(0.0, 1.0);
+                hsl_colour.lightness =
+                    (hsl_colour.lightness + lum * 0.25 * weight).clamp(0.0, 1.0);
+                Srgba::from_color(hsl_colour)
+            }
+            MixerSpace::Lch => {
+                let mut lch_colour: Lcha = colour.into_linear().into_color();
+                let weight = (lch_colour.chroma / FULL_CHROMA).min(1.0);
+                let (hue, sat, lum) =
+                    mixer.adjustments(lch_colour.hue.to_positive_degrees());
+
+                lch_colour = lch_colour.shift_hue(hue * weight);
+                lch_colour.chroma = (lch_colour.chroma * (1.0 + sat)).max(0.0);
+                lch_colour.l = (lch_colour.l + lum * 25.0 * weight).clamp(0.0, 100.0);
+                Srgba::from_linear(lch_colour.into_color()).into_format()
+            }
+        };
+
+        let components = final_colour.into_components();
+        px[0] = (components.0 * 255.0).round() as u8;
+        px[1] = (components.1 * 255.0).round() as u8;
+        px[2] = (components.2 * 255.0).round() as u8;
+    }
+}
+
+/// Bradford cone response matrix, used for chromatic adaptation in XYZ.
+const BRADFORD: [[f32; 3]; 3] = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+/// Inverse of the Bradford cone response matrix.
+const BRADFORD_INV: [[f32; 3]; 3] = [
+    [0.986_993, -0.147_054, 0.159_963],
+    [0.432_305, 0.518_360, 0.049_291],
+    [-0.008_529, 0.040_043, 0.968_487],
+];
+
+/// Colour temperature, in Kelvin, which `white_balance` treats as neutral.
+const NEUTRAL_TEMPERATURE: f32 = 6500.0;
+
+/// Method used by `auto_white_balance` to estimate the colour of the light in a scene.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WhiteBalanceMethod {
+    /// Assume the average colour of the scene is grey.
+    GrayWorld = 1,
+    /// Assume the brightest part of the scene is white.
+    WhitePatch = 2,
+}
+
+pub(crate) fn mat_mul(a: &[[f32; 3]; 3], b: &[[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    let mut out = [[0.0; 3]; 3];
+    for (row, out_row) in out.iter_mut().enumerate() {
+        for (col, val) in out_row.iter_mut().enumerate() {
+            *val = (0..3).map(|k| a[row][k] * b[k][col]).sum();
+        }
+    }
+    out
+}
+
+pub(crate) fn mat_vec(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+/// Inverse of a 3x3 matrix, or `None` if it is singular.
+pub(crate) fn mat_inverse(m: &[[f32; 3]; 3]) -> Option<[[f32; 3]; 3]> {
+    let cofactor = |row: usize, col: usize| {
+        let (r1, r2) = ((row + 1) % 3, (row + 2) % 3);
+        let (c1, c2) = ((col + 1) % 3, (col + 2) % 3);
+        m[r1][c1] * m[r2][c2] - m[r1][c2] * m[r2][c1]
+    };
+    let det: f32 = (0..3).map(|col| m[0][col] * cofactor(0, col)).sum();
+    if det.abs() <= f32::EPSILON {
+        return None;
+    }
+
+    let mut out = [[0.0; 3]; 3];
+    for (row, out_row) in out.iter_mut().enumerate() {
+        for (col, val) in out_row.iter_mut().enumerate() {
+            *val = cofactor(col, row) / det;
+        }
+    }
+    Some(out)
+}
+
+/// XYZ of a white with chromaticity `(x, y)`, normalised to a luminance of 1.
+pub(crate) fn white_from_chromaticity(x: f32, y: f32) -> [f32; 3] {
+    [x / y, 1.0, (1.0 - x - y) / y]
+}
+
+/// Approximate chromaticity of a black body at the given temperature (Kim et al.).
+fn planckian_chromaticity(temperature: f32) -> (f32, f32) {
+    let t = temperature.clamp(1667.0, 25000.0) as f64;
+    let (t2, t3) = (t * t, t * t * t);
+    let x = if t <= 4000.0 {
+        -0.266_123_9e9 / t3 - 0.234_358_9e6 / t2 + 0.877_695_6e3 / t + 0.179_910
+    } else {
+        -3.025_846_9e9 / t3 + 2.107_037_9e6 / t2 + 0.222_634_7e3 / t + 0.240_390
+    };
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t <= 2222.0 {
+        -1.106_381_4 * x3 - 1.348_110_2 * x2 + 2.185_558_3 * x - 0.202_196_83
+    } else if t <= 4000.0 {
+        -0.954_947_6 * x3 - 1.374_185_9 * x2 + 2.091_370_2 * x - 0.167_488_67
+    } else {
+        3.081_758 * x3 - 5.873_386_7 * x2 + 3.751_13 * x - 0.370_014_83
+    };
+    (x as f32, y as f32)
+}
+
+/// XYZ of a linear sRGB colour, normalised to a luminance of 1.
+fn white_from_linear_rgb(rgb: [f32; 3]) -> Option<[f32; 3]> {
+    let xyz: Xyz = Xyz::from_color(LinSrgb::new(rgb[0], rgb[1], rgb[2]));
+    if xyz.y <= f32::EPSILON {
+        return None;
+    }
+    Some([xyz.x / xyz.y, 1.0, xyz.z / xyz.y])
+}
+
+/// Bradford transform in XYZ from colours seen under `source_white` to colours seen
+/// under `dest_white`.
+pub(crate) fn bradford_adaptation(
+    source_white: [f32; 3],
+    dest_white: [f32; 3],
+) -> [[f32; 3]; 3] {
+    let source_cone = mat_vec(&BRADFORD, source_white);
+    let dest_cone = mat_vec(&BRADFORD, dest_white);
+    let mut scale = [[0.0; 3]; 3];
+    for chan in 0..3 {
+        scale[chan][chan] = dest_cone[chan] / source_cone[chan];
+    }
+    mat_mul(&BRADFORD_INV, &mat_mul(&scale, &BRADFORD))
+}
+
+/// Adapt an image lit by `source_white` so that it appears lit by `dest_white`,
+/// using the Bradford transform.
+fn adapt_white(
+    photon_image: &mut PhotonImage,
+    source_white: [f32; 3],
+    dest_white: [f32; 3],
+) {
+    let adapt_xyz = bradford_adaptation(source_white, dest_white);
+
+    // Fold the conversions between linear sRGB and XYZ into the same matrix.
+    let mut rgb_to_xyz = [[0.0; 3]; 3];
+    let mut xyz_to_rgb = [[0.0; 3]; 3];
+    for col in 0..3 {
+        let mut basis = [0.0; 3];
+        basis[col] = 1.0;
+        let xyz: Xyz = Xyz::from_color(LinSrgb::new(basis[0], basis[1], basis[2]));
+        let rgb = LinSrgb::from_color_unclamped(Xyz::new(basis[0], basis[1], basis[2]));
+        for (row, val) in [xyz.x, xyz.y, xyz.z].into_iter().enumerate() {
+            rgb_to_xyz[row][col] = val;
+        }
+        for (row, val) in [rgb.red, rgb.green, rgb.blue].into_iter().enumerate() {
+            xyz_to_rgb[row][col] = val;
+        }
+    }
+    let transform = mat_mul(&xyz_to_rgb, &mat_mul(&adapt_xyz, &rgb_to_xyz));
+
+    let to_linear: Vec<f32> = (0..=255_u8)
+        .map(|val| Srgb::new(val, 0, 0).into_format::<f32>().into_linear().red)
+        .collect();
+
+    for px in photon_image.raw_pixels.chunks_mut(4) {
+        let rgb = [
+            to_linear[px[0] as usize],
+            to_linear[px[1] as usize],
+            to_linear[px[2] as usize],
+        ];
+        let adapted = mat_vec(&transform, rgb);
+        let adapted = LinSrgb::new(
+            adapted[0].clamp(0.0, 1.0),
+            adapted[1].clamp(0.0, 1.0),
+            adapted[2].clamp(0.0, 1.0),
+        );
+        let encoded: Srgb<u8> = Srgb::from_linear(adapted).into_format();
+        px[0] = encoded.red;
+        px[1] = encoded.green;
+        px[2] = encoded.blue;
+    }
+}
+
+/// Adjust the white balance of an image by colour temperature and tint.
+///
+/// The temperature is that of the light the image was taken under: lower values correct
+/// for warm, tungsten-like light by cooling the image, and higher values warm it up.
+/// A temperature of 6500 with a tint of 0 leaves the image unchanged.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `temperature` - Colour temperature of the scene's light in Kelvin, from 1667 to 25000.
+/// * `tint` - Green/magenta correction, from -100 to 100. Positive values add magenta to
+/// correct for green light, negative values add green.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to correct an image taken under tungsten light:
+/// use photon_rs::colour_spaces::white_balance;
+/// use photon_rs::native::open_image;
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// white_balance(&mut img, 3200.0, 0.0);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn white_balance(photon_image: &mut PhotonImage, temperature: f32, tint: f32) {
+    let (x, y) = planckian_chromaticity(temperature);
+    let y = y + tint.clamp(-100.0, 100.0) * 0.0002;
+    let (dest_x, dest_y) = planckian_chromaticity(NEUTRAL_TEMPERATURE);
+
+    adapt_white(
+        photon_image,
+        white_from_chromaticity(x, y),
+        white_from_chromaticity(dest_x, dest_y),
+    );
+}
+
+/// Automatically correct a colour cast, by estimating the colour of the light in the scene.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `method` - How the colour of the light is estimated.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::colour_spaces::{auto_white_balance, WhiteBalanceMethod};
+/// use photon_rs::native::open_image;
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// auto_white_balance(&mut img, WhiteBalanceMethod::GrayWorld);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn auto_white_balance(photon_image: &mut PhotonImage, method: WhiteBalanceMethod) {
+    let linear: Vec<[f32; 3]> = photon_image
+        .raw_pixels
+        .chunks(4)
+        .filter(|px| px[3] > 0)
+        .map(|px| {
+            let rgb: LinSrgb =
+                Srgb::new(px[0], px[1], px[2]).into_format().into_linear();
+            [rgb.red, rgb.green, rgb.blue]
+        })
+        .collect();
+    if linear.is_empty() {
+        return;
+    }
+
+    let selected: Vec<&[f32; 3]> = match method {
+        WhiteBalanceMethod::GrayWorld => linear.iter().collect(),
+        WhiteBalanceMethod::WhitePatch => {
+            // Average the brightest 1% of pixels, so a single clipped highlight doesn't
+            // decide the result.
+            let mut by_luma: Vec<&[f32; 3]> = linear.iter().collect();
+            by_luma.sort_by(|a, b| {
+                let luma_a = 0.2126 * a[0] + 0.7152 * a[1] + 0.0722 * a[2];
+                let luma_b = 0.2126 * b[0] + 0.7152 * b[1] + 0.0722 * b[2];
+                luma_b.total_cmp(&luma_a)
+            });
+            let count = (by_luma.len() / 100).max(1);
+            by_luma.truncate(count);
+            by_luma
+        }
+    };
+
+    let mut sum = [0.0_f32; 3];
+    for rgb in &selected {
+        for chan in 0..3 {
+            sum[chan] += rgb[chan];
+        }
+    }
+    let average = sum.map(|val| val / selected.len() as f32);
+
+    if let (Some(source), Some(dest)) = (
+        white_from_linear_rgb(average),
+        white_from_linear_rgb([1.0, 1.0, 1.0]),
+    ) {
+        adapt_white(photon_image, source, dest);
+    }
+}
+
+/// Correct a colour cast by picking a colour in the image which should be neutral grey.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `neutral` - A colour sampled from an area of the image which should be grey or white.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::colour_spaces::white_balance_from_neutral;
+/// use photon_rs::native::open_image;
+/// use photon_rs::Rgb;
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// white_balance_from_neutral(&mut img, Rgb::new(190, 200, 220));
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn white_balance_from_neutral(photon_image: &mut PhotonImage, neutral: Rgb) {
+    let rgb: LinSrgb = Srgb::new(neutral.r, neutral.g, neutral.b)
+        .into_format()
+        .into_linear();
+
+    if let (Some(source), Some(dest)) = (
+        white_from_linear_rgb([rgb.red, rgb.green, rgb.blue]),
+        white_from_linear_rgb([1.0, 1.0, 1.0]),
+    ) {
+        adapt_white(photon_image, source, dest);
+    }
+}
@@ -1,9 +1,12 @@
 //! Convolution effects such as sharpening, blurs, sobel filters, etc.,
 
 use crate::helpers;
+use crate::monochrome::grayscale;
+use crate::storage::PhotonImageGray;
 use crate::PhotonImage;
 use image::DynamicImage::ImageRgba8;
 use image::{GenericImage, GenericImageView, Pixel};
+use serde::{Deserialize, Serialize};
 use std::cmp::min;
 
 #[cfg(feature = "enable_wasm")]
@@ -196,7 +199,7 @@ pub fn gaussian_blur(photon_image: &mut PhotonImage, radius: i32) {
     photon_image.raw_pixels = target;
 }
 
-fn boxes_for_gauss(sigma: f32, n: usize) -> Vec<i32> {
+pub(crate) fn boxes_for_gauss(sigma: f32, n: usize) -> Vec<i32> {
     let n_float = n as f32;
 
     let w_ideal = (12.0 * sigma * sigma / n_float).sqrt() + 1.0;
@@ -654,3 +657,60 @@ pub fn sobel_global(photon_image: &mut PhotonImage) {
 
     sobel_y.raw_pixels = sob_xy_values;
 }
+
+/// Edge detectors which `detect_edges_gray` can output as a compact single-channel image.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeDetector {
+    /// As `edge_detection`.
+    EdgeDetection = 1,
+    /// As `edge_one`.
+    EdgeOne = 2,
+    /// As `laplace`.
+    Laplace = 3,
+    /// As `sobel_horizontal`.
+    SobelHorizontal = 4,
+    /// As `sobel_vertical`.
+    SobelVertical = 5,
+    /// As `sobel_global`.
+    SobelGlobal = 6,
+    /// As `prewitt_horizontal`.
+    PrewittHorizontal = 7,
+}
+
+/// Detect edges in an image, returning a single-channel greyscale edge map.
+///
+/// The image is converted to greyscale before the edges are detected, so the edge map
+/// doesn't depend on hue.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `detector` - The edge detector to use.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::conv::{detect_edges_gray, EdgeDetector};
+/// use photon_rs::native::open_image;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let edges = detect_edges_gray(&img, EdgeDetector::SobelGlobal);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn detect_edges_gray(
+    photon_image: &PhotonImage,
+    detector: EdgeDetector,
+) -> PhotonImageGray {
+    let mut img = photon_image.clone();
+    grayscale(&mut img);
+    match detector {
+        EdgeDetector::EdgeDetection => edge_detection(&mut img),
+        EdgeDetector::EdgeOne => edge_one(&mut img),
+        EdgeDetector::Laplace => laplace(&mut img),
+        EdgeDetector::SobelHorizontal => sobel_horizontal(&mut img),
+        EdgeDetector::SobelVertical => sobel_vertical(&mut img),
+        EdgeDetector::SobelGlobal => sobel_global(&mut img),
+        EdgeDetector::PrewittHorizontal => prewitt_horizontal(&mut img),
+    }
+    PhotonImageGray::from_gray_rgba(&img)
+}
@@ -0,0 +1,504 @@
+//! Dithering: error diffusion, ordered (Bayer) and blue noise dithering to black and white,
+//! to a number of levels per channel, or to a palette.
+
+use crate::effects::{luminance, LuminanceFormula};
+use crate::quantize::Palette;
+use crate::PhotonImage;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+#[cfg(feature = "enable_wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Width and height of the generated blue noise threshold map.
+const BLUE_NOISE_SIZE: usize = 32;
+
+/// Standard deviation of the Gaussian filter used to find clusters and voids when
+/// generating blue noise.
+const BLUE_NOISE_SIGMA: f32 = 1.5;
+
+/// Dithering algorithm.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DitherAlgorithm {
+    /// Use the nearest colour, without dithering.
+    None = 1,
+    /// Floyd-Steinberg error diffusion.
+    FloydSteinberg = 2,
+    /// Atkinson error diffusion, which keeps more contrast but loses detail in shadows
+    /// and highlights.
+    Atkinson = 3,
+    /// Ordered dithering with a 4x4 Bayer matrix.
+    Bayer4 = 4,
+    /// Jarvis, Judice and Ninke error diffusion, smoother than Floyd-Steinberg.
+    JarvisJudiceNinke = 5,
+    /// Stucki error diffusion, a sharper variant of Jarvis, Judice and Ninke.
+    Stucki = 6,
+    /// Sierra (three row) error diffusion.
+    Sierra = 7,
+    /// Ordered dithering with a 2x2 Bayer matrix.
+    Bayer2 = 8,
+    /// Ordered dithering with an 8x8 Bayer matrix.
+    Bayer8 = 9,
+    /// Ordered dithering with a blue noise threshold map, without a visible pattern.
+    BlueNoise = 10,
+}
+
+/// Error diffusion kernel, as `(dx, dy, weight)` offsets from the current pixel, and the
+/// divisor of the weights.
+type Kernel = (&'static [(isize, usize, f32)], f32);
+
+const FLOYD_STEINBERG: Kernel =
+    (&[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)], 16.0);
+
+const ATKINSON: Kernel = (
+    &[
+        (1, 0, 1.0),
+        (2, 0, 1.0),
+        (-1, 1, 1.0),
+        (0, 1, 1.0),
+        (1, 1, 1.0),
+        (0, 2, 1.0),
+    ],
+    8.0,
+);
+
+const JARVIS_JUDICE_NINKE: Kernel = (
+    &[
+        (1, 0, 7.0),
+        (2, 0, 5.0),
+        (-2, 1, 3.0),
+        (-1, 1, 5.0),
+        (0, 1, 7.0),
+        (1, 1, 5.0),
+        (2, 1, 3.0),
+        (-2, 2, 1.0),
+        (-1, 2, 3.0),
+        (0, 2, 5.0),
+        (1, 2, 3.0),
+        (2, 2, 1.0),
+    ],
+    48.0,
+);
+
+const STUCKI: Kernel = (
+    &[
+        (1, 0, 8.0),
+        (2, 0, 4.0),
+        (-2, 1, 2.0),
+        (-1, 1, 4.0),
+        (0, 1, 8.0),
+        (1, 1, 4.0),
+        (2, 1, 2.0),
+        (-2, 2, 1.0),
+        (-1, 2, 2.0),
+        (0, 2, 4.0),
+        (1, 2, 2.0),
+        (2, 2, 1.0),
+    ],
+    42.0,
+);
+
+const SIERRA: Kernel = (
+    &[
+        (1, 0, 5.0),
+        (2, 0, 3.0),
+        (-2, 1, 2.0),
+        (-1, 1, 4.0),
+        (0, 1, 5.0),
+        (1, 1, 4.0),
+        (2, 1, 2.0),
+        (-1, 2, 2.0),
+        (0, 2, 3.0),
+        (1, 2, 2.0),
+    ],
+    32.0,
+);
+
+/// Options for dithering an image.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DitherOptions {
+    algorithm: DitherAlgorithm,
+    serpentine: bool,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl DitherOptions {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create options for the given algorithm, scanning every row left to right.
+    pub fn new(algorithm: DitherAlgorithm) -> DitherOptions {
+        DitherOptions {
+            algorithm,
+            serpentine: false,
+        }
+    }
+
+    /// Get the dithering algorithm.
+    pub fn get_algorithm(&self) -> DitherAlgorithm {
+        self.algorithm
+    }
+
+    /// Set whether error diffusion alternates direction on every row, which avoids the
+    /// diagonal artifacts of always scanning left to right.
+    pub fn set_serpentine(&mut self, serpentine: bool) {
+        self.serpentine = serpentine;
+    }
+}
+
+impl DitherAlgorithm {
+    fn kernel(&self) -> Option<Kernel> {
+        match self {
+            DitherAlgorithm::FloydSteinberg => Some(FLOYD_STEINBERG),
+            DitherAlgorithm::Atkinson => Some(ATKINSON),
+            DitherAlgorithm::JarvisJudiceNinke => Some(JARVIS_JUDICE_NINKE),
+            DitherAlgorithm::Stucki => Some(STUCKI),
+            DitherAlgorithm::Sierra => Some(SIERRA),
+            _ => None,
+        }
+    }
+
+    /// Threshold map for ordered dithering, with values centred on zero from -0.5 to 0.5,
+    /// and its size.
+    pub(crate) fn threshold_map(&self) -> Option<(&'static [f32], usize)> {
+        static BAYER_2: OnceLock<Vec<f32>> = OnceLock::new();
+        static BAYER_4: OnceLock<Vec<f32>> = OnceLock::new();
+        static BAYER_8: OnceLock<Vec<f32>> = OnceLock::new();
+        static BLUE_NOISE: OnceLock<Vec<f32>> = OnceLock::new();
+        match self {
+            DitherAlgorithm::Bayer2 => {
+                Some((BAYER_2.get_or_init(|| bayer_matrix(2)), 2))
+            }
+            DitherAlgorithm::Bayer4 => {
+                Some((BAYER_4.get_or_init(|| bayer_matrix(4)), 4))
+            }
+            DitherAlgorithm::Bayer8 => {
+                Some((BAYER_8.get_or_init(|| bayer_matrix(8)), 8))
+            }
+            DitherAlgorithm::BlueNoise => {
+                Some((BLUE_NOISE.get_or_init(blue_noise), BLUE_NOISE_SIZE))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Normalise ranks from 0 to `len - 1` into thresholds from -0.5 to 0.5.
+fn thresholds_from_ranks(ranks: &[usize]) -> Vec<f32> {
+    let len = ranks.len() as f32;
+    ranks
+        .iter()
+        .map(|rank| (*rank as f32 + 0.5) / len - 0.5)
+        .collect()
+}
+
+/// Bayer matrix of a power of two size, built recursively from the 2x2 matrix.
+fn bayer_matrix(size: usize) -> Vec<f32> {
+    let mut matrix = vec![0_usize];
+    let mut current = 1;
+    while current < size {
+        let next = current * 2;
+        let mut expanded = vec![0; next * next];
+        for y in 0..next {
+            for x in 0..next {
+                let base = matrix[(y % current) * current + x % current] * 4;
+                let offset = match (x / current, y / current) {
+                    (0, 0) => 0,
+                    (1, 0) => 2,
+                    (0, _) => 3,
+                    _ => 1,
+                };
+                expanded[y * next + x] = base + offset;
+            }
+        }
+        matrix = expanded;
+        current = next;
+    }
+    thresholds_from_ranks(&matrix)
+}
+
+/// Blue noise threshold map, generated with Ulichney's void-and-cluster method.
+fn blue_noise() -> Vec<f32> {
+    const SIZE: usize = BLUE_NOISE_SIZE;
+    const AREA: usize = SIZE * SIZE;
+
+    // Gaussian weight for every toroidal offset between two cells.
+    let mut kernel = vec![0.0_f32; AREA];
+    for dy in 0..SIZE {
+        for dx in 0..SIZE {
+            let wrap_x = dx.min(SIZE - dx) as f32;
+            let wrap_y = dy.min(SIZE - dy) as f32;
+            let dist = wrap_x * wrap_x + wrap_y * wrap_y;
+            kernel[dy * SIZE + dx] = (-dist / (2.0 * BLUE_NOISE_SIGMA.powi(2))).exp();
+        }
+    }
+    let update = |energy: &mut [f32], cell: usize, sign: f32| {
+        let (cx, cy) = (cell % SIZE, cell / SIZE);
+        for (idx, val) in energy.iter_mut().enumerate() {
+            let (x, y) = (idx % SIZE, idx / SIZE);
+            let offset = ((y + SIZE - cy) % SIZE) * SIZE + (x + SIZE - cx) % SIZE;
+            *val += sign * kernel[offset];
+        }
+    };
+    let energy_of = |pattern: &[bool]| {
+        let mut energy = vec![0.0; AREA];
+        for (cell, _) in pattern.iter().enumerate().filter(|(_, set)| **set) {
+            update(&mut energy, cell, 1.0);
+        }
+        energy
+    };
+    // The set cell in the tightest cluster, or the unset cell in the largest void.
+    let extreme = |pattern: &[bool], energy: &[f32], set: bool| {
+        let cells = (0..AREA).filter(|cell| pattern[*cell] == set);
+        if set {
+            cells.max_by(|a, b| energy[*a].total_cmp(&energy[*b]))
+        } else {
+            cells.min_by(|a, b| energy[*a].total_cmp(&energy[*b]))
+        }
+        .unwrap()
+    };
+
+    // Start from a tenth of the cells set by a simple generator, then move points from
+    // clusters into voids until they are evenly spread.
+    let mut pattern = vec![false; AREA];
+    let mut seed: u32 = 0x9E37_79B9;
+    let initial = AREA / 10;
+    let mut placed = 0;
+    while placed < initial {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        let cell = (seed >> 8) as usize % AREA;
+        if !pattern[cell] {
+            pattern[cell] = true;
+            placed += 1;
+        }
+    }
+    let mut energy = energy_of(&pattern);
+    loop {
+        let cluster = extreme(&pattern, &energy, true);
+        pattern[cluster] = false;
+        update(&mut energy, cluster, -1.0);
+        let void = extreme(&pattern, &energy, false);
+        pattern[void] = true;
+        update(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; AREA];
+    // Rank the initial points by removing the tightest clusters first.
+    let mut removing = pattern.clone();
+    let mut removing_energy = energy.clone();
+    for rank in (0..initial).rev() {
+        let cluster = extreme(&removing, &removing_energy, true);
+        removing[cluster] = false;
+        update(&mut removing_energy, cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+    // Fill the largest voids up to half of the cells.
+    for rank in initial..AREA / 2 {
+        let void = extreme(&pattern, &energy, false);
+        pattern[void] = true;
+        update(&mut energy, void, 1.0);
+        ranks[void] = rank;
+    }
+    // Then fill the tightest clusters of the remaining unset cells.
+    let mut inverted: Vec<bool> = pattern.iter().map(|set| !set).collect();
+    let mut inverted_energy = energy_of(&inverted);
+    for rank in AREA / 2..AREA {
+        let cluster = extreme(&inverted, &inverted_energy, true);
+        inverted[cluster] = false;
+        update(&mut inverted_energy, cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+
+    thresholds_from_ranks(&ranks)
+}
+
+/// Dither a buffer of colours in place, covering every pixel.
+///
+/// `spread` is the gap between the colours being quantized to, which scales ordered
+/// dithering thresholds. `quantize` is called with the index of each pixel and its
+/// colour plus any diffused error, and returns the colour the pixel becomes.
+pub(crate) fn dither_buffer<F>(
+    colours: &mut [[f32; 3]],
+    width: usize,
+    height: usize,
+    options: &DitherOptions,
+    spread: f32,
+    mut quantize: F,
+) where
+    F: FnMut(usize, [f32; 3]) -> [f32; 3],
+{
+    let kernel = options.algorithm.kernel();
+    let threshold_map = options.algorithm.threshold_map();
+
+    for y in 0..height {
+        let reverse = options.serpentine && y % 2 == 1;
+        for step in 0..width {
+            let x = if reverse { width - 1 - step } else { step };
+            let idx = y * width + x;
+            let mut colour = colours[idx].map(|val| val.clamp(0.0, 255.0));
+            if let Some((map, size)) = threshold_map {
+                let threshold = map[(y % size) * size + x % size] * spread;
+                colour = colour.map(|val| val + threshold);
+            }
+
+            let quantized = quantize(idx, colour);
+            colours[idx] = quantized;
+
+            let Some((offsets, divisor)) = kernel else {
+                continue;
+            };
+            for (dx, dy, weight) in offsets {
+                let dx = if reverse { -dx } else { *dx };
+                let (nx, ny) = (x as isize + dx, y + dy);
+                if nx < 0 || nx >= width as isize || ny >= height {
+                    continue;
+                }
+                let target = &mut colours[ny * width + nx as usize];
+                for chan in 0..3 {
+                    target[chan] += (colour[chan] - quantized[chan]) * weight / divisor;
+                }
+            }
+        }
+    }
+}
+
+/// The RGB channels of an image as floats.
+fn colours_of(photon_image: &PhotonImage) -> Vec<[f32; 3]> {
+    photon_image
+        .raw_pixels
+        .chunks_exact(4)
+        .map(|px| [px[0] as f32, px[1] as f32, px[2] as f32])
+        .collect()
+}
+
+/// Write dithered colours back into an image, leaving alpha unchanged.
+fn write_colours(photon_image: &mut PhotonImage, colours: &[[f32; 3]]) {
+    for (px, colour) in photon_image.raw_pixels.chunks_exact_mut(4).zip(colours) {
+        for chan in 0..3 {
+            px[chan] = colour[chan].round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Dither an image to black and white, by its luminance.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `options` - The dithering algorithm and scanning order.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::dither::{dither_monochrome, DitherAlgorithm, DitherOptions};
+/// use photon_rs::native::open_image;
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// dither_monochrome(&mut img, &DitherOptions::new(DitherAlgorithm::Atkinson));
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn dither_monochrome(photon_image: &mut PhotonImage, options: &DitherOptions) {
+    let mut colours: Vec<[f32; 3]> = photon_image
+        .raw_pixels
+        .chunks_exact(4)
+        .map(|px| [luminance(px[0], px[1], px[2], LuminanceFormula::Rec709); 3])
+        .collect();
+    let (width, height) = (photon_image.width as usize, photon_image.height as usize);
+    dither_buffer(&mut colours, width, height, options, 255.0, |_, colour| {
+        [if colour[0] < 127.5 { 0.0 } else { 255.0 }; 3]
+    });
+    write_colours(photon_image, &colours);
+}
+
+/// Dither each RGB channel of an image independently to a number of evenly spaced levels.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `levels` - Number of levels per channel, from 2 to 256. 2 gives 8 colours.
+/// * `options` - The dithering algorithm and scanning order.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::dither::{dither_channels, DitherAlgorithm, DitherOptions};
+/// use photon_rs::native::open_image;
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// let mut options = DitherOptions::new(DitherAlgorithm::JarvisJudiceNinke);
+/// options.set_serpentine(true);
+/// dither_channels(&mut img, 4, &options);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn dither_channels(
+    photon_image: &mut PhotonImage,
+    levels: u32,
+    options: &DitherOptions,
+) {
+    let step = 255.0 / (levels.clamp(2, 256) - 1) as f32;
+    let mut colours = colours_of(photon_image);
+    let (width, height) = (photon_image.width as usize, photon_image.height as usize);
+    dither_buffer(&mut colours, width, height, options, step, |_, colour| {
+        colour.map(|val| (val / step).round() * step)
+    });
+    write_colours(photon_image, &colours);
+}
+
+/// Dither an image to the colours of a palette. Alpha is unchanged.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `palette` - The palette, generated by `quantize::generate_palette` or fixed.
+/// * `options` - The dithering algorithm and scanning order.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::dither::{dither_palette, DitherAlgorithm, DitherOptions};
+/// use photon_rs::native::open_image;
+/// use photon_rs::quantize::Palette;
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// dither_palette(&mut img, &Palette::cga(), &DitherOptions::new(DitherAlgorithm::BlueNoise));
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn dither_palette(
+    photon_image: &mut PhotonImage,
+    palette: &Palette,
+    options: &DitherOptions,
+) {
+    if palette.is_empty() {
+        return;
+    }
+    let indices = palette_indices(photon_image, palette, options);
+    for (px, idx) in photon_image.raw_pixels.chunks_exact_mut(4).zip(indices) {
+        px[..3].copy_from_slice(&palette.entries()[idx]);
+    }
+}
+
+/// Palette index of every pixel of an image, dithered with the given options.
+pub(crate) fn palette_indices(
+    photon_image: &PhotonImage,
+    palette: &Palette,
+    options: &DitherOptions,
+) -> Vec<usize> {
+    // Ordered dithering spreads thresholds over the average gap between palette colours.
+    let spread = 255.0 / (palette.len() as f32).cbrt().max(1.0);
+    let mut colours = colours_of(photon_image);
+    let mut indices = vec![0; colours.len()];
+    let (width, height) = (photon_image.width as usize, photon_image.height as usize);
+    dither_buffer(
+        &mut colours,
+        width,
+        height,
+        options,
+        spread,
+        |idx, colour| {
+            let nearest = palette.nearest(colour);
+            indices[idx] = nearest;
+            palette.entries()[nearest].map(|val| val as f32)
+        },
+    );
+    indices
+}
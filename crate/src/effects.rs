@@ -1,7 +1,11 @@
 //! Special effects.
 
+use crate::conv::gaussian_blur;
+use crate::dither::{dither_channels, DitherAlgorithm, DitherOptions};
+use crate::gradient::{gradient_lut, ColorStop, GradientInterpolation};
 use crate::helpers;
 use crate::iter::ImageIterator;
+use crate::storage::PhotonImageF32;
 use crate::{PhotonImage, Rgb};
 use image::Pixel;
 use image::Rgba;
@@ -9,6 +13,7 @@ use image::{GenericImage, GenericImageView};
 use imageproc::drawing::draw_filled_rect_mut;
 use imageproc::rect::Rect;
 use perlin2d::PerlinNoise2D;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::f64;
 
@@ -641,6 +646,30 @@ pub fn dec_brightness(photon_image: &mut PhotonImage, brightness: u8) {
     }
 }
 
+/// Increase or decrease the brightness of a floating-point image by a constant.
+///
+/// # Arguments
+/// * `img` - A PhotonImageF32.
+/// * `brightness` - Amount to add to every channel, where 1 is full brightness.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::native::open_image_f32;
+/// use photon_rs::effects::adjust_brightness_f32;
+///
+/// let mut img = open_image_f32("img.png").expect("File should open");
+/// adjust_brightness_f32(&mut img, 0.1_f32);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn adjust_brightness_f32(img: &mut PhotonImageF32, brightness: f32) {
+    for px in img.raw_pixels.chunks_mut(4) {
+        for val in px.iter_mut().take(3) {
+            *val += brightness;
+        }
+    }
+}
+
 /// Adjust the contrast of an image by a factor.
 ///
 /// # Arguments
@@ -660,18 +689,11 @@ pub fn dec_brightness(photon_image: &mut PhotonImage, brightness: u8) {
 pub fn adjust_contrast(photon_image: &mut PhotonImage, contrast: f32) {
     let mut img = helpers::dyn_image_from_raw(photon_image);
 
-    let clamped_contrast = contrast.clamp(-255.0, 255.0);
-
-    // Some references:
-    // https://math.stackexchange.com/questions/906240/algorithms-to-increase-or-decrease-the-contrast-of-an-image
-    // https://www.dfstudios.co.uk/articles/programming/image-programming-algorithms/image-processing-algorithms-part-5-contrast-adjustment/
-    let factor =
-        (259.0 * (clamped_contrast + 255.0)) / (255.0 * (259.0 - clamped_contrast));
+    let factor = contrast_factor(contrast);
     let mut lookup_table: Vec<u8> = vec![0; 256];
-    let offset = -128.0 * factor + 128.0;
 
     for (i, table) in lookup_table.iter_mut().enumerate().take(256_usize) {
-        let new_val = i as f32 * factor + offset;
+        let new_val = apply_contrast(i as f32, factor);
         *table = new_val.clamp(0.0, 255.0) as u8;
     }
 
@@ -691,6 +713,48 @@ pub fn adjust_contrast(photon_image: &mut PhotonImage, contrast: f32) {
     photon_image.raw_pixels = img.into_bytes();
 }
 
+/// The factor `adjust_contrast` scales channel values by, for a contrast between -255 and 255.
+fn contrast_factor(contrast: f32) -> f32 {
+    let clamped_contrast = contrast.clamp(-255.0, 255.0);
+
+    // Some references:
+    // https://math.stackexchange.com/questions/906240/algorithms-to-increase-or-decrease-the-contrast-of-an-image
+    // https://www.dfstudios.co.uk/articles/programming/image-programming-algorithms/image-processing-algorithms-part-5-contrast-adjustment/
+    (259.0 * (clamped_contrast + 255.0)) / (255.0 * (259.0 - clamped_contrast))
+}
+
+/// Scale a channel value from 0 to 255 away from or towards the middle grey.
+fn apply_contrast(val: f32, factor: f32) -> f32 {
+    (val - 128.0) * factor + 128.0
+}
+
+/// Adjust the contrast of a floating-point image, with the same scale as
+/// `effects::adjust_contrast`.
+///
+/// # Arguments
+/// * `img` - A PhotonImageF32.
+/// * `contrast` - An f32 factor used to adjust contrast, between [-255.0, 255.0].
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::native::open_image_f32;
+/// use photon_rs::effects::adjust_contrast_f32;
+///
+/// let mut img = open_image_f32("img.png").expect("File should open");
+/// adjust_contrast_f32(&mut img, 30_f32);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn adjust_contrast_f32(img: &mut PhotonImageF32, contrast: f32) {
+    let factor = contrast_factor(contrast);
+
+    for px in img.raw_pixels.chunks_mut(4) {
+        for val in px.iter_mut().take(3) {
+            *val = apply_contrast(*val * 255.0, factor) / 255.0;
+        }
+    }
+}
+
 /// Tint an image by adding an offset to averaged RGB channel values.
 ///
 /// # Arguments
@@ -749,6 +813,32 @@ pub fn tint(
     photon_image.raw_pixels = raw_pixels;
 }
 
+/// Tint a floating-point image by adding an offset to each RGB channel.
+///
+/// # Arguments
+/// * `img` - A PhotonImageF32.
+/// * `r_offset` - The amount the R channel should be incremented by, where 1 is full brightness.
+/// * `g_offset` - The amount the G channel should be incremented by.
+/// * `b_offset` - The amount the B channel should be incremented by.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::effects::tint_f32;
+/// use photon_rs::native::open_image_f32;
+///
+/// let mut img = open_image_f32("img.png").expect("File should open");
+/// tint_f32(&mut img, 0.04_f32, 0.08_f32, 0.06_f32);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn tint_f32(img: &mut PhotonImageF32, r_offset: f32, g_offset: f32, b_offset: f32) {
+    for px in img.raw_pixels.chunks_mut(4) {
+        for (val, offset) in px.iter_mut().zip([r_offset, g_offset, b_offset]) {
+            *val += offset;
+        }
+    }
+}
+
 fn draw_horizontal_strips(photon_image: &mut PhotonImage, num_strips: u8, color: Rgb) {
     let mut img = helpers::dyn_image_from_raw(photon_image);
     let (width, height) = img.dimensions();
@@ -1171,6 +1261,7 @@ pub fn normalize(photon_image: &mut PhotonImage) {
 
 /// Applies Floyd-Steinberg dithering to an image.
 /// Only RGB channels are processed, alpha remains unchanged.
+/// See the `dither` module for other algorithms, and black and white or palette dithering.
 /// # Arguments
 /// * `photon_image` - A PhotonImage that contains a view into the image.
 /// * `depth` - bits per channel. Clamped between 1 and 8.
@@ -1188,62 +1279,14 @@ pub fn normalize(photon_image: &mut PhotonImage) {
 ///
 #[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
 pub fn dither(photon_image: &mut PhotonImage, depth: u32) {
-    let width = photon_image.get_width() as usize;
-    let height = photon_image.get_height() as usize;
-    let buf = photon_image.raw_pixels.as_mut_slice();
-    let channels = 4;
-    let chan_stride = 1;
-    let col_stride = chan_stride * channels;
-    let row_stride = col_stride * width;
-
-    // Depth basically specifies the number of colours, e.g. when depth is 1,
-    // than means monochrome values in each channel:
-    // Number of colours = 2 ^ depth = 2 ^ 1 = 2
-    // In order to resample pixel, the original value must be downscaled to [0..colours] range
-    // (divide by the quant rate) and then upscaled back to [0..255] (multiply by the rate).
-    let depth = depth.clamp(1, 8);
-    let num_colours = u16::pow(2, depth);
-    let quant_rate = (256_u16 / num_colours) as u8;
-    let mut lookup_table: Vec<u8> = vec![0; 256];
-    for (tbl_idx, table) in lookup_table.iter_mut().enumerate().take(256_usize) {
-        let downscaled_val = (tbl_idx as u8) / quant_rate;
-        let upscaled_val = downscaled_val * quant_rate;
-        *table = upscaled_val.clamp(0, 255);
-    }
-
-    for row in 0..height - 1 {
-        for col in 0..width - 1 {
-            for chan in 0..channels - 1 {
-                let buf_idx = row * row_stride + col * col_stride + chan * chan_stride;
-                let old_pixel = buf[buf_idx];
-                let new_pixel = lookup_table[old_pixel as usize];
-
-                buf[buf_idx] = new_pixel;
-
-                let quant_error = (old_pixel as i16) - (new_pixel as i16);
-
-                let buf_idx =
-                    row * row_stride + (col + 1) * col_stride + chan * chan_stride;
-                let new_pixel = (buf[buf_idx] as i16) + (quant_error * 7) / 16;
-                buf[buf_idx] = new_pixel.clamp(0, 255) as u8;
-
-                let buf_idx = (row + 1) * row_stride + col * col_stride - col_stride
-                    + chan * chan_stride;
-                let new_pixel = (buf[buf_idx] as i16) + (quant_error * 3) / 16;
-                buf[buf_idx] = new_pixel.clamp(0, 255) as u8;
-
-                let buf_idx =
-                    (row + 1) * row_stride + col * col_stride + chan * chan_stride;
-                let new_pixel = (buf[buf_idx] as i16) + (quant_error * 5) / 16;
-                buf[buf_idx] = new_pixel.clamp(0, 255) as u8;
-
-                let buf_idx =
-                    (row + 1) * row_stride + (col + 1) * col_stride + chan * chan_stride;
-                let new_pixel = (buf[buf_idx] as i16) + quant_error / 16;
-                buf[buf_idx] = new_pixel.clamp(0, 255) as u8;
-            }
-        }
-    }
+    // Depth specifies the number of levels per channel, e.g. when depth is 1, each
+    // channel is either 0 or 255: number of levels = 2 ^ depth = 2 ^ 1 = 2
+    let levels = 1 << depth.clamp(1, 8);
+    dither_channels(
+        photon_image,
+        levels,
+        &DitherOptions::new(DitherAlgorithm::FloydSteinberg),
+    );
 }
 
 fn create_gradient_map(color_a: Rgb, color_b: Rgb) -> Vec<Rgb> {
@@ -1281,3 +1324,269 @@ pub fn duotone(photon_image: &mut PhotonImage, color_a: Rgb, color_b: Rgb) {
         px[2] = mapped_luma.b;
     }
 }
+
+/// Formula used to compute the luminance of a pixel.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LuminanceFormula {
+    /// ITU-R BT.709 (sRGB/HDTV) weights: 0.2126 R + 0.7152 G + 0.0722 B.
+    Rec709 = 1,
+    /// ITU-R BT.601 (SDTV) weights: 0.299 R + 0.587 G + 0.114 B.
+    Rec601 = 2,
+    /// Plain average of the R, G and B channels.
+    Average = 3,
+    /// HSL lightness, the mean of the largest and smallest channel.
+    Lightness = 4,
+}
+
+/// Compute the luminance of an RGB triplet with the given formula.
+pub(crate) fn luminance(r: u8, g: u8, b: u8, formula: LuminanceFormula) -> f32 {
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    match formula {
+        LuminanceFormula::Rec709 => 0.2126 * r + 0.7152 * g + 0.0722 * b,
+        LuminanceFormula::Rec601 => 0.299 * r + 0.587 * g + 0.114 * b,
+        LuminanceFormula::Average => (r + g + b) / 3.0,
+        LuminanceFormula::Lightness => (r.max(g).max(b) + r.min(g).min(b)) / 2.0,
+    }
+}
+
+/// Built-in gradient maps.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GradientMapPreset {
+    /// Perceptually uniform dark purple to yellow map, from matplotlib.
+    Viridis = 1,
+    /// Perceptually uniform black to pale yellow map through purple and orange, from matplotlib.
+    Inferno = 2,
+    /// "Ironbow" style map used by thermal cameras.
+    Thermal = 3,
+}
+
+/// Get the colour stops of a built-in gradient map.
+///
+/// # Arguments
+/// * `preset` - The built-in gradient map.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn gradient_map_stops(preset: GradientMapPreset) -> Vec<ColorStop> {
+    let hex_colors: &[u32] = match preset {
+        GradientMapPreset::Viridis => &[
+            0x440154, 0x472d7b, 0x3b528b, 0x2c728e, 0x21918c, 0x28ae80, 0x5ec962,
+            0xaddc30, 0xfde725,
+        ],
+        GradientMapPreset::Inferno => &[
+            0x000004, 0x1b0c41, 0x4a0c6b, 0x781c6d, 0xa52c60, 0xcf4446, 0xed6925,
+            0xfb9b06, 0xfcffa4,
+        ],
+        GradientMapPreset::Thermal => &[
+            0x000000, 0x1e0a5a, 0x5a0a8c, 0xa01e78, 0xd2343c, 0xf06e0a, 0xfaaa00,
+            0xffe050, 0xffffe6,
+        ],
+    };
+    let last = (hex_colors.len() - 1) as f32;
+
+    hex_colors
+        .iter()
+        .enumerate()
+        .map(|(i, hex)| {
+            let color =
+                crate::Rgba::new((hex >> 16) as u8, (hex >> 8) as u8, *hex as u8, 255);
+            ColorStop::new(i as f32 / last, color)
+        })
+        .collect()
+}
+
+/// Map the luminance of each pixel through a gradient with any number of colour stops.
+///
+/// Dark pixels take the colour of the first stop and bright pixels that of the last one,
+/// which allows tritone, false colour and thermal-camera looks. The alpha of a stop scales
+/// how strongly its colour is applied, and the alpha channel of the image is left untouched.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage that contains a view into the image.
+/// * `stops` - The colour stops of the gradient. Without any stops the image is left unchanged.
+/// * `interpolation` - The colour space neighbouring stops are mixed in.
+/// * `formula` - The formula used to compute the luminance of a pixel.
+/// * `opacity` - How much of the mapped colour is mixed with the original, from 0 to 1.
+/// # Example
+///
+/// ```no_run
+/// // For example, to apply a blue, magenta and yellow tritone:
+/// use photon_rs::effects::{gradient_map, LuminanceFormula};
+/// use photon_rs::gradient::{ColorStop, GradientInterpolation};
+/// use photon_rs::native::open_image;
+/// use photon_rs::Rgba;
+///
+/// let stops = vec![
+///     ColorStop::new(0.0, Rgba::new(20, 30, 120, 255)),
+///     ColorStop::new(0.5, Rgba::new(200, 40, 140, 255)),
+///     ColorStop::new(1.0, Rgba::new(255, 230, 90, 255)),
+/// ];
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// gradient_map(&mut img, stops, GradientInterpolation::Lab, LuminanceFormula::Rec709, 1.0);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn gradient_map(
+    photon_image: &mut PhotonImage,
+    stops: Vec<ColorStop>,
+    interpolation: GradientInterpolation,
+    formula: LuminanceFormula,
+    opacity: f32,
+) {
+    let lut = gradient_lut(&stops, interpolation, 256);
+    let opacity = opacity.clamp(0.0, 1.0);
+
+    for px in photon_image.raw_pixels.chunks_mut(4) {
+        let luma = luminance(px[0], px[1], px[2], formula)
+            .round()
+            .clamp(0.0, 255.0);
+        let mapped = &lut[luma as usize];
+        let amount = opacity * mapped[3] / 255.0;
+
+        for (chan, mapped_val) in px.iter_mut().zip(mapped.iter()).take(3) {
+            let val = *chan as f32 + (mapped_val - *chan as f32) * amount;
+            *chan = val.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Map the luminance of each pixel through one of the built-in gradient maps.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage that contains a view into the image.
+/// * `preset` - The built-in gradient map, such as viridis, inferno or thermal.
+/// * `opacity` - How much of the mapped colour is mixed with the original, from 0 to 1.
+/// # Example
+///
+/// ```no_run
+/// // For example, to give an image a thermal camera look:
+/// use photon_rs::effects::{gradient_map_preset, GradientMapPreset};
+/// use photon_rs::native::open_image;
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// gradient_map_preset(&mut img, GradientMapPreset::Thermal, 1.0);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn gradient_map_preset(
+    photon_image: &mut PhotonImage,
+    preset: GradientMapPreset,
+    opacity: f32,
+) {
+    gradient_map(
+        photon_image,
+        gradient_map_stops(preset),
+        GradientInterpolation::Srgb,
+        LuminanceFormula::Rec709,
+        opacity,
+    );
+}
+
+/// Rec. 709 luminance of every pixel, from 0 to 1, blurred with a Gaussian of the given radius.
+fn blurred_luminance(photon_image: &PhotonImage, radius: i32) -> Vec<f32> {
+    let mut mask_pixels = Vec::with_capacity(photon_image.raw_pixels.len());
+    for px in photon_image.raw_pixels.chunks(4) {
+        let luma = luminance(px[0], px[1], px[2], LuminanceFormula::Rec709)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+        mask_pixels.extend_from_slice(&[luma, luma, luma, 255]);
+    }
+    let mut mask =
+        PhotonImage::new(mask_pixels, photon_image.width, photon_image.height);
+
+    // Images too small for the requested radius are used unblurred.
+    let max_radius = (photon_image.width.min(photon_image.height) / 2) as i32 - 1;
+    if radius.min(max_radius) >= 1 {
+        gaussian_blur(&mut mask, radius);
+    }
+
+    mask.raw_pixels
+        .chunks(4)
+        .map(|px| px[0] as f32 / 255.0)
+        .collect()
+}
+
+/// Recover detail in the shadows and highlights of an image.
+///
+/// Shadows and highlights are found with a blurred luminance mask, so whole regions are
+/// brightened or darkened together rather than flattening contrast within them.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `shadows` - How much to brighten dark regions, from -1 to 1. Negative values deepen them.
+/// * `highlights` - How much to darken bright regions, from -1 to 1. Negative values
+/// brighten them.
+/// * `radius` - Blur radius of the mask. Around 2% of the image width works well.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::effects::shadows_highlights;
+/// use photon_rs::native::open_image;
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// shadows_highlights(&mut img, 0.5_f32, 0.3_f32, 20_i32);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn shadows_highlights(
+    photon_image: &mut PhotonImage,
+    shadows: f32,
+    highlights: f32,
+    radius: i32,
+) {
+    let shadows = shadows.clamp(-1.0, 1.0) * 0.5;
+    let highlights = highlights.clamp(-1.0, 1.0) * 0.5;
+    let mask = blurred_luminance(photon_image, radius);
+
+    for (px, mask_val) in photon_image.raw_pixels.chunks_mut(4).zip(mask) {
+        let luma = luminance(px[0], px[1], px[2], LuminanceFormula::Rec709) / 255.0;
+        let shadow_weight = (1.0 - mask_val) * (1.0 - mask_val);
+        let highlight_weight = mask_val * mask_val;
+
+        let lifted = luma + shadows * shadow_weight * (1.0 - luma);
+        let new_luma = (lifted - highlights * highlight_weight * lifted).clamp(0.0, 1.0);
+
+        for chan in px.iter_mut().take(3) {
+            let val = if luma > 1.0 / 255.0 {
+                *chan as f32 * new_luma / luma
+            } else {
+                *chan as f32 + (new_luma - luma) * 255.0
+            };
+            *chan = val.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Adjust the local contrast of an image, bringing out texture without changing its
+/// overall contrast.
+///
+/// The effect is strongest in the midtones, so shadows and highlights don't clip.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `amount` - Strength of the effect, from -1 (soft) to 1 (crisp).
+/// * `radius` - Size of the details to enhance, as a blur radius. Around 1% of the image
+/// width works well.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::effects::clarity;
+/// use photon_rs::native::open_image;
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// clarity(&mut img, 0.4_f32, 10_i32);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn clarity(photon_image: &mut PhotonImage, amount: f32, radius: i32) {
+    let amount = amount.clamp(-1.0, 1.0);
+    let mask = blurred_luminance(photon_image, radius);
+
+    for (px, blurred) in photon_image.raw_pixels.chunks_mut(4).zip(mask) {
+        let luma = luminance(px[0], px[1], px[2], LuminanceFormula::Rec709) / 255.0;
+        let midtones = 4.0 * luma * (1.0 - luma);
+        let delta = amount * (luma - blurred) * midtones * 255.0;
+
+        for chan in px.iter_mut().take(3) {
+            *chan = (*chan as f32 + delta).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
@@ -0,0 +1,407 @@
+//! Encoding images to PNG, JPEG, WebP, GIF, BMP, TIFF and ICO, with per-format options.
+
+use crate::PhotonImage;
+use image::codecs::bmp::BmpEncoder;
+use image::codecs::gif::GifEncoder;
+use image::codecs::ico::IcoEncoder;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::codecs::tiff::TiffEncoder;
+use image::{ColorType, ImageEncoder};
+use jpeg_encoder::SamplingFactor;
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Write};
+use std::path::Path;
+use thiserror::Error;
+
+#[cfg(feature = "enable_wasm")]
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Error)]
+pub enum EncodeError {
+    #[error(transparent)]
+    ImageError(#[from] image::ImageError),
+
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    JpegError(#[from] jpeg_encoder::EncodingError),
+
+    #[error("WebP encoding failed: {0}")]
+    WebPError(String),
+
+    #[error("{0}")]
+    Unsupported(String),
+}
+
+#[cfg(feature = "enable_wasm")]
+impl From<EncodeError> for JsValue {
+    fn from(err: EncodeError) -> JsValue {
+        JsError::new(&err.to_string()).into()
+    }
+}
+
+/// Image file format to encode to.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png = 1,
+    Jpeg = 2,
+    WebP = 3,
+    Gif = 4,
+    Bmp = 5,
+    Tiff = 6,
+    /// Icons can be at most 256x256 pixels.
+    Ico = 7,
+}
+
+impl OutputFormat {
+    /// Guess the format from the extension of a path, such as `.jpg`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<OutputFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(OutputFormat::Png),
+            "jpg" | "jpeg" | "jpe" | "jfif" => Some(OutputFormat::Jpeg),
+            "webp" => Some(OutputFormat::WebP),
+            "gif" => Some(OutputFormat::Gif),
+            "bmp" => Some(OutputFormat::Bmp),
+            "tif" | "tiff" => Some(OutputFormat::Tiff),
+            "ico" => Some(OutputFormat::Ico),
+            _ => None,
+        }
+    }
+
+    /// The MIME type of the format, such as `image/png`.
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::WebP => "image/webp",
+            OutputFormat::Gif => "image/gif",
+            OutputFormat::Bmp => "image/bmp",
+            OutputFormat::Tiff => "image/tiff",
+            OutputFormat::Ico => "image/x-icon",
+        }
+    }
+}
+
+/// Trade-off between PNG file size and encoding speed.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PngCompression {
+    Default = 1,
+    Fast = 2,
+    Best = 3,
+}
+
+/// Filter applied to each row of a PNG before compression.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PngFilter {
+    NoFilter = 1,
+    Sub = 2,
+    Up = 3,
+    Avg = 4,
+    Paeth = 5,
+    /// Choose the best filter for each row.
+    Adaptive = 6,
+}
+
+/// Resolution of the colour channels of a JPEG, relative to its brightness.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChromaSubsampling {
+    /// Full colour resolution.
+    Yuv444 = 1,
+    /// Half horizontal colour resolution.
+    Yuv422 = 2,
+    /// Half horizontal and vertical colour resolution, the smallest files.
+    Yuv420 = 3,
+}
+
+/// Options for encoding an image. Each option only affects its own format.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EncodeOptions {
+    png_compression: PngCompression,
+    png_filter: PngFilter,
+    jpeg_quality: u8,
+    jpeg_progressive: bool,
+    jpeg_subsampling: ChromaSubsampling,
+    webp_quality: Option<f32>,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions::new()
+    }
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl EncodeOptions {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create options with default PNG compression, baseline 4:2:0 JPEGs at quality 90
+    /// and lossless WebP.
+    pub fn new() -> EncodeOptions {
+        EncodeOptions {
+            png_compression: PngCompression::Default,
+            png_filter: PngFilter::Adaptive,
+            jpeg_quality: 90,
+            jpeg_progressive: false,
+            jpeg_subsampling: ChromaSubsampling::Yuv420,
+            webp_quality: None,
+        }
+    }
+
+    /// Set the PNG compression level.
+    pub fn set_png_compression(&mut self, compression: PngCompression) {
+        self.png_compression = compression;
+    }
+
+    /// Set the PNG row filter.
+    pub fn set_png_filter(&mut self, filter: PngFilter) {
+        self.png_filter = filter;
+    }
+
+    /// Set the JPEG quality, from 1 to 100.
+    pub fn set_jpeg_quality(&mut self, quality: u8) {
+        self.jpeg_quality = quality.clamp(1, 100);
+    }
+
+    /// Set whether JPEGs are progressive, so they load in increasing detail.
+    pub fn set_jpeg_progressive(&mut self, progressive: bool) {
+        self.jpeg_progressive = progressive;
+    }
+
+    /// Set the JPEG chroma subsampling.
+    pub fn set_jpeg_subsampling(&mut self, subsampling: ChromaSubsampling) {
+        self.jpeg_subsampling = subsampling;
+    }
+
+    /// Encode WebP images lossily, with a quality from 0 to 100.
+    /// Needs the `webp-lossy` feature.
+    pub fn set_webp_quality(&mut self, quality: f32) {
+        self.webp_quality = Some(quality.clamp(0.0, 100.0));
+    }
+
+    /// Encode WebP images losslessly, which is the default.
+    pub fn set_webp_lossless(&mut self) {
+        self.webp_quality = None;
+    }
+}
+
+/// Encode an image in the given format.
+///
+/// JPEG and BMP files have no alpha channel, so it is dropped. See `encode_to_writer` to
+/// write the image straight to a file or socket.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `format` - The format to encode to.
+/// * `options` - Options for the format.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::encode::{encode, ChromaSubsampling, EncodeOptions, OutputFormat};
+/// use photon_rs::native::open_image;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let mut options = EncodeOptions::new();
+/// options.set_jpeg_quality(80);
+/// options.set_jpeg_progressive(true);
+/// options.set_jpeg_subsampling(ChromaSubsampling::Yuv444);
+/// let bytes = encode(&img, OutputFormat::Jpeg, &options).expect("Image should encode");
+/// ```
+pub fn encode(
+    photon_image: &PhotonImage,
+    format: OutputFormat,
+    options: &EncodeOptions,
+) -> Result<Vec<u8>, EncodeError> {
+    let mut buffer = Vec::new();
+    encode_to_writer(photon_image, &mut buffer, format, options)?;
+    Ok(buffer)
+}
+
+/// Encode an image in the given format, writing it to a writer such as a file or socket.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `writer` - Where to write the encoded image.
+/// * `format` - The format to encode to.
+/// * `options` - Options for the format.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::encode::{encode_to_writer, EncodeOptions, OutputFormat};
+/// use photon_rs::native::open_image;
+/// use std::net::TcpStream;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let stream = TcpStream::connect("127.0.0.1:8080").expect("Should connect");
+/// encode_to_writer(&img, stream, OutputFormat::Png, &EncodeOptions::new())
+///     .expect("Image should encode");
+/// ```
+pub fn encode_to_writer<W: Write>(
+    photon_image: &PhotonImage,
+    mut writer: W,
+    format: OutputFormat,
+    options: &EncodeOptions,
+) -> Result<(), EncodeError> {
+    let (width, height) = (photon_image.width, photon_image.height);
+    let pixels = &photon_image.raw_pixels;
+    let rgb = || -> Vec<u8> {
+        pixels
+            .chunks_exact(4)
+            .flat_map(|px| [px[0], px[1], px[2]])
+            .collect()
+    };
+
+    match format {
+        OutputFormat::Png => {
+            let compression = match options.png_compression {
+                PngCompression::Default => CompressionType::Default,
+                PngCompression::Fast => CompressionType::Fast,
+                PngCompression::Best => CompressionType::Best,
+            };
+            let filter = match options.png_filter {
+                PngFilter::NoFilter => FilterType::NoFilter,
+                PngFilter::Sub => FilterType::Sub,
+                PngFilter::Up => FilterType::Up,
+                PngFilter::Avg => FilterType::Avg,
+                PngFilter::Paeth => FilterType::Paeth,
+                PngFilter::Adaptive => FilterType::Adaptive,
+            };
+            PngEncoder::new_with_quality(&mut writer, compression, filter).write_image(
+                pixels,
+                width,
+                height,
+                ColorType::Rgba8,
+            )?;
+        }
+        OutputFormat::Jpeg => {
+            if width > u16::MAX as u32 || height > u16::MAX as u32 {
+                return Err(EncodeError::Unsupported(
+                    "JPEG images can be at most 65535 pixels wide and high".to_string(),
+                ));
+            }
+            let mut encoder = jpeg_encoder::Encoder::new(
+                &mut writer,
+                options.jpeg_quality.clamp(1, 100),
+            );
+            encoder.set_progressive(options.jpeg_progressive);
+            encoder.set_sampling_factor(match options.jpeg_subsampling {
+                ChromaSubsampling::Yuv444 => SamplingFactor::R_4_4_4,
+                ChromaSubsampling::Yuv422 => SamplingFactor::R_4_2_2,
+                ChromaSubsampling::Yuv420 => SamplingFactor::R_4_2_0,
+            });
+            encoder.encode(
+                &rgb(),
+                width as u16,
+                height as u16,
+                jpeg_encoder::ColorType::Rgb,
+            )?;
+        }
+        OutputFormat::WebP => match options.webp_quality {
+            None => {
+                image::codecs::webp::WebPEncoder::new_lossless(&mut writer)
+                    .write_image(pixels, width, height, ColorType::Rgba8)?;
+            }
+            Some(quality) => {
+                writer.write_all(&encode_webp_lossy(photon_image, quality)?)?;
+            }
+        },
+        OutputFormat::Gif => {
+            GifEncoder::new(&mut writer).encode(
+                pixels,
+                width,
+                height,
+                ColorType::Rgba8,
+            )?;
+        }
+        OutputFormat::Bmp => {
+            BmpEncoder::new(&mut writer).write_image(
+                &rgb(),
+                width,
+                height,
+                ColorType::Rgb8,
+            )?;
+        }
+        OutputFormat::Tiff => {
+            // TIFF encoding needs to seek, so the image is encoded in memory first.
+            let mut buffer = Cursor::new(Vec::new());
+            TiffEncoder::new(&mut buffer).write_image(
+                pixels,
+                width,
+                height,
+                ColorType::Rgba8,
+            )?;
+            writer.write_all(&buffer.into_inner())?;
+        }
+        OutputFormat::Ico => {
+            IcoEncoder::new(&mut writer).write_image(
+                pixels,
+                width,
+                height,
+                ColorType::Rgba8,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "webp-lossy")]
+fn encode_webp_lossy(
+    photon_image: &PhotonImage,
+    quality: f32,
+) -> Result<Vec<u8>, EncodeError> {
+    let encoder = webp::Encoder::from_rgba(
+        &photon_image.raw_pixels,
+        photon_image.width,
+        photon_image.height,
+    );
+    let encoded = encoder
+        .encode_simple(false, quality)
+        .map_err(|err| EncodeError::WebPError(format!("{:?}", err)))?;
+    Ok(encoded.to_vec())
+}
+
+#[cfg(not(feature = "webp-lossy"))]
+fn encode_webp_lossy(_: &PhotonImage, _: f32) -> Result<Vec<u8>, EncodeError> {
+    Err(EncodeError::Unsupported(
+        "lossy WebP encoding needs the `webp-lossy` feature".to_string(),
+    ))
+}
+
+/// Encode an image in the given format as a base64 data URL, such as
+/// `data:image/jpeg;base64,...`.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `format` - The format to encode to.
+/// * `options` - Options for the format.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::encode::{encode_base64, EncodeOptions, OutputFormat};
+/// use photon_rs::native::open_image;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let data_url = encode_base64(&img, OutputFormat::WebP, &EncodeOptions::new())
+///     .expect("Image should encode");
+/// ```
+pub fn encode_base64(
+    photon_image: &PhotonImage,
+    format: OutputFormat,
+    options: &EncodeOptions,
+) -> Result<String, EncodeError> {
+    let bytes = encode(photon_image, format, options)?;
+    Ok(format!(
+        "data:{};base64,{}",
+        format.mime_type(),
+        base64::encode(bytes)
+    ))
+}
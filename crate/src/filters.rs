@@ -87,6 +87,55 @@ pub fn ryo(photon_image: &mut PhotonImage) {
     }
 }
 
+/// A filter preset and the name it's looked up by.
+pub(crate) type NamedFilter = (&'static str, fn(&mut PhotonImage));
+
+/// The presets accepted by `filter`.
+pub(crate) const FILTERS: [NamedFilter; 22] = [
+    ("oceanic", |img| {
+        mix_with_colour(img, Rgb::new(0, 89, 173), 0.2)
+    }),
+    ("islands", |img| {
+        mix_with_colour(img, Rgb::new(0, 24, 95), 0.2)
+    }),
+    ("marine", |img| {
+        mix_with_colour(img, Rgb::new(0, 14, 119), 0.2)
+    }),
+    ("seagreen", |img| {
+        mix_with_colour(img, Rgb::new(0, 68, 62), 0.2)
+    }),
+    ("flagblue", |img| {
+        mix_with_colour(img, Rgb::new(0, 0, 131), 0.2)
+    }),
+    ("diamante", |img| {
+        mix_with_colour(img, Rgb::new(30, 82, 87), 0.1)
+    }),
+    ("liquid", |img| {
+        mix_with_colour(img, Rgb::new(0, 10, 75), 0.2)
+    }),
+    ("radio", |img| monochrome::monochrome(img, 5, 40, 20)),
+    ("twenties", |img| monochrome::monochrome(img, 18, 12, 20)),
+    ("rosetint", |img| monochrome::monochrome(img, 80, 20, 31)),
+    ("mauve", |img| monochrome::monochrome(img, 90, 40, 80)),
+    ("bluechrome", |img| monochrome::monochrome(img, 20, 30, 60)),
+    ("vintage", |img| {
+        mix_with_colour(img, Rgb::new(120, 70, 13), 0.2)
+    }),
+    ("perfume", |img| {
+        mix_with_colour(img, Rgb::new(80, 40, 120), 0.2)
+    }),
+    ("serenity", |img| {
+        mix_with_colour(img, Rgb::new(10, 40, 90), 0.2)
+    }),
+    ("golden", golden),
+    ("pastel_pink", pastel_pink),
+    ("cali", cali),
+    ("dramatic", dramatic),
+    ("firenze", firenze),
+    ("obsidian", obsidian),
+    ("lofi", lofi),
+];
+
 /// Apply a filter to an image. Over 20 filters are available.
 /// The filters are as follows:
 /// * **oceanic**: Add an aquamarine-tinted hue to an image.
@@ -119,43 +168,11 @@ pub fn ryo(photon_image: &mut PhotonImage) {
 /// ```
 #[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
 pub fn filter(img: &mut PhotonImage, filter_name: &str) {
-    let oceanic_rgb = Rgb::new(0, 89, 173);
-    let islands_rgb = Rgb::new(0, 24, 95);
-    let marine_rgb = Rgb::new(0, 14, 119);
-    let seagreen_rgb = Rgb::new(0, 68, 62);
-    let flagblue_rgb = Rgb::new(0, 0, 131);
-    let diamante_rgb = Rgb::new(30, 82, 87);
-    let liquid_rgb = Rgb::new(0, 10, 75);
-    let vintage_rgb = Rgb::new(120, 70, 13);
-    let perfume_rgb = Rgb::new(80, 40, 120);
-    let serenity_rgb = Rgb::new(10, 40, 90);
-
-    match filter_name {
-        // Match filter name to its corresponding function.
-        "oceanic" => mix_with_colour(img, oceanic_rgb, 0.2),
-        "islands" => mix_with_colour(img, islands_rgb, 0.2),
-        "marine" => mix_with_colour(img, marine_rgb, 0.2),
-        "seagreen" => mix_with_colour(img, seagreen_rgb, 0.2),
-        "flagblue" => mix_with_colour(img, flagblue_rgb, 0.2),
-        "diamante" => mix_with_colour(img, diamante_rgb, 0.1),
-        "liquid" => mix_with_colour(img, liquid_rgb, 0.2),
-        "radio" => monochrome::monochrome(img, 5, 40, 20),
-        "twenties" => monochrome::monochrome(img, 18, 12, 20),
-        "rosetint" => monochrome::monochrome(img, 80, 20, 31),
-        "mauve" => monochrome::monochrome(img, 90, 40, 80),
-        "bluechrome" => monochrome::monochrome(img, 20, 30, 60),
-        "vintage" => mix_with_colour(img, vintage_rgb, 0.2),
-        "perfume" => mix_with_colour(img, perfume_rgb, 0.2),
-        "serenity" => mix_with_colour(img, serenity_rgb, 0.2),
-        "golden" => golden(img),
-        "pastel_pink" => pastel_pink(img),
-        "cali" => cali(img),
-        "dramatic" => dramatic(img),
-        "firenze" => firenze(img),
-        "obsidian" => obsidian(img),
-        "lofi" => lofi(img),
-        _ => monochrome::monochrome(img, 90, 40, 80),
-    };
+    // Match filter name to its corresponding function.
+    match FILTERS.iter().find(|(name, _)| *name == filter_name) {
+        Some((_, apply)) => apply(img),
+        None => monochrome::monochrome(img, 90, 40, 80),
+    }
 }
 
 /// Apply a lofi effect to an image.
@@ -0,0 +1,420 @@
+//! Gradient generation with multiple colour stops, in linear, radial, elliptical, conic and diamond shapes.
+
+use crate::dither::DitherAlgorithm;
+use crate::{PhotonImage, Rgba};
+use palette::{FromColor, IntoColor};
+use palette::{Gradient, Laba, Lcha, LinSrgba, Srgba};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+#[cfg(feature = "enable_wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Number of entries in the lookup table a gradient is sampled into before rendering.
+const LUT_SIZE: usize = 1024;
+
+/// The shape of a gradient.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GradientShape {
+    Linear = 1,
+    Radial = 2,
+    Elliptical = 3,
+    Conic = 4,
+    Diamond = 5,
+}
+
+/// How a gradient behaves outside of the \[0, 1\] range of its stops.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpreadMode {
+    /// Extend the first and last colours.
+    Pad = 1,
+    /// Start the gradient again from the first stop.
+    Repeat = 2,
+    /// Mirror the gradient back and forth.
+    Reflect = 3,
+}
+
+/// The colour space two neighbouring stops are mixed in.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GradientInterpolation {
+    /// Mix the gamma-encoded sRGB values, as browsers do for CSS gradients.
+    Srgb = 1,
+    /// Mix in linear light.
+    Linear = 2,
+    /// Mix in the CIE L\*a\*b\* colour space.
+    Lab = 3,
+    /// Mix in the LCh colour space, travelling along the shortest hue path.
+    Lch = 4,
+}
+
+/// A colour stop of a gradient.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ColorStop {
+    position: f32,
+    color: Rgba,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl ColorStop {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create a new colour stop. `position` is clamped to the \[0, 1\] range.
+    pub fn new(position: f32, color: Rgba) -> ColorStop {
+        ColorStop {
+            position: position.clamp(0.0, 1.0),
+            color,
+        }
+    }
+
+    /// Get the position of the stop.
+    pub fn get_position(&self) -> f32 {
+        self.position
+    }
+
+    /// Get the colour of the stop.
+    pub fn get_color(&self) -> Rgba {
+        self.color.clone()
+    }
+}
+
+/// Geometry and rendering settings of a gradient.
+///
+/// The centre is given as a fraction of the image width and height, so (0.5, 0.5) is the
+/// middle of the image. Radii are given as fractions of the image width (x) and height (y);
+/// circular radial gradients only use the x radius.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GradientOptions {
+    shape: GradientShape,
+    angle: f32,
+    center_x: f32,
+    center_y: f32,
+    radius_x: f32,
+    radius_y: f32,
+    spread: SpreadMode,
+    interpolation: GradientInterpolation,
+    dither: bool,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl GradientOptions {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create options for a gradient of the given shape, centred in the image, padded,
+    /// interpolated in linear light and without dithering.
+    pub fn new(shape: GradientShape) -> GradientOptions {
+        GradientOptions {
+            shape,
+            angle: 0.0,
+            center_x: 0.5,
+            center_y: 0.5,
+            radius_x: 0.5,
+            radius_y: 0.5,
+            spread: SpreadMode::Pad,
+            interpolation: GradientInterpolation::Linear,
+            dither: false,
+        }
+    }
+
+    /// Set the angle in degrees, clockwise from the positive x axis.
+    /// Used as the direction of linear gradients and the starting angle of conic gradients.
+    pub fn set_angle(&mut self, angle: f32) {
+        self.angle = angle;
+    }
+
+    /// Set the centre of the gradient.
+    pub fn set_center(&mut self, center_x: f32, center_y: f32) {
+        self.center_x = center_x;
+        self.center_y = center_y;
+    }
+
+    /// Set the radii of radial, elliptical and diamond gradients.
+    pub fn set_radius(&mut self, radius_x: f32, radius_y: f32) {
+        self.radius_x = radius_x;
+        self.radius_y = radius_y;
+    }
+
+    /// Set the spread mode.
+    pub fn set_spread(&mut self, spread: SpreadMode) {
+        self.spread = spread;
+    }
+
+    /// Set the colour space the stops are mixed in.
+    pub fn set_interpolation(&mut self, interpolation: GradientInterpolation) {
+        self.interpolation = interpolation;
+    }
+
+    /// Enable or disable ordered dithering of the output, which hides banding in smooth gradients.
+    pub fn set_dither(&mut self, dither: bool) {
+        self.dither = dither;
+    }
+
+    /// Get the shape of the gradient.
+    pub fn get_shape(&self) -> GradientShape {
+        self.shape
+    }
+
+    /// Get the spread mode.
+    pub fn get_spread(&self) -> SpreadMode {
+        self.spread
+    }
+
+    /// Get the interpolation colour space.
+    pub fn get_interpolation(&self) -> GradientInterpolation {
+        self.interpolation
+    }
+}
+
+/// Sample the stops into `size` evenly spaced colours, as gamma-encoded RGBA values in \[0, 255\].
+pub(crate) fn gradient_lut(
+    stops: &[ColorStop],
+    interpolation: GradientInterpolation,
+    size: usize,
+) -> Vec<[f32; 4]> {
+    if stops.is_empty() {
+        // Without any stops there is no colour to draw, so the gradient is fully transparent.
+        return vec![[0.0; 4]; size];
+    }
+
+    let mut stops = stops.to_vec();
+    stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+
+    let positions = stops.iter().map(|stop| stop.position);
+    let encoded = stops.iter().map(|stop| {
+        Srgba::new(
+            stop.color.r as f32 / 255.0,
+            stop.color.g as f32 / 255.0,
+            stop.color.b as f32 / 255.0,
+            stop.color.a as f32 / 255.0,
+        )
+    });
+    let sample_at = |i: usize| {
+        if size > 1 {
+            i as f32 / (size - 1) as f32
+        } else {
+            0.0
+        }
+    };
+
+    let colors: Vec<Srgba> = match interpolation {
+        GradientInterpolation::Srgb => {
+            // Encoded sRGB does not implement `Mix`, so the encoded components are
+            // carried in a linear container and mixed as plain numbers.
+            let grad = Gradient::with_domain(
+                positions
+                    .zip(encoded.map(|c| {
+                        let (r, g, b, a) = c.into_components();
+                        LinSrgba::new(r, g, b, a)
+                    }))
+                    .collect::<Vec<_>>(),
+            );
+            (0..size)
+                .map(|i| {
+                    let (r, g, b, a) = grad.get(sample_at(i)).into_components();
+                    Srgba::new(r, g, b, a)
+                })
+                .collect()
+        }
+        GradientInterpolation::Linear => {
+            let grad = Gradient::with_domain(
+                positions
+                    .zip(encoded.map(|c| c.into_linear()))
+                    .collect::<Vec<(f32, LinSrgba)>>(),
+            );
+            (0..size)
+                .map(|i| Srgba::from_linear(grad.get(sample_at(i))))
+                .collect()
+        }
+        GradientInterpolation::Lab => {
+            let grad = Gradient::with_domain(
+                positions
+                    .zip(encoded.map(|c| Laba::from_color(c.into_linear())))
+                    .collect::<Vec<_>>(),
+            );
+            (0..size)
+                .map(|i| {
+                    let lin: LinSrgba = grad.get(sample_at(i)).into_color();
+                    Srgba::from_linear(lin)
+                })
+                .collect()
+        }
+        GradientInterpolation::Lch => {
+            let grad = Gradient::with_domain(
+                positions
+                    .zip(encoded.map(|c| Lcha::from_color(c.into_linear())))
+                    .collect::<Vec<_>>(),
+            );
+            (0..size)
+                .map(|i| {
+                    let lin: LinSrgba = grad.get(sample_at(i)).into_color();
+                    Srgba::from_linear(lin)
+                })
+                .collect()
+        }
+    };
+
+    colors
+        .into_iter()
+        .map(|c| {
+            let (r, g, b, a) = c.into_components();
+            [
+                (r * 255.0).clamp(0.0, 255.0),
+                (g * 255.0).clamp(0.0, 255.0),
+                (b * 255.0).clamp(0.0, 255.0),
+                (a * 255.0).clamp(0.0, 255.0),
+            ]
+        })
+        .collect()
+}
+
+/// Map a raw gradient coordinate into \[0, 1\] according to the spread mode.
+pub(crate) fn apply_spread(t: f32, spread: SpreadMode) -> f32 {
+    match spread {
+        SpreadMode::Pad => t.clamp(0.0, 1.0),
+        SpreadMode::Repeat => t - t.floor(),
+        SpreadMode::Reflect => {
+            let period = t.rem_euclid(2.0);
+            if period > 1.0 {
+                2.0 - period
+            } else {
+                period
+            }
+        }
+    }
+}
+
+/// Project a point onto the line from `start` to `end`, giving 0 at `start` and 1 at `end`.
+pub(crate) fn line_position(start: (f32, f32), end: (f32, f32), x: f32, y: f32) -> f32 {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let len_sq = dx * dx + dy * dy;
+    if len_sq <= 0.0 {
+        return 0.0;
+    }
+    ((x - start.0) * dx + (y - start.1) * dy) / len_sq
+}
+
+/// Compute the raw (unspread) gradient coordinate of a pixel.
+fn gradient_position(
+    options: &GradientOptions,
+    width: f32,
+    height: f32,
+    x: f32,
+    y: f32,
+) -> f32 {
+    let cx = options.center_x * width;
+    let cy = options.center_y * height;
+    let dx = x - cx;
+    let dy = y - cy;
+    let rx = (options.radius_x * width).max(f32::EPSILON);
+    let ry = (options.radius_y * height).max(f32::EPSILON);
+    let angle = options.angle.to_radians();
+
+    match options.shape {
+        GradientShape::Linear => {
+            // As with CSS gradients, the line is long enough for the corners of the image
+            // to lie exactly on the first and last stops.
+            let (sin, cos) = angle.sin_cos();
+            let half_len = ((width / 2.0) * cos).abs() + ((height / 2.0) * sin).abs();
+            let start = (cx - cos * half_len, cy - sin * half_len);
+            let end = (cx + cos * half_len, cy + sin * half_len);
+            line_position(start, end, x, y)
+        }
+        GradientShape::Radial => (dx * dx + dy * dy).sqrt() / rx,
+        GradientShape::Elliptical => ((dx / rx).powi(2) + (dy / ry).powi(2)).sqrt(),
+        GradientShape::Conic => {
+            let theta = dy.atan2(dx) - angle;
+            (theta / (2.0 * PI)).rem_euclid(1.0)
+        }
+        GradientShape::Diamond => dx.abs() / rx + dy.abs() / ry,
+    }
+}
+
+/// Render a gradient with an arbitrary number of colour stops.
+///
+/// # Arguments
+/// * `width` - Width of the generated image.
+/// * `height` - Height of the generated image.
+/// * `stops` - The colour stops. They are sorted by position; without any stops the image is transparent.
+/// * `options` - Shape, geometry, spread mode, interpolation and dithering of the gradient.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to render a dithered 45 degree linear gradient from red through white to blue:
+/// use photon_rs::gradient::{gradient, ColorStop, GradientOptions, GradientShape};
+/// use photon_rs::native::save_image;
+/// use photon_rs::Rgba;
+///
+/// let stops = vec![
+///     ColorStop::new(0.0, Rgba::new(255, 0, 0, 255)),
+///     ColorStop::new(0.5, Rgba::new(255, 255, 255, 255)),
+///     ColorStop::new(1.0, Rgba::new(0, 0, 255, 255)),
+/// ];
+/// let mut options = GradientOptions::new(GradientShape::Linear);
+/// options.set_angle(45.0);
+/// options.set_dither(true);
+/// let img = gradient(800, 600, stops, &options);
+/// save_image(img, "gradient.png").expect("Save failed");
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn gradient(
+    width: u32,
+    height: u32,
+    stops: Vec<ColorStop>,
+    options: &GradientOptions,
+) -> PhotonImage {
+    let lut = gradient_lut(&stops, options.interpolation, LUT_SIZE);
+    let spread = match options.shape {
+        // A conic gradient wraps around on its own.
+        GradientShape::Conic => SpreadMode::Pad,
+        _ => options.spread,
+    };
+
+    let width_f32 = width as f32;
+    let height_f32 = height as f32;
+    let mut raw_pixels = Vec::with_capacity((width * height * 4) as usize);
+    // Ordered dithering hides banding.
+    let bayer = options
+        .dither
+        .then(|| DitherAlgorithm::Bayer8.threshold_map())
+        .flatten();
+
+    for y in 0..height {
+        for x in 0..width {
+            let t = gradient_position(
+                options,
+                width_f32,
+                height_f32,
+                x as f32 + 0.5,
+                y as f32 + 0.5,
+            );
+            let t = apply_spread(t, spread);
+
+            // Interpolate between the two closest lookup table entries.
+            let pos = t * (LUT_SIZE - 1) as f32;
+            let idx = (pos.floor() as usize).min(LUT_SIZE - 1);
+            let next_idx = (idx + 1).min(LUT_SIZE - 1);
+            let frac = pos - idx as f32;
+
+            let offset = match bayer {
+                Some((map, size)) => {
+                    map[(y as usize % size) * size + x as usize % size] + 0.5
+                }
+                None => 0.5,
+            };
+
+            for (low, high) in lut[idx].iter().zip(lut[next_idx].iter()) {
+                let val = low + (high - low) * frac;
+                raw_pixels.push((val + offset).floor().clamp(0.0, 255.0) as u8);
+            }
+        }
+    }
+
+    PhotonImage {
+        raw_pixels,
+        width,
+        height,
+    }
+}
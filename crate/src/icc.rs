@@ -0,0 +1,586 @@
+//! ICC colour profiles, and conversion of images between RGB colour spaces.
+//!
+//! Matrix/TRC RGB profiles are supported, which covers sRGB, Display P3, Adobe RGB,
+//! ProPhoto RGB and most other camera and display profiles.
+
+use crate::colour_spaces::{
+    bradford_adaptation, mat_inverse, mat_mul, mat_vec, white_from_chromaticity,
+};
+use crate::PhotonImage;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[cfg(feature = "enable_wasm")]
+use wasm_bindgen::prelude::*;
+
+/// White point of the ICC profile connection space.
+const D50: [f32; 3] = [0.9642, 1.0, 0.8249];
+
+/// Chromaticity of the D65 white point.
+const D65_CHROMATICITY: (f32, f32) = (0.3127, 0.3290);
+
+/// Chromaticity of the D50 white point.
+const D50_CHROMATICITY: (f32, f32) = (0.3457, 0.3585);
+
+/// Number of entries in the tables used to invert tone curves.
+const INVERSE_CURVE_SIZE: usize = 4096;
+
+#[derive(Debug, Error)]
+pub enum IccError {
+    #[error("ICC profile is truncated or malformed")]
+    Malformed,
+
+    #[error("only RGB ICC profiles are supported")]
+    NotRgb,
+
+    #[error("ICC profile has no '{0}' tag, only matrix/TRC profiles are supported")]
+    MissingTag(String),
+
+    #[error("unsupported ICC tag type '{0}'")]
+    UnsupportedType(String),
+}
+
+/// Tone curve from encoded to linear values, in the form of an ICC parametric curve
+/// or a sampled table.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+enum ToneCurve {
+    /// `(a * x + b)^g + e` for `x >= d`, otherwise `c * x + f`.
+    /// Stored as `[g, a, b, c, d, e, f]`.
+    Parametric([f32; 7]),
+    /// Evenly spaced samples from 0 to 1.
+    Table(Vec<f32>),
+}
+
+impl ToneCurve {
+    fn gamma(gamma: f32) -> ToneCurve {
+        ToneCurve::Parametric([gamma, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0])
+    }
+
+    fn srgb() -> ToneCurve {
+        ToneCurve::Parametric([
+            2.4,
+            1.0 / 1.055,
+            0.055 / 1.055,
+            1.0 / 12.92,
+            0.04045,
+            0.0,
+            0.0,
+        ])
+    }
+
+    fn eval(&self, x: f32) -> f32 {
+        match self {
+            ToneCurve::Parametric([g, a, b, c, d, e, f]) => {
+                if x >= *d {
+                    (a * x + b).max(0.0).powf(*g) + e
+                } else {
+                    c * x + f
+                }
+            }
+            ToneCurve::Table(table) => interpolate(table, x),
+        }
+    }
+
+    /// Sample the inverse of the curve, from linear to encoded values.
+    fn inverse_table(&self) -> Vec<f32> {
+        let (low, high) = (self.eval(0.0), self.eval(1.0));
+        (0..INVERSE_CURVE_SIZE)
+            .map(|idx| {
+                let target = idx as f32 / (INVERSE_CURVE_SIZE - 1) as f32;
+                if target <= low {
+                    return 0.0;
+                }
+                if target >= high {
+                    return 1.0;
+                }
+                // Tone curves are increasing, so bisect for the encoded value.
+                let (mut lo, mut hi) = (0.0_f32, 1.0_f32);
+                for _ in 0..24 {
+                    let mid = (lo + hi) / 2.0;
+                    if self.eval(mid) < target {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                (lo + hi) / 2.0
+            })
+            .collect()
+    }
+}
+
+/// Linearly interpolate in a table of evenly spaced samples from 0 to 1.
+fn interpolate(table: &[f32], x: f32) -> f32 {
+    match table.len() {
+        0 => x,
+        1 => table[0],
+        len => {
+            let pos = x.clamp(0.0, 1.0) * (len - 1) as f32;
+            let idx = (pos as usize).min(len - 2);
+            let frac = pos - idx as f32;
+            table[idx] + (table[idx + 1] - table[idx]) * frac
+        }
+    }
+}
+
+/// An RGB colour profile, defined by a matrix to XYZ and a tone curve per channel.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IccProfile {
+    description: String,
+    /// Linear RGB to XYZ, adapted to the D50 white of the profile connection space.
+    to_xyz: [[f32; 3]; 3],
+    curves: [ToneCurve; 3],
+    data: Vec<u8>,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl IccProfile {
+    /// The sRGB colour space, which photon assumes for untagged images.
+    pub fn srgb() -> IccProfile {
+        IccProfile::from_primaries(
+            "sRGB",
+            [(0.64, 0.33), (0.30, 0.60), (0.15, 0.06)],
+            D65_CHROMATICITY,
+            ToneCurve::srgb(),
+        )
+    }
+
+    /// The Display P3 colour space, used by recent Apple devices.
+    pub fn display_p3() -> IccProfile {
+        IccProfile::from_primaries(
+            "Display P3",
+            [(0.680, 0.320), (0.265, 0.690), (0.150, 0.060)],
+            D65_CHROMATICITY,
+            ToneCurve::srgb(),
+        )
+    }
+
+    /// The Adobe RGB (1998) colour space.
+    pub fn adobe_rgb() -> IccProfile {
+        IccProfile::from_primaries(
+            "Adobe RGB (1998)",
+            [(0.64, 0.33), (0.21, 0.71), (0.15, 0.06)],
+            D65_CHROMATICITY,
+            ToneCurve::gamma(563.0 / 256.0),
+        )
+    }
+
+    /// The ProPhoto RGB (ROMM RGB) colour space, a wide gamut working space.
+    pub fn pro_photo_rgb() -> IccProfile {
+        IccProfile::from_primaries(
+            "ProPhoto RGB",
+            [(0.7347, 0.2653), (0.1596, 0.8404), (0.0366, 0.0001)],
+            D50_CHROMATICITY,
+            ToneCurve::Parametric([1.8, 1.0, 0.0, 1.0 / 16.0, 1.0 / 32.0, 0.0, 0.0]),
+        )
+    }
+
+    /// Get the description of the profile, such as "Display P3".
+    pub fn get_description(&self) -> String {
+        self.description.clone()
+    }
+
+    /// Get the profile as ICC data, for embedding in an image.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+}
+
+impl IccProfile {
+    /// Parse a matrix/TRC RGB profile from ICC data, such as the profile embedded in an
+    /// image.
+    ///
+    /// # Arguments
+    /// * `data` - The ICC profile data.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use photon_rs::icc::IccProfile;
+    /// use photon_rs::metadata::read_metadata;
+    ///
+    /// let bytes = std::fs::read("img.jpg").expect("File should open");
+    /// if let Some(icc) = read_metadata(&bytes).get_icc_profile() {
+    ///     let profile = IccProfile::from_bytes(&icc).expect("Profile should be RGB");
+    ///     println!("Image is in {}", profile.get_description());
+    /// }
+    /// ```
+    pub fn from_bytes(data: &[u8]) -> Result<IccProfile, IccError> {
+        if data.len() < 132 || &data[36..40] != b"acsp" {
+            return Err(IccError::Malformed);
+        }
+        if &data[16..20] != b"RGB " {
+            return Err(IccError::NotRgb);
+        }
+
+        let tag_count = read_u32(data, 128)? as usize;
+        // The count comes from the file, so it is not trusted for preallocation.
+        let mut tags = Vec::new();
+        for idx in 0..tag_count {
+            let entry = 132 + idx * 12;
+            let signature = data.get(entry..entry + 4).ok_or(IccError::Malformed)?;
+            let offset = read_u32(data, entry + 4)? as usize;
+            let size = read_u32(data, entry + 8)? as usize;
+            let body = data
+                .get(offset..offset.saturating_add(size))
+                .ok_or(IccError::Malformed)?;
+            tags.push((signature, body));
+        }
+        let tag = |name: &str| {
+            tags.iter()
+                .find(|(signature, _)| *signature == name.as_bytes())
+                .map(|(_, body)| *body)
+                .ok_or_else(|| IccError::MissingTag(name.to_string()))
+        };
+
+        let mut to_xyz = [[0.0; 3]; 3];
+        for (col, name) in ["rXYZ", "gXYZ", "bXYZ"].into_iter().enumerate() {
+            let xyz = parse_xyz(tag(name)?)?;
+            for row in 0..3 {
+                to_xyz[row][col] = xyz[row];
+            }
+        }
+        let curves = [
+            parse_curve(tag("rTRC")?)?,
+            parse_curve(tag("gTRC")?)?,
+            parse_curve(tag("bTRC")?)?,
+        ];
+        let description = tag("desc").ok().and_then(parse_text).unwrap_or_default();
+
+        Ok(IccProfile {
+            description,
+            to_xyz,
+            curves,
+            data: data.to_vec(),
+        })
+    }
+
+    fn from_primaries(
+        description: &str,
+        primaries: [(f32, f32); 3],
+        white: (f32, f32),
+        curve: ToneCurve,
+    ) -> IccProfile {
+        // Scale the primaries so that equal amounts of each give the white point.
+        let mut primaries_xyz = [[0.0; 3]; 3];
+        for (col, (x, y)) in primaries.into_iter().enumerate() {
+            let xyz = white_from_chromaticity(x, y);
+            for row in 0..3 {
+                primaries_xyz[row][col] = xyz[row];
+            }
+        }
+        let white_xyz = white_from_chromaticity(white.0, white.1);
+        let scale = mat_vec(&mat_inverse(&primaries_xyz).unwrap(), white_xyz);
+        let mut to_xyz = primaries_xyz;
+        for row in to_xyz.iter_mut() {
+            for (val, factor) in row.iter_mut().zip(scale) {
+                *val *= factor;
+            }
+        }
+        let adaptation = bradford_adaptation(white_xyz, D50);
+        let to_xyz = mat_mul(&adaptation, &to_xyz);
+
+        let mut profile = IccProfile {
+            description: description.to_string(),
+            to_xyz,
+            curves: [curve.clone(), curve.clone(), curve],
+            data: Vec::new(),
+        };
+        profile.data = profile.encode(&adaptation);
+        profile
+    }
+
+    /// Encode the profile as ICC version 4 data.
+    fn encode(&self, adaptation: &[[f32; 3]; 3]) -> Vec<u8> {
+        let mut tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+            (b"desc", encode_text(&self.description)),
+            (b"cprt", encode_text("No copyright, use freely")),
+            (b"wtpt", encode_xyz(D50)),
+        ];
+        let mut chad = b"sf32\0\0\0\0".to_vec();
+        for val in adaptation.iter().flatten() {
+            chad.extend_from_slice(&s15_fixed16(*val));
+        }
+        tags.push((b"chad", chad));
+        for (col, name) in [b"rXYZ", b"gXYZ", b"bXYZ"].into_iter().enumerate() {
+            let xyz = [
+                self.to_xyz[0][col],
+                self.to_xyz[1][col],
+                self.to_xyz[2][col],
+            ];
+            tags.push((name, encode_xyz(xyz)));
+        }
+        for (chan, name) in [b"rTRC", b"gTRC", b"bTRC"].into_iter().enumerate() {
+            tags.push((name, encode_curve(&self.curves[chan])));
+        }
+
+        let mut body = Vec::new();
+        let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+        let body_start = 128 + 4 + tags.len() * 12;
+        for (name, tag) in &tags {
+            table.extend_from_slice(*name);
+            table.extend_from_slice(&((body_start + body.len()) as u32).to_be_bytes());
+            table.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+            body.extend_from_slice(tag);
+            // Tag data is aligned to four bytes.
+            body.resize(body.len().next_multiple_of(4), 0);
+        }
+
+        let mut header = vec![0; 128];
+        let size = (128 + table.len() + body.len()) as u32;
+        header[0..4].copy_from_slice(&size.to_be_bytes());
+        header[8..12].copy_from_slice(&[4, 0x30, 0, 0]);
+        header[12..16].copy_from_slice(b"mntr");
+        header[16..20].copy_from_slice(b"RGB ");
+        header[20..24].copy_from_slice(b"XYZ ");
+        header[36..40].copy_from_slice(b"acsp");
+        header[68..80].copy_from_slice(&encode_xyz(D50)[8..]);
+
+        [header, table, body].concat()
+    }
+
+    fn is_same_space(&self, other: &IccProfile) -> bool {
+        let close = self
+            .to_xyz
+            .iter()
+            .flatten()
+            .zip(other.to_xyz.iter().flatten())
+            .all(|(a, b)| (a - b).abs() < 1e-4);
+        close && self.curves == other.curves
+    }
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32, IccError> {
+    let bytes = data.get(pos..pos + 4).ok_or(IccError::Malformed)?;
+    Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_s15_fixed16(data: &[u8], pos: usize) -> Result<f32, IccError> {
+    Ok(read_u32(data, pos)? as i32 as f32 / 65536.0)
+}
+
+fn s15_fixed16(val: f32) -> [u8; 4] {
+    ((val * 65536.0).round() as i32).to_be_bytes()
+}
+
+fn tag_type(tag: &[u8]) -> Result<&[u8], IccError> {
+    tag.get(0..4).ok_or(IccError::Malformed)
+}
+
+fn parse_xyz(tag: &[u8]) -> Result<[f32; 3], IccError> {
+    if tag_type(tag)? != b"XYZ " {
+        return Err(IccError::UnsupportedType(
+            String::from_utf8_lossy(&tag[0..4]).to_string(),
+        ));
+    }
+    Ok([
+        read_s15_fixed16(tag, 8)?,
+        read_s15_fixed16(tag, 12)?,
+        read_s15_fixed16(tag, 16)?,
+    ])
+}
+
+fn parse_curve(tag: &[u8]) -> Result<ToneCurve, IccError> {
+    match tag_type(tag)? {
+        b"curv" => {
+            let count = read_u32(tag, 8)? as usize;
+            let end = count
+                .checked_mul(2)
+                .and_then(|len| len.checked_add(12))
+                .ok_or(IccError::Malformed)?;
+            let samples = tag.get(12..end).ok_or(IccError::Malformed)?;
+            let samples: Vec<u16> = samples
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            Ok(match samples.as_slice() {
+                [] => ToneCurve::gamma(1.0),
+                // A single entry is a gamma, as an unsigned 8.8 fixed point number.
+                [gamma] => ToneCurve::gamma(*gamma as f32 / 256.0),
+                _ => ToneCurve::Table(
+                    samples.iter().map(|val| *val as f32 / 65535.0).collect(),
+                ),
+            })
+        }
+        b"para" => {
+            let function = tag.get(8..10).ok_or(IccError::Malformed)?;
+            let function = u16::from_be_bytes([function[0], function[1]]);
+            let param_count = match function {
+                0 => 1,
+                1 => 3,
+                2 => 4,
+                3 => 5,
+                4 => 7,
+                _ => {
+                    return Err(IccError::UnsupportedType(format!("para {}", function)))
+                }
+            };
+            let mut params = [0.0; 7];
+            for (idx, param) in params.iter_mut().take(param_count).enumerate() {
+                *param = read_s15_fixed16(tag, 12 + idx * 4)?;
+            }
+            let [g, a, b, c, d, e, f] = params;
+            // Normalise every function type to the general form of type 4.
+            let threshold = if a != 0.0 { -b / a } else { 0.0 };
+            Ok(ToneCurve::Parametric(match function {
+                0 => [g, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                1 => [g, a, b, 0.0, threshold, 0.0, 0.0],
+                2 => [g, a, b, 0.0, threshold, c, c],
+                3 => [g, a, b, c, d, 0.0, 0.0],
+                _ => [g, a, b, c, d, e, f],
+            }))
+        }
+        other => Err(IccError::UnsupportedType(
+            String::from_utf8_lossy(other).to_string(),
+        )),
+    }
+}
+
+/// Read the text of a `desc` (version 2) or `mluc` (version 4) tag.
+fn parse_text(tag: &[u8]) -> Option<String> {
+    match tag.get(0..4)? {
+        b"desc" => {
+            let len = read_u32(tag, 8).ok()? as usize;
+            let text = tag.get(12..len.checked_add(12)?)?;
+            Some(
+                String::from_utf8_lossy(text)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            )
+        }
+        b"mluc" => {
+            let len = read_u32(tag, 20).ok()? as usize;
+            let offset = read_u32(tag, 24).ok()? as usize;
+            let units: Vec<u16> = tag
+                .get(offset..offset.checked_add(len)?)?
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            Some(String::from_utf16_lossy(&units))
+        }
+        _ => None,
+    }
+}
+
+fn encode_xyz(xyz: [f32; 3]) -> Vec<u8> {
+    let mut tag = b"XYZ \0\0\0\0".to_vec();
+    for val in xyz {
+        tag.extend_from_slice(&s15_fixed16(val));
+    }
+    tag
+}
+
+fn encode_text(text: &str) -> Vec<u8> {
+    let units: Vec<u16> = text.encode_utf16().collect();
+    let mut tag = b"mluc\0\0\0\0".to_vec();
+    tag.extend_from_slice(&1_u32.to_be_bytes());
+    tag.extend_from_slice(&12_u32.to_be_bytes());
+    tag.extend_from_slice(b"enUS");
+    tag.extend_from_slice(&(units.len() as u32 * 2).to_be_bytes());
+    tag.extend_from_slice(&28_u32.to_be_bytes());
+    for unit in units {
+        tag.extend_from_slice(&unit.to_be_bytes());
+    }
+    tag
+}
+
+fn encode_curve(curve: &ToneCurve) -> Vec<u8> {
+    match curve {
+        ToneCurve::Parametric(params) => {
+            let mut tag = b"para\0\0\0\0".to_vec();
+            tag.extend_from_slice(&[0, 4, 0, 0]);
+            for param in params {
+                tag.extend_from_slice(&s15_fixed16(*param));
+            }
+            tag
+        }
+        ToneCurve::Table(table) => {
+            let mut tag = b"curv\0\0\0\0".to_vec();
+            tag.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            for val in table {
+                tag.extend_from_slice(&((val * 65535.0).round() as u16).to_be_bytes());
+            }
+            tag
+        }
+    }
+}
+
+/// Convert the pixels of an image from one colour profile to another.
+///
+/// Colours outside the gamut of the destination profile are clipped.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage, with pixels in the `source` colour space.
+/// * `source` - The profile the image is currently in, such as its embedded profile.
+/// * `dest` - The profile to convert to, such as `IccProfile::srgb()`.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::icc::{convert_colour_profile, IccProfile};
+/// use photon_rs::native::open_image;
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// convert_colour_profile(&mut img, &IccProfile::display_p3(), &IccProfile::srgb());
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn convert_colour_profile(
+    photon_image: &mut PhotonImage,
+    source: &IccProfile,
+    dest: &IccProfile,
+) {
+    if source.is_same_space(dest) {
+        return;
+    }
+    let Some(from_xyz) = mat_inverse(&dest.to_xyz) else {
+        return;
+    };
+    let transform = mat_mul(&from_xyz, &source.to_xyz);
+
+    let to_linear: Vec<Vec<f32>> = source
+        .curves
+        .iter()
+        .map(|curve| {
+            (0..=255)
+                .map(|val| curve.eval(val as f32 / 255.0))
+                .collect()
+        })
+        .collect();
+    let to_encoded: Vec<Vec<f32>> =
+        dest.curves.iter().map(ToneCurve::inverse_table).collect();
+
+    for px in photon_image.raw_pixels.chunks_exact_mut(4) {
+        let rgb = [
+            to_linear[0][px[0] as usize],
+            to_linear[1][px[1] as usize],
+            to_linear[2][px[2] as usize],
+        ];
+        let converted = mat_vec(&transform, rgb);
+        for chan in 0..3 {
+            let encoded = interpolate(&to_encoded[chan], converted[chan]);
+            px[chan] = (encoded * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Convert the pixels of an image from a colour profile to sRGB, which photon's other
+/// functions assume.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage, with pixels in the `source` colour space.
+/// * `source` - The profile the image is currently in, such as its embedded profile.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::icc::{convert_to_srgb, IccProfile};
+/// use photon_rs::native::open_image;
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// convert_to_srgb(&mut img, &IccProfile::adobe_rgb());
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn convert_to_srgb(photon_image: &mut PhotonImage, source: &IccProfile) {
+    convert_colour_profile(photon_image, source, &IccProfile::srgb());
+}
@@ -1,8 +1,8 @@
 //! A high-performance image processing library, available for use both natively and on the web.
 //!
 //! #### Functions
-//! 96 functions are available, including:
-//! - **Transformations**: Resize, crop, and flip images.
+//! Hundreds of functions are available, including:
+//! - **Transformations**: Resize (including fit, contain, cover and thumbnails), crop (including content-aware smart crops and trimming margins), seam carve (shrinking, enlarging and object removal), flip, rotate, shear and scale images, and warp them with affine or perspective transforms.
 //! - **Image correction**: Hue rotation, sharpening, brightness adjustment, adjusting saturation, lightening/darkening all within various colour spaces.
 //! - **Convolutions**: Sobel filters, blurs, Laplace effects, edge detection, etc.,
 //! - **Channel manipulation**: Increasing/decreasing RGB channel values, swapping channels, removing channels, etc.
@@ -12,6 +12,18 @@
 //! - **Text**: Apply text to imagery in artistic ways, or to watermark, etc.,
 //! - **Watermarking**: Watermark images in multiple formats.
 //! - **Blending**: Blend images together using 10 different techniques, change image backgrounds.
+//! - **Pyramids**: Gaussian and Laplacian image pyramids, exposure fusion and multi-band blending.
+//! - **Gradients**: Generate linear, radial, elliptical, conic and diamond gradients with any number of colour stops.
+//! - **Linear light**: Resize, blur and fade images in linear light for gamma-correct results.
+//! - **High bit depth**: Open, edit and save 16-bit and floating-point images without banding.
+//! - **HDR**: Load high dynamic range images and tone map them with Reinhard, ACES, Hable and Drago operators.
+//! - **Animation**: Decode animated GIF, APNG and WebP, apply any function to every frame, and encode animated GIF and APNG.
+//! - **Quantization**: Generate palettes with median cut, octree or k-means, use fixed palettes such as web-safe, Game Boy and CGA, and remap images with dithering.
+//! - **Dithering**: Floyd-Steinberg, Jarvis-Judice-Ninke, Stucki, Atkinson, Sierra, Bayer and blue noise dithering to black and white, per-channel levels or a palette, with serpentine scanning.
+//! - **Encoding**: Export PNG, JPEG, WebP, GIF, BMP, TIFF and ICO with per-format options, such as JPEG quality, progressive mode and chroma subsampling (lossy WebP needs the `webp-lossy` feature).
+//! - **Metadata**: Read EXIF orientation, camera, date, GPS and ICC profile data, auto-orient photos, and write or strip metadata in JPEG, PNG and WebP files.
+//! - **Colour management**: Convert images tagged with Display P3, Adobe RGB and other ICC profiles to sRGB or a chosen working space, and embed profiles on export.
+//! - **LUTs**: Load and apply 3D LUTs from `.cube` files or Hald CLUT images, and export the preset filters as `.cube` files.
 //!
 //! ## Example
 //! ```no_run
@@ -39,11 +51,11 @@
 //! ### Live Demo
 //! View the [official demo of WASM in action](https://silvia-odwyer.github.io/photon).
 
-use base64::{decode, encode};
+use crate::encode::{EncodeError, EncodeOptions, OutputFormat};
+use base64::decode;
 use image::DynamicImage::ImageRgba8;
 use image::GenericImage;
 use serde::{Deserialize, Serialize};
-use std::io::Cursor;
 
 #[cfg(feature = "enable_wasm")]
 use wasm_bindgen::prelude::*;
@@ -111,6 +123,13 @@ impl PhotonImage {
         }
     }
 
+    /// Create a new PhotonImage from a byteslice, rotated and flipped upright according to
+    /// its EXIF orientation.
+    pub fn new_from_byteslice_oriented(vec: Vec<u8>) -> PhotonImage {
+        let orientation = metadata::read_metadata(&vec).get_orientation();
+        metadata::apply_orientation(&PhotonImage::new_from_byteslice(vec), orientation)
+    }
+
     /// Create a new PhotonImage from a Blob/File.
     #[cfg(feature = "web-sys")]
     pub fn new_from_blob(blob: Blob) -> PhotonImage {
@@ -181,51 +200,54 @@ impl PhotonImage {
         self.height
     }
 
-    /// Convert the PhotonImage to base64.
-    pub fn get_base64(&self) -> String {
-        let mut img = helpers::dyn_image_from_raw(self);
-        img = ImageRgba8(img.to_rgba8());
-
-        let mut buffer = vec![];
-        img.write_to(&mut Cursor::new(&mut buffer), image::ImageOutputFormat::Png)
-            .unwrap();
-        let base64 = encode(&buffer);
+    /// Encode the PhotonImage in the given format. See `encode::encode`.
+    pub fn encode(
+        &self,
+        format: OutputFormat,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, EncodeError> {
+        encode::encode(self, format, options)
+    }
 
-        let res_base64 = format!("data:image/png;base64,{}", base64.replace("\r\n", ""));
+    /// Encode the PhotonImage in the given format as a base64 data URL.
+    /// See `encode::encode_base64`.
+    pub fn encode_base64(
+        &self,
+        format: OutputFormat,
+        options: &EncodeOptions,
+    ) -> Result<String, EncodeError> {
+        encode::encode_base64(self, format, options)
+    }
 
-        res_base64
+    /// Convert the PhotonImage to base64. Returns a PNG data URL, or an empty string if
+    /// the image can't be encoded. Use `encode_base64` to get the error.
+    pub fn get_base64(&self) -> String {
+        self.encode_base64(OutputFormat::Png, &EncodeOptions::new())
+            .unwrap_or_default()
     }
 
-    /// Convert the PhotonImage to raw bytes. Returns PNG.
+    /// Convert the PhotonImage to raw bytes. Returns PNG, or no bytes if the image can't
+    /// be encoded. Use `encode` to get the error.
     pub fn get_bytes(&self) -> Vec<u8> {
-        let mut img = helpers::dyn_image_from_raw(self);
-        img = ImageRgba8(img.to_rgba8());
-        let mut buffer = vec![];
-        img.write_to(&mut Cursor::new(&mut buffer), image::ImageOutputFormat::Png)
-            .unwrap();
-        buffer
+        self.encode(OutputFormat::Png, &EncodeOptions::new())
+            .unwrap_or_default()
     }
 
-    /// Convert the PhotonImage to raw bytes. Returns a JPEG.
+    /// Convert the PhotonImage to raw bytes. Returns a JPEG, or no bytes if the image can't
+    /// be encoded, for example because it is more than 65535 pixels wide. Use `encode` to
+    /// get the error.
     pub fn get_bytes_jpeg(&self, quality: u8) -> Vec<u8> {
-        let mut img = helpers::dyn_image_from_raw(self);
-        img = ImageRgba8(img.to_rgba8());
-        let mut buffer = vec![];
-        let out_format = image::ImageOutputFormat::Jpeg(quality);
-        img.write_to(&mut Cursor::new(&mut buffer), out_format)
-            .unwrap();
-        buffer
+        let mut options = EncodeOptions::new();
+        options.set_jpeg_quality(quality);
+        self.encode(OutputFormat::Jpeg, &options)
+            .unwrap_or_default()
     }
 
-    /// Convert the PhotonImage to raw bytes. Returns a WEBP.
+    /// Convert the PhotonImage to raw bytes. Returns a lossless WEBP, or no bytes if the
+    /// image can't be encoded. Use `encode` to get the error.
     pub fn get_bytes_webp(&self) -> Vec<u8> {
-        let mut img = helpers::dyn_image_from_raw(self);
-        img = ImageRgba8(img.to_rgba8());
-        let mut buffer = vec![];
-        let out_format = image::ImageOutputFormat::WebP;
-        img.write_to(&mut Cursor::new(&mut buffer), out_format)
-            .unwrap();
-        buffer
+        self.encode(OutputFormat::WebP, &EncodeOptions::new())
+            .unwrap_or_default()
     }
 
     /// Convert the PhotonImage's raw pixels to JS-compatible ImageData.
@@ -341,7 +363,7 @@ impl From<Vec<u8>> for Rgb {
 
 /// RGBA color type.
 #[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Rgba {
     r: u8,
     g: u8,
@@ -546,17 +568,30 @@ fn set_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
+pub mod animation;
 pub mod channels;
 pub mod colour_spaces;
 pub mod conv;
+pub mod dither;
 pub mod effects;
+pub mod encode;
 pub mod filters;
+pub mod gradient;
 pub mod helpers;
+pub mod icc;
 mod iter;
+pub mod linear;
+pub mod lut;
+pub mod metadata;
 pub mod monochrome;
 pub mod multiple;
 pub mod native;
 pub mod noise;
+pub mod pyramid;
+pub mod quantize;
+mod seam;
+pub mod storage;
 mod tests;
 pub mod text;
+pub mod tonemap;
 pub mod transform;
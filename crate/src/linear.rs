@@ -0,0 +1,321 @@
+//! Linear-light processing.
+//!
+//! The pixels of a `PhotonImage` are sRGB-encoded, so averaging them directly (as blurs,
+//! resizes and blends do) gives results which are too dark: a black and white checkerboard
+//! averages to a value of 128 rather than the correct 188. Converting to a `LinearImage`
+//! first, processing it with the functions in this module, and converting back gives
+//! physically correct results at the cost of some speed.
+
+use crate::conv::boxes_for_gauss;
+use crate::multiple::fade_gradient;
+use crate::transform::{filter_type_from_sampling_filter, SamplingFilter};
+use crate::PhotonImage;
+use image::{ImageBuffer, Rgba};
+use palette::{LinSrgb, Srgb};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "enable_wasm")]
+use wasm_bindgen::prelude::*;
+
+/// An image with linear-light RGBA pixels stored as `f32` values, nominally from 0 to 1.
+/// High dynamic range images have colour values above 1. Alpha is not premultiplied.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LinearImage {
+    pub(crate) pixels: Vec<f32>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl LinearImage {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create a new LinearImage from a Vec of linear RGBA values.
+    ///
+    /// Panics if `pixels` does not hold exactly `width * height * 4` values.
+    pub fn new(pixels: Vec<f32>, width: u32, height: u32) -> LinearImage {
+        if pixels.len() != width as usize * height as usize * 4 {
+            panic!("Pixel buffer must hold width * height * 4 values.");
+        }
+        LinearImage {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Convert a PhotonImage to linear light.
+    pub fn from_photon_image(photon_image: &PhotonImage) -> LinearImage {
+        let to_linear: Vec<f32> = (0..=255_u8)
+            .map(|val| Srgb::new(val, 0, 0).into_format::<f32>().into_linear().red)
+            .collect();
+
+        let pixels = photon_image
+            .raw_pixels
+            .chunks(4)
+            .flat_map(|px| {
+                [
+                    to_linear[px[0] as usize],
+                    to_linear[px[1] as usize],
+                    to_linear[px[2] as usize],
+                    px[3] as f32 / 255.0,
+                ]
+            })
+            .collect();
+
+        LinearImage {
+            pixels,
+            width: photon_image.width,
+            height: photon_image.height,
+        }
+    }
+
+    /// Convert the image back to an sRGB-encoded PhotonImage.
+    pub fn to_photon_image(&self) -> PhotonImage {
+        let raw_pixels = self
+            .pixels
+            .chunks(4)
+            .flat_map(|px| {
+                let linear = LinSrgb::new(
+                    px[0].clamp(0.0, 1.0),
+                    px[1].clamp(0.0, 1.0),
+                    px[2].clamp(0.0, 1.0),
+                );
+                let encoded: Srgb<u8> = Srgb::from_linear(linear).into_format();
+                [
+                    encoded.red,
+                    encoded.green,
+                    encoded.blue,
+                    (px[3].clamp(0.0, 1.0) * 255.0).round() as u8,
+                ]
+            })
+            .collect();
+
+        PhotonImage::new(raw_pixels, self.width, self.height)
+    }
+
+    /// Get the width of the image.
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the image.
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the linear RGBA values of the image.
+    pub fn get_pixels(&self) -> Vec<f32> {
+        self.pixels.clone()
+    }
+}
+
+/// Multiply the colour channels by alpha, so that transparent pixels don't bleed their
+/// colour into their neighbours when averaged.
+fn premultiply(pixels: &mut [f32]) {
+    for px in pixels.chunks_mut(4) {
+        px[0] *= px[3];
+        px[1] *= px[3];
+        px[2] *= px[3];
+    }
+}
+
+fn unpremultiply(pixels: &mut [f32]) {
+    for px in pixels.chunks_mut(4) {
+        if px[3] > 0.0 {
+            px[0] /= px[3];
+            px[1] /= px[3];
+            px[2] /= px[3];
+        }
+    }
+}
+
+/// Blur `src` into `target` with a box of the given radius, along rows if `stride` is 1 or
+/// along columns if it is the row length. Edge pixels are repeated.
+fn box_blur_pass(
+    src: &[f32],
+    target: &mut [f32],
+    lines: usize,
+    line_len: usize,
+    line_step: usize,
+    stride: usize,
+    radius: usize,
+) {
+    let scale = 1.0 / (2 * radius + 1) as f32;
+    for line in 0..lines {
+        let base = line * line_step;
+        let at = |pos: usize, chan: usize| src[(base + pos * stride) * 4 + chan];
+        for chan in 0..4 {
+            let mut sum = 0.0;
+            for offset in 0..=2 * radius {
+                let pos =
+                    (offset as isize - radius as isize).clamp(0, line_len as isize - 1);
+                sum += at(pos as usize, chan);
+            }
+            for pos in 0..line_len {
+                target[(base + pos * stride) * 4 + chan] = sum * scale;
+                let leaving = pos.saturating_sub(radius);
+                let entering = (pos + radius + 1).min(line_len - 1);
+                sum += at(entering, chan) - at(leaving, chan);
+            }
+        }
+    }
+}
+
+/// Resize an image in linear light.
+///
+/// # Arguments
+/// * `img` - The LinearImage to resize.
+/// * `width` - New width.
+/// * `height` - New height.
+/// * `sampling_filter` - Nearest = 1, Triangle = 2, CatmullRom = 3, Gaussian = 4, Lanczos3 = 5
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::linear::{resize_linear, LinearImage};
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::SamplingFilter;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let linear = LinearImage::from_photon_image(&img);
+/// let resized = resize_linear(&linear, 200, 200, SamplingFilter::Lanczos3).to_photon_image();
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn resize_linear(
+    img: &LinearImage,
+    width: u32,
+    height: u32,
+    sampling_filter: SamplingFilter,
+) -> LinearImage {
+    let mut pixels = img.pixels.clone();
+    premultiply(&mut pixels);
+
+    let buffer: ImageBuffer<Rgba<f32>, Vec<f32>> =
+        ImageBuffer::from_raw(img.width, img.height, pixels)
+            .expect("Pixel buffer must hold width * height * 4 values.");
+    let filter = filter_type_from_sampling_filter(sampling_filter);
+    let mut pixels = image::imageops::resize(&buffer, width, height, filter).into_raw();
+    unpremultiply(&mut pixels);
+
+    LinearImage {
+        pixels,
+        width,
+        height,
+    }
+}
+
+/// Gaussian blur an image in linear light.
+///
+/// # Arguments
+/// * `img` - The LinearImage to blur.
+/// * `radius` - blur radius
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::linear::{gaussian_blur_linear, LinearImage};
+/// use photon_rs::native::open_image;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let mut linear = LinearImage::from_photon_image(&img);
+/// gaussian_blur_linear(&mut linear, 3_i32);
+/// let blurred = linear.to_photon_image();
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn gaussian_blur_linear(img: &mut LinearImage, radius: i32) {
+    if radius < 1 || img.pixels.is_empty() {
+        return;
+    }
+    let width = img.width as usize;
+    let height = img.height as usize;
+
+    let mut src = img.pixels.clone();
+    premultiply(&mut src);
+    let mut target = vec![0.0; src.len()];
+
+    // Three box blurs approximate a Gaussian, as in `conv::gaussian_blur`.
+    for size in boxes_for_gauss(radius as f32, 3) {
+        let box_radius = ((size - 1) / 2).max(0) as usize;
+        box_blur_pass(&src, &mut target, height, width, width, 1, box_radius);
+        box_blur_pass(&target, &mut src, width, height, 1, width, box_radius);
+    }
+
+    unpremultiply(&mut src);
+    img.pixels = src;
+}
+
+/// Fade one image into another in linear light.
+///
+/// For horizontal fading, set both `start_y` and `end_y` to the same value.
+/// For vertical fading, set both `start_x` and `end_x` to the same value.
+/// Otherwise, axial fading is applied.
+///
+/// # Arguments
+/// * `img1` - Image to fade from. Must be the same size as img2.
+/// * `img2` - Image to fade to. Must be the same size as img1.
+/// * `start_x` - Column where the fading begins.
+/// * `end_x` - Column where the fading ends.
+/// * `start_y` - Row where the fading begins.
+/// * `end_y` - Row where the fading ends.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::linear::{fade_linear, LinearImage};
+/// use photon_rs::native::open_image;
+///
+/// let img1 = open_image("img1.jpg").expect("File should open");
+/// let img2 = open_image("img2.jpg").expect("File should open");
+/// let faded = fade_linear(
+///     &LinearImage::from_photon_image(&img1),
+///     &LinearImage::from_photon_image(&img2),
+///     0,
+///     100,
+///     0,
+///     100,
+/// );
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn fade_linear(
+    img1: &LinearImage,
+    img2: &LinearImage,
+    start_x: i32,
+    end_x: i32,
+    start_y: i32,
+    end_y: i32,
+) -> LinearImage {
+    if img1.width != img2.width || img1.height != img2.height {
+        panic!("Images must have the same size.");
+    }
+
+    let gradient = fade_gradient(
+        img1.width as usize,
+        img1.height as usize,
+        start_x,
+        end_x,
+        start_y,
+        end_y,
+    );
+
+    let mut pixels = Vec::with_capacity(img1.pixels.len());
+    for ((px1, px2), opacity_img1) in img1
+        .pixels
+        .chunks(4)
+        .zip(img2.pixels.chunks(4))
+        .zip(gradient)
+    {
+        let opacity_img2 = 1.0 - opacity_img1;
+        for chan in 0..3 {
+            pixels.push(px1[chan] * opacity_img1 + px2[chan] * opacity_img2);
+        }
+        // Set alpha channel to 100%, as `multiple::fade` does.
+        pixels.push(1.0);
+    }
+
+    LinearImage {
+        pixels,
+        width: img1.width,
+        height: img1.height,
+    }
+}
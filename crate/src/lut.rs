@@ -0,0 +1,659 @@
+//! Colour lookup tables (LUTs), loaded from Adobe `.cube` files or Hald CLUT images.
+//!
+//! A LUT maps every input colour to an output colour, and is the usual way colour graders
+//! hand over a "look". Any of the pixel-wise preset filters can also be sampled into a LUT
+//! and exported as a `.cube` file.
+
+use crate::filters;
+use crate::storage::PhotonImageF32;
+use crate::PhotonImage;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use thiserror::Error;
+
+#[cfg(feature = "enable_wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Largest edge length accepted for a 3D LUT.
+const MAX_3D_SIZE: usize = 256;
+
+/// Largest number of entries accepted for a 1D LUT.
+const MAX_1D_SIZE: usize = 65536;
+
+#[derive(Debug, Error)]
+pub enum LutError {
+    #[error("line {line}: {message}")]
+    Parse { line: usize, message: String },
+
+    #[error("expected {expected} table entries, found {found}")]
+    EntryCount { expected: usize, found: usize },
+
+    #[error("invalid Hald CLUT: {0}")]
+    InvalidHald(String),
+
+    #[error("unknown filter: {0}")]
+    UnknownFilter(String),
+}
+
+#[cfg(feature = "enable_wasm")]
+impl From<LutError> for JsValue {
+    fn from(err: LutError) -> JsValue {
+        JsError::new(&err.to_string()).into()
+    }
+}
+
+/// Interpolation used to look up colours which fall between the entries of a 3D LUT.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LutInterpolation {
+    /// Blend the 8 surrounding entries.
+    Trilinear = 1,
+    /// Blend the 4 entries of the enclosing tetrahedron. Slightly more accurate on neutrals,
+    /// and what most grading software uses.
+    Tetrahedral = 2,
+}
+
+/// A 1D or 3D colour lookup table.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Lut {
+    title: Option<String>,
+    size: usize,
+    is_3d: bool,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    // RGB triplets. For 3D LUTs red changes fastest, then green, then blue.
+    table: Vec<f32>,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl Lut {
+    /// Create an identity 3D LUT, which maps every colour to itself.
+    pub fn identity(size: u32) -> Lut {
+        let size = (size as usize).clamp(2, MAX_3D_SIZE);
+        let max = (size - 1) as f32;
+        let mut table = Vec::with_capacity(size * size * size * 3);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    table.push(r as f32 / max);
+                    table.push(g as f32 / max);
+                    table.push(b as f32 / max);
+                }
+            }
+        }
+
+        Lut {
+            title: None,
+            size,
+            is_3d: true,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            table,
+        }
+    }
+
+    /// Get the number of entries along each axis of the LUT.
+    pub fn get_size(&self) -> u32 {
+        self.size as u32
+    }
+
+    /// Check whether the LUT is a 3D LUT, as opposed to three per-channel 1D curves.
+    pub fn is_3d(&self) -> bool {
+        self.is_3d
+    }
+
+    /// Get the title of the LUT, if it has one.
+    pub fn get_title(&self) -> Option<String> {
+        self.title.clone()
+    }
+
+    /// Set the title written to exported `.cube` files.
+    pub fn set_title(&mut self, title: String) {
+        self.title = Some(title);
+    }
+
+    /// Serialize the LUT in the Adobe `.cube` format.
+    pub fn to_cube(&self) -> String {
+        let mut cube = String::new();
+        if let Some(title) = &self.title {
+            let _ = writeln!(cube, "TITLE \"{}\"", title.replace('"', "'"));
+        }
+        let keyword = if self.is_3d {
+            "LUT_3D_SIZE"
+        } else {
+            "LUT_1D_SIZE"
+        };
+        let _ = writeln!(cube, "{} {}", keyword, self.size);
+        if self.domain_min != [0.0; 3] || self.domain_max != [1.0; 3] {
+            let (min, max) = (self.domain_min, self.domain_max);
+            let _ = writeln!(cube, "DOMAIN_MIN {} {} {}", min[0], min[1], min[2]);
+            let _ = writeln!(cube, "DOMAIN_MAX {} {} {}", max[0], max[1], max[2]);
+        }
+        for rgb in self.table.chunks(3) {
+            let _ = writeln!(cube, "{:.6} {:.6} {:.6}", rgb[0], rgb[1], rgb[2]);
+        }
+        cube
+    }
+
+    /// Render a 3D LUT as a Hald CLUT image of the given level.
+    /// A level `L` image is `L * L * L` pixels wide and high, and holds a LUT of size `L * L`.
+    pub fn to_hald_clut(&self, level: u32) -> PhotonImage {
+        let mut img = hald_identity(level);
+        apply_lut(&mut img, self, LutInterpolation::Tetrahedral, 1.0);
+        img
+    }
+
+    /// Parse the contents of an Adobe `.cube` file, holding either a 1D or a 3D LUT.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use photon_rs::lut::Lut;
+    ///
+    /// let text = std::fs::read_to_string("look.cube").expect("File should open");
+    /// let lut = Lut::from_cube(&text).expect("LUT should parse");
+    /// ```
+    pub fn from_cube(text: &str) -> Result<Lut, LutError> {
+        let mut title = None;
+        let mut size_1d = None;
+        let mut size_3d = None;
+        let mut domain_min = [0.0_f32; 3];
+        let mut domain_max = [1.0_f32; 3];
+        let mut table = Vec::new();
+
+        for (idx, raw_line) in text.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = raw_line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_err = |message: &str| LutError::Parse {
+                line: line_no,
+                message: message.to_string(),
+            };
+
+            let mut parts = line.split_whitespace();
+            let keyword = parts.next().unwrap_or_default();
+            let parse_floats = |parts: std::str::SplitWhitespace| {
+                parts
+                    .map(|v| v.parse::<f32>())
+                    .collect::<Result<Vec<f32>, _>>()
+                    .map_err(|_| parse_err("expected a number"))
+            };
+
+            match keyword {
+                "TITLE" => {
+                    let rest = line["TITLE".len()..].trim();
+                    title = Some(rest.trim_matches('"').to_string());
+                }
+                "LUT_1D_SIZE" | "LUT_3D_SIZE" => {
+                    let size = parts
+                        .next()
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .ok_or_else(|| parse_err("expected an integer size"))?;
+                    if keyword == "LUT_1D_SIZE" {
+                        if !(2..=MAX_1D_SIZE).contains(&size) {
+                            return Err(parse_err("1D LUT size out of range"));
+                        }
+                        size_1d = Some(size);
+                    } else {
+                        if !(2..=MAX_3D_SIZE).contains(&size) {
+                            return Err(parse_err("3D LUT size out of range"));
+                        }
+                        size_3d = Some(size);
+                    }
+                }
+                "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                    let values = parse_floats(parts)?;
+                    if values.len() != 3 {
+                        return Err(parse_err("expected three values"));
+                    }
+                    let domain = [values[0], values[1], values[2]];
+                    if keyword == "DOMAIN_MIN" {
+                        domain_min = domain;
+                    } else {
+                        domain_max = domain;
+                    }
+                }
+                "LUT_1D_INPUT_RANGE" | "LUT_3D_INPUT_RANGE" => {
+                    let values = parse_floats(parts)?;
+                    if values.len() != 2 {
+                        return Err(parse_err("expected two values"));
+                    }
+                    domain_min = [values[0]; 3];
+                    domain_max = [values[1]; 3];
+                }
+                _ if keyword.starts_with(|c: char| {
+                    c.is_ascii_digit() || c == '-' || c == '.'
+                }) =>
+                {
+                    let values = parse_floats(line.split_whitespace())?;
+                    if values.len() != 3 {
+                        return Err(parse_err("expected an RGB triplet"));
+                    }
+                    table.extend_from_slice(&values);
+                }
+                // Unknown keywords are vendor extensions, and are skipped.
+                _ => {}
+            }
+        }
+
+        let (size, is_3d) = match (size_1d, size_3d) {
+            (Some(size), None) => (size, false),
+            (None, Some(size)) => (size, true),
+            (Some(_), Some(_)) => {
+                return Err(LutError::Parse {
+                    line: 0,
+                    message: "files with both a 1D and a 3D LUT are not supported"
+                        .to_string(),
+                })
+            }
+            (None, None) => {
+                return Err(LutError::Parse {
+                    line: 0,
+                    message: "missing LUT_1D_SIZE or LUT_3D_SIZE".to_string(),
+                })
+            }
+        };
+
+        let expected = if is_3d { size * size * size } else { size };
+        let found = table.len() / 3;
+        if found != expected {
+            return Err(LutError::EntryCount { expected, found });
+        }
+
+        Ok(Lut {
+            title,
+            size,
+            is_3d,
+            domain_min,
+            domain_max,
+            table,
+        })
+    }
+
+    /// Read a 3D LUT from a Hald CLUT image, such as one loaded with `native::open_image`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use photon_rs::lut::Lut;
+    /// use photon_rs::native::open_image;
+    ///
+    /// let hald = open_image("hald_8.png").expect("File should open");
+    /// let lut = Lut::from_hald_clut(&hald).expect("Image should be a Hald CLUT");
+    /// ```
+    pub fn from_hald_clut(img: &PhotonImage) -> Result<Lut, LutError> {
+        let width = img.get_width() as usize;
+        let height = img.get_height() as usize;
+        if width != height {
+            return Err(LutError::InvalidHald("image must be square".to_string()));
+        }
+        let level = (2..=16)
+            .find(|level| level * level * level == width)
+            .ok_or_else(|| {
+                LutError::InvalidHald(
+                    "width must be the cube of a level from 2 to 16".to_string(),
+                )
+            })?;
+
+        let size = level * level;
+        let table = img
+            .raw_pixels
+            .chunks(4)
+            .flat_map(|px| px[0..3].iter().map(|val| *val as f32 / 255.0))
+            .collect();
+
+        Ok(Lut {
+            title: None,
+            size,
+            is_3d: true,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            table,
+        })
+    }
+}
+
+impl Lut {
+    /// Sample a pixel-wise filter into a 3D LUT of the given size.
+    ///
+    /// Filters which look at neighbouring pixels, such as blurs, cannot be expressed as a LUT.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use photon_rs::filters::lofi;
+    /// use photon_rs::lut::Lut;
+    ///
+    /// let lut = Lut::from_filter_fn(33, lofi);
+    /// std::fs::write("lofi.cube", lut.to_cube()).expect("File should save");
+    /// ```
+    pub fn from_filter_fn<F>(size: u32, filter: F) -> Lut
+    where
+        F: FnOnce(&mut PhotonImage),
+    {
+        let mut lut = Lut::identity(size);
+        let size = lut.size;
+
+        // Lay the identity colours out as an image, one blue slice after the other.
+        let mut raw_pixels: Vec<u8> = lut
+            .table
+            .chunks(3)
+            .flat_map(|rgb| {
+                [
+                    (rgb[0] * 255.0).round() as u8,
+                    (rgb[1] * 255.0).round() as u8,
+                    (rgb[2] * 255.0).round() as u8,
+                    255,
+                ]
+            })
+            .collect();
+        // Some filters leave the final pixel of an image untouched, so pad with an extra row.
+        raw_pixels.resize((size * size) * (size + 1) * 4, 0);
+
+        let mut img =
+            PhotonImage::new(raw_pixels, (size * size) as u32, (size + 1) as u32);
+        filter(&mut img);
+
+        for (entry, px) in lut.table.chunks_mut(3).zip(img.raw_pixels.chunks(4)) {
+            entry[0] = px[0] as f32 / 255.0;
+            entry[1] = px[1] as f32 / 255.0;
+            entry[2] = px[2] as f32 / 255.0;
+        }
+        lut
+    }
+
+    /// Look up the output colour for an RGB colour with components from 0 to 1.
+    pub(crate) fn lookup(
+        &self,
+        rgb: [f32; 3],
+        interpolation: LutInterpolation,
+    ) -> [f32; 3] {
+        if self.is_3d {
+            self.lookup_3d(rgb, interpolation)
+        } else {
+            self.lookup_1d(rgb)
+        }
+    }
+
+    fn normalize(&self, value: f32, chan: usize) -> f32 {
+        let range = self.domain_max[chan] - self.domain_min[chan];
+        if range <= 0.0 {
+            return 0.0;
+        }
+        ((value - self.domain_min[chan]) / range).clamp(0.0, 1.0)
+    }
+
+    fn entry(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        let idx = ((b * self.size + g) * self.size + r) * 3;
+        [self.table[idx], self.table[idx + 1], self.table[idx + 2]]
+    }
+
+    fn lookup_1d(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let max = (self.size - 1) as f32;
+        let mut out = [0.0; 3];
+        for chan in 0..3 {
+            let pos = self.normalize(rgb[chan], chan) * max;
+            let low = (pos.floor() as usize).min(self.size - 1);
+            let high = (low + 1).min(self.size - 1);
+            let frac = pos - low as f32;
+            let low_val = self.table[low * 3 + chan];
+            let high_val = self.table[high * 3 + chan];
+            out[chan] = low_val + (high_val - low_val) * frac;
+        }
+        out
+    }
+
+    fn lookup_3d(&self, rgb: [f32; 3], interpolation: LutInterpolation) -> [f32; 3] {
+        let max = (self.size - 1) as f32;
+        let pos = [
+            self.normalize(rgb[0], 0) * max,
+            self.normalize(rgb[1], 1) * max,
+            self.normalize(rgb[2], 2) * max,
+        ];
+        let low = pos.map(|p| (p.floor() as usize).min(self.size - 1));
+        let high = low.map(|l| (l + 1).min(self.size - 1));
+        let (fr, fg, fb) = (
+            pos[0] - low[0] as f32,
+            pos[1] - low[1] as f32,
+            pos[2] - low[2] as f32,
+        );
+
+        let c000 = self.entry(low[0], low[1], low[2]);
+        let c111 = self.entry(high[0], high[1], high[2]);
+        let mut out = [0.0; 3];
+
+        match interpolation {
+            LutInterpolation::Trilinear => {
+                let c100 = self.entry(high[0], low[1], low[2]);
+                let c010 = self.entry(low[0], high[1], low[2]);
+                let c110 = self.entry(high[0], high[1], low[2]);
+                let c001 = self.entry(low[0], low[1], high[2]);
+                let c101 = self.entry(high[0], low[1], high[2]);
+                let c011 = self.entry(low[0], high[1], high[2]);
+                for chan in 0..3 {
+                    let c00 = c000[chan] + (c100[chan] - c000[chan]) * fr;
+                    let c10 = c010[chan] + (c110[chan] - c010[chan]) * fr;
+                    let c01 = c001[chan] + (c101[chan] - c001[chan]) * fr;
+                    let c11 = c011[chan] + (c111[chan] - c011[chan]) * fr;
+                    let c0 = c00 + (c10 - c00) * fg;
+                    let c1 = c01 + (c11 - c01) * fg;
+                    out[chan] = c0 + (c1 - c0) * fb;
+                }
+            }
+            LutInterpolation::Tetrahedral => {
+                // Pick the tetrahedron containing the point, by ordering the fractions.
+                let (a, wa, b, wb, w0, w1) = if fr > fg {
+                    if fg > fb {
+                        (
+                            self.entry(high[0], low[1], low[2]),
+                            fr - fg,
+                            self.entry(high[0], high[1], low[2]),
+                            fg - fb,
+                            1.0 - fr,
+                            fb,
+                        )
+                    } else if fr > fb {
+                        (
+                            self.entry(high[0], low[1], low[2]),
+                            fr - fb,
+                            self.entry(high[0], low[1], high[2]),
+                            fb - fg,
+                            1.0 - fr,
+                            fg,
+                        )
+                    } else {
+                        (
+                            self.entry(low[0], low[1], high[2]),
+                            fb - fr,
+                            self.entry(high[0], low[1], high[2]),
+                            fr - fg,
+                            1.0 - fb,
+                            fg,
+                        )
+                    }
+                } else if fb > fg {
+                    (
+                        self.entry(low[0], low[1], high[2]),
+                        fb - fg,
+                        self.entry(low[0], high[1], high[2]),
+                        fg - fr,
+                        1.0 - fb,
+                        fr,
+                    )
+                } else if fb > fr {
+                    (
+                        self.entry(low[0], high[1], low[2]),
+                        fg - fb,
+                        self.entry(low[0], high[1], high[2]),
+                        fb - fr,
+                        1.0 - fg,
+                        fr,
+                    )
+                } else {
+                    (
+                        self.entry(low[0], high[1], low[2]),
+                        fg - fr,
+                        self.entry(high[0], high[1], low[2]),
+                        fr - fb,
+                        1.0 - fg,
+                        fb,
+                    )
+                };
+                for chan in 0..3 {
+                    out[chan] =
+                        w0 * c000[chan] + wa * a[chan] + wb * b[chan] + w1 * c111[chan];
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Create an identity Hald CLUT image of the given level (2 to 16).
+///
+/// Apply any edits to the returned image in an external editor or with photon, then load
+/// the result with `Lut::from_hald_clut` to reuse the edits as a LUT.
+///
+/// # Arguments
+/// * `level` - The Hald level. The image is `level^3` pixels wide and high.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn hald_identity(level: u32) -> PhotonImage {
+    let level = level.clamp(2, 16);
+    let size = level * level;
+    let side = level * level * level;
+    let max = (size - 1) as f32;
+
+    let mut raw_pixels = Vec::with_capacity((side * side * 4) as usize);
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                raw_pixels.push((r as f32 / max * 255.0).round() as u8);
+                raw_pixels.push((g as f32 / max * 255.0).round() as u8);
+                raw_pixels.push((b as f32 / max * 255.0).round() as u8);
+                raw_pixels.push(255);
+            }
+        }
+    }
+
+    PhotonImage::new(raw_pixels, side, side)
+}
+
+/// Apply a LUT to an image.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `lut` - The LUT to apply.
+/// * `interpolation` - How colours between the entries of a 3D LUT are looked up.
+/// Ignored for 1D LUTs, which are always interpolated linearly.
+/// * `intensity` - Strength of the effect, from 0 (original image) to 1 (full LUT).
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::lut::{apply_lut, Lut, LutInterpolation};
+/// use photon_rs::native::open_image;
+///
+/// let text = std::fs::read_to_string("look.cube").expect("File should open");
+/// let lut = Lut::from_cube(&text).expect("LUT should parse");
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// apply_lut(&mut img, &lut, LutInterpolation::Tetrahedral, 0.8);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn apply_lut(
+    photon_image: &mut PhotonImage,
+    lut: &Lut,
+    interpolation: LutInterpolation,
+    intensity: f32,
+) {
+    let intensity = intensity.clamp(0.0, 1.0);
+
+    for px in photon_image.raw_pixels.chunks_mut(4) {
+        let rgb = [
+            px[0] as f32 / 255.0,
+            px[1] as f32 / 255.0,
+            px[2] as f32 / 255.0,
+        ];
+        let mapped = lut.lookup(rgb, interpolation);
+
+        for chan in 0..3 {
+            let val = rgb[chan] + (mapped[chan] - rgb[chan]) * intensity;
+            px[chan] = (val * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Apply a LUT to a floating-point image.
+///
+/// # Arguments
+/// * `img` - A PhotonImageF32.
+/// * `lut` - The LUT to apply.
+/// * `interpolation` - How colours between the entries of a 3D LUT are looked up.
+/// * `intensity` - Strength of the effect, from 0 (original image) to 1 (full LUT).
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::lut::LutInterpolation;
+/// use photon_rs::native::{open_image_f32, open_lut};
+/// use photon_rs::lut::apply_lut_f32;
+///
+/// let lut = open_lut("look.cube").expect("LUT should open");
+/// let mut img = open_image_f32("img.png").expect("File should open");
+/// apply_lut_f32(&mut img, &lut, LutInterpolation::Tetrahedral, 1.0);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn apply_lut_f32(
+    img: &mut PhotonImageF32,
+    lut: &Lut,
+    interpolation: LutInterpolation,
+    intensity: f32,
+) {
+    let intensity = intensity.clamp(0.0, 1.0);
+    for px in img.raw_pixels.chunks_mut(4) {
+        let rgb = [px[0], px[1], px[2]];
+        let mapped = lut.lookup(rgb, interpolation);
+        for chan in 0..3 {
+            px[chan] = rgb[chan] + (mapped[chan] - rgb[chan]) * intensity;
+        }
+    }
+}
+
+/// Sample one of the preset filters into a 3D LUT, for example to export it as a `.cube` file.
+///
+/// # Arguments
+/// * `filter_name` - Any name accepted by `filters::filter`, or the name of one of the
+///   `neue`, `lix`, `ryo` and `duotone_*` filters.
+/// * `size` - The number of entries along each axis of the LUT. 33 is a common choice.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::lut::lut_from_filter;
+///
+/// let lut = lut_from_filter("firenze", 33).expect("Filter should exist");
+/// std::fs::write("firenze.cube", lut.to_cube()).expect("File should save");
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn lut_from_filter(filter_name: &str, size: u32) -> Result<Lut, LutError> {
+    let extra: [filters::NamedFilter; 7] = [
+        ("neue", filters::neue),
+        ("lix", filters::lix),
+        ("ryo", filters::ryo),
+        ("duotone_violette", filters::duotone_violette),
+        ("duotone_horizon", filters::duotone_horizon),
+        ("duotone_lilac", filters::duotone_lilac),
+        ("duotone_ochre", filters::duotone_ochre),
+    ];
+    let (_, apply) = filters::FILTERS
+        .iter()
+        .chain(extra.iter())
+        .find(|(name, _)| *name == filter_name)
+        .ok_or_else(|| LutError::UnknownFilter(filter_name.to_string()))?;
+
+    let mut lut = Lut::from_filter_fn(size, apply);
+    lut.set_title(filter_name.to_string());
+    Ok(lut)
+}
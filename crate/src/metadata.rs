@@ -0,0 +1,424 @@
+//! Image metadata: EXIF orientation, camera details, capture date, GPS position and ICC
+//! profiles.
+//!
+//! Metadata is read from encoded image bytes, and can be written back to, or stripped from,
+//! encoded JPEG, PNG and WebP files.
+
+use crate::PhotonImage;
+use exif::{In, Tag, Value};
+use image::imageops;
+use img_parts::{Bytes, DynImage, ImageEXIF, ImageICC};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[cfg(feature = "enable_wasm")]
+use wasm_bindgen::prelude::*;
+
+/// EXIF tag number of the orientation field.
+const ORIENTATION_TAG: u16 = 0x0112;
+
+#[derive(Debug, Error)]
+pub enum MetadataError {
+    #[error("metadata can only be written to JPEG, PNG and WebP images")]
+    UnsupportedFormat,
+
+    #[error(transparent)]
+    ContainerError(#[from] img_parts::Error),
+}
+
+/// Metadata read from an image file.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ImageMetadata {
+    orientation: u16,
+    make: Option<String>,
+    model: Option<String>,
+    date_time: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    exif: Option<Vec<u8>>,
+    icc_profile: Option<Vec<u8>>,
+}
+
+impl Default for ImageMetadata {
+    fn default() -> Self {
+        ImageMetadata {
+            orientation: 1,
+            make: None,
+            model: None,
+            date_time: None,
+            latitude: None,
+            longitude: None,
+            exif: None,
+            icc_profile: None,
+        }
+    }
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl ImageMetadata {
+    /// Get the EXIF orientation, from 1 to 8. 1 means the pixels are already upright.
+    pub fn get_orientation(&self) -> u16 {
+        self.orientation
+    }
+
+    /// Set the EXIF orientation written by `embed_metadata`, from 1 to 8.
+    /// Metadata without EXIF data gets a minimal EXIF block holding only the orientation.
+    pub fn set_orientation(&mut self, orientation: u16) {
+        self.orientation = orientation.clamp(1, 8);
+    }
+
+    /// Get the camera manufacturer.
+    pub fn get_make(&self) -> Option<String> {
+        self.make.clone()
+    }
+
+    /// Get the camera model.
+    pub fn get_model(&self) -> Option<String> {
+        self.model.clone()
+    }
+
+    /// Get the date and time the image was taken, as `YYYY:MM:DD HH:MM:SS`.
+    pub fn get_date_time(&self) -> Option<String> {
+        self.date_time.clone()
+    }
+
+    /// Get the GPS latitude in degrees, negative in the southern hemisphere.
+    pub fn get_latitude(&self) -> Option<f64> {
+        self.latitude
+    }
+
+    /// Get the GPS longitude in degrees, negative west of Greenwich.
+    pub fn get_longitude(&self) -> Option<f64> {
+        self.longitude
+    }
+
+    /// Get the raw EXIF data, as a TIFF structure.
+    pub fn get_exif(&self) -> Option<Vec<u8>> {
+        self.exif.clone()
+    }
+
+    /// Replace the raw EXIF data, re-reading the fields from it.
+    pub fn set_exif(&mut self, exif: Vec<u8>) {
+        let icc_profile = self.icc_profile.take();
+        *self = ImageMetadata::from_parts(Some(exif), icc_profile);
+    }
+
+    /// Get the embedded ICC colour profile.
+    pub fn get_icc_profile(&self) -> Option<Vec<u8>> {
+        self.icc_profile.clone()
+    }
+
+    /// Replace the embedded ICC colour profile.
+    pub fn set_icc_profile(&mut self, icc_profile: Vec<u8>) {
+        self.icc_profile = Some(icc_profile);
+    }
+}
+
+impl ImageMetadata {
+    fn from_parts(exif: Option<Vec<u8>>, icc_profile: Option<Vec<u8>>) -> ImageMetadata {
+        let mut metadata = ImageMetadata {
+            exif,
+            icc_profile,
+            ..Default::default()
+        };
+
+        let parsed = match &metadata.exif {
+            Some(raw) => exif::Reader::new().read_raw(raw.clone()).ok(),
+            None => None,
+        };
+        if let Some(parsed) = parsed {
+            metadata.read_fields(&parsed);
+        }
+        metadata
+    }
+
+    fn read_fields(&mut self, parsed: &exif::Exif) {
+        let text = |tag: Tag| match parsed.get_field(tag, In::PRIMARY).map(|f| &f.value)
+        {
+            Some(Value::Ascii(values)) => values
+                .first()
+                .map(|val| String::from_utf8_lossy(val).trim().to_string()),
+            _ => None,
+        };
+        let coordinate = |tag: Tag, ref_tag: Tag, negative: &str| {
+            let degrees = match parsed.get_field(tag, In::PRIMARY).map(|f| &f.value) {
+                Some(Value::Rational(parts)) if parts.len() == 3 => {
+                    parts[0].to_f64()
+                        + parts[1].to_f64() / 60.0
+                        + parts[2].to_f64() / 3600.0
+                }
+                _ => return None,
+            };
+            let sign = if text(ref_tag).as_deref() == Some(negative) {
+                -1.0
+            } else {
+                1.0
+            };
+            Some(sign * degrees)
+        };
+
+        if let Some(orientation) = parsed
+            .get_field(Tag::Orientation, In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+        {
+            self.orientation = (orientation as u16).clamp(1, 8);
+        }
+        self.make = text(Tag::Make);
+        self.model = text(Tag::Model);
+        self.date_time = text(Tag::DateTimeOriginal).or_else(|| text(Tag::DateTime));
+        self.latitude = coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S");
+        self.longitude = coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W");
+    }
+}
+
+/// Set the orientation entry in the first IFD of a raw EXIF TIFF structure.
+///
+/// Without an orientation entry, a copy of the first IFD with one added is appended and the
+/// header is pointed at it, so none of the existing offsets move. EXIF data which can't be
+/// parsed is left as is.
+fn patch_orientation(exif: &mut Vec<u8>, orientation: u16) {
+    let little_endian = match exif.get(0..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => return,
+    };
+    let read_u16 = |buf: &[u8], pos: usize| -> Option<u16> {
+        let bytes = [*buf.get(pos)?, *buf.get(pos + 1)?];
+        Some(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let read_u32 = |buf: &[u8], pos: usize| -> Option<u32> {
+        let bytes: [u8; 4] = buf.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+    let u16_bytes = |val: u16| {
+        if little_endian {
+            val.to_le_bytes()
+        } else {
+            val.to_be_bytes()
+        }
+    };
+    let u32_bytes = |val: u32| {
+        if little_endian {
+            val.to_le_bytes()
+        } else {
+            val.to_be_bytes()
+        }
+    };
+
+    let Some(ifd) = read_u32(exif, 4).map(|offset| offset as usize) else {
+        return;
+    };
+    let Some(entries) = read_u16(exif, ifd) else {
+        return;
+    };
+    // Entries are sorted by tag, so a new one goes before the first larger tag.
+    let mut insert_at = entries as usize;
+    for entry in 0..entries as usize {
+        let pos = ifd + 2 + entry * 12;
+        match read_u16(exif, pos) {
+            Some(ORIENTATION_TAG) if pos + 10 <= exif.len() => {
+                exif[pos + 8..pos + 10].copy_from_slice(&u16_bytes(orientation));
+                return;
+            }
+            Some(tag) if tag > ORIENTATION_TAG => {
+                insert_at = insert_at.min(entry);
+            }
+            Some(_) => {}
+            None => return,
+        }
+    }
+
+    let entries_start = ifd + 2;
+    let split = entries_start + insert_at * 12;
+    let entries_end = entries_start + entries as usize * 12;
+    let (Some(new_count), Some(next_ifd)) = (
+        entries.checked_add(1),
+        exif.get(entries_end..entries_end + 4),
+    ) else {
+        return;
+    };
+    let mut rebuilt = Vec::with_capacity(entries_end - ifd + 16);
+    rebuilt.extend_from_slice(&u16_bytes(new_count));
+    rebuilt.extend_from_slice(&exif[entries_start..split]);
+    rebuilt.extend_from_slice(&u16_bytes(ORIENTATION_TAG));
+    // A single SHORT value, padded to the 4 bytes of the value field.
+    rebuilt.extend_from_slice(&u16_bytes(3));
+    rebuilt.extend_from_slice(&u32_bytes(1));
+    rebuilt.extend_from_slice(&u16_bytes(orientation));
+    rebuilt.extend_from_slice(&[0, 0]);
+    rebuilt.extend_from_slice(&exif[split..entries_end]);
+    rebuilt.extend_from_slice(next_ifd);
+
+    // IFDs start on a word boundary.
+    let Ok(new_ifd) = u32::try_from(exif.len() + exif.len() % 2) else {
+        return;
+    };
+    exif.resize(new_ifd as usize, 0);
+    exif.extend_from_slice(&rebuilt);
+    exif[4..8].copy_from_slice(&u32_bytes(new_ifd));
+}
+
+/// Build a minimal little-endian EXIF TIFF structure holding only an orientation entry.
+fn orientation_exif(orientation: u16) -> Vec<u8> {
+    let mut exif = Vec::with_capacity(26);
+    exif.extend_from_slice(b"II");
+    exif.extend_from_slice(&42_u16.to_le_bytes());
+    // Offset of the first IFD, right after the header.
+    exif.extend_from_slice(&8_u32.to_le_bytes());
+    exif.extend_from_slice(&1_u16.to_le_bytes());
+    exif.extend_from_slice(&ORIENTATION_TAG.to_le_bytes());
+    // A single SHORT value, padded to the 4 bytes of the value field.
+    exif.extend_from_slice(&3_u16.to_le_bytes());
+    exif.extend_from_slice(&1_u32.to_le_bytes());
+    exif.extend_from_slice(&orientation.to_le_bytes());
+    exif.extend_from_slice(&[0, 0]);
+    // No further IFDs.
+    exif.extend_from_slice(&0_u32.to_le_bytes());
+    exif
+}
+
+/// Read the metadata of an encoded image.
+///
+/// Images without metadata, or in formats which can't hold it, give empty metadata with
+/// an orientation of 1.
+///
+/// # Arguments
+/// * `bytes` - The encoded image, for example the contents of a JPEG file.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::metadata::read_metadata;
+///
+/// let bytes = std::fs::read("img.jpg").expect("File should open");
+/// let metadata = read_metadata(&bytes);
+/// println!("Taken with a {:?}", metadata.get_model());
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn read_metadata(bytes: &[u8]) -> ImageMetadata {
+    if let Ok(Some(container)) = DynImage::from_bytes(Bytes::copy_from_slice(bytes)) {
+        return ImageMetadata::from_parts(
+            container.exif().map(|exif| exif.to_vec()),
+            container.icc_profile().map(|icc| icc.to_vec()),
+        );
+    }
+
+    // Other formats, such as TIFF, are read by the EXIF parser directly.
+    let mut cursor = std::io::Cursor::new(bytes);
+    match exif::Reader::new().read_from_container(&mut cursor) {
+        Ok(parsed) => {
+            let mut metadata = ImageMetadata::from_parts(None, None);
+            metadata.read_fields(&parsed);
+            metadata
+        }
+        Err(_) => ImageMetadata::from_parts(None, None),
+    }
+}
+
+/// Rotate and flip an image so that it is upright, given its EXIF orientation.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage, as stored in the file.
+/// * `orientation` - The EXIF orientation, from 1 to 8.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::metadata::{apply_orientation, read_metadata};
+/// use photon_rs::native::open_image;
+///
+/// let bytes = std::fs::read("img.jpg").expect("File should open");
+/// let metadata = read_metadata(&bytes);
+/// let img = open_image("img.jpg").expect("File should open");
+/// let upright = apply_orientation(&img, metadata.get_orientation());
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn apply_orientation(photon_image: &PhotonImage, orientation: u16) -> PhotonImage {
+    let img = crate::helpers::dyn_image_from_raw(photon_image).to_rgba8();
+    let oriented = match orientation {
+        2 => imageops::flip_horizontal(&img),
+        3 => imageops::rotate180(&img),
+        4 => imageops::flip_vertical(&img),
+        5 => imageops::flip_horizontal(&imageops::rotate90(&img)),
+        6 => imageops::rotate90(&img),
+        7 => imageops::flip_horizontal(&imageops::rotate270(&img)),
+        8 => imageops::rotate270(&img),
+        _ => img,
+    };
+
+    let (width, height) = oriented.dimensions();
+    PhotonImage::new(oriented.into_raw(), width, height)
+}
+
+/// Write metadata into an encoded JPEG, PNG or WebP image, replacing any it already has.
+///
+/// The EXIF orientation is set from the metadata, so images which were made upright with
+/// `apply_orientation` should have their orientation set to 1 first.
+///
+/// # Arguments
+/// * `encoded` - The encoded image.
+/// * `metadata` - The metadata to write.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::metadata::{embed_metadata, read_metadata};
+///
+/// let original = std::fs::read("img.jpg").expect("File should open");
+/// let edited = std::fs::read("edited.jpg").expect("File should open");
+/// let with_metadata = embed_metadata(edited, &read_metadata(&original))
+///     .expect("Image should be a JPEG, PNG or WebP");
+/// ```
+pub fn embed_metadata(
+    encoded: Vec<u8>,
+    metadata: &ImageMetadata,
+) -> Result<Vec<u8>, MetadataError> {
+    let mut container =
+        DynImage::from_bytes(encoded.into())?.ok_or(MetadataError::UnsupportedFormat)?;
+
+    let exif = match metadata.exif.clone() {
+        Some(mut exif) => {
+            patch_orientation(&mut exif, metadata.orientation);
+            Some(exif)
+        }
+        None if metadata.orientation != 1 => {
+            Some(orientation_exif(metadata.orientation))
+        }
+        None => None,
+    };
+    container.set_exif(exif.map(Bytes::from));
+    container.set_icc_profile(metadata.icc_profile.clone().map(Bytes::from));
+
+    Ok(container.encoder().bytes().to_vec())
+}
+
+/// Remove all EXIF data and ICC profiles from an encoded JPEG, PNG or WebP image.
+///
+/// # Arguments
+/// * `encoded` - The encoded image.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::metadata::strip_metadata;
+///
+/// let bytes = std::fs::read("img.jpg").expect("File should open");
+/// let stripped = strip_metadata(bytes).expect("Image should be a JPEG, PNG or WebP");
+/// ```
+pub fn strip_metadata(encoded: Vec<u8>) -> Result<Vec<u8>, MetadataError> {
+    let mut container =
+        DynImage::from_bytes(encoded.into())?.ok_or(MetadataError::UnsupportedFormat)?;
+    container.set_exif(None);
+    container.set_icc_profile(None);
+
+    Ok(container.encoder().bytes().to_vec())
+}
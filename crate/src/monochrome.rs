@@ -2,9 +2,11 @@
 
 use crate::helpers;
 use crate::iter::ImageIterator;
+use crate::storage::PhotonImageGray;
 use crate::PhotonImage;
 use image::Pixel;
 use image::{GenericImage, GenericImageView};
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "enable_wasm")]
 use wasm_bindgen::prelude::*;
@@ -430,20 +432,105 @@ pub fn threshold(img: &mut PhotonImage, threshold: u32) {
     let end = img.get_raw_pixels().len();
 
     for i in (0..end).step_by(4) {
-        let r = img.raw_pixels[i] as f32;
-        let g = img.raw_pixels[i + 1] as f32;
-        let b = img.raw_pixels[i + 2] as f32;
+        let v = threshold_pixel(&img.raw_pixels[i..i + 3], threshold);
 
-        let mut v = 0.2126 * r + 0.7152 * g + 0.072 * b;
+        img.raw_pixels[i] = v;
+        img.raw_pixels[i + 1] = v;
+        img.raw_pixels[i + 2] = v;
+    }
+}
 
-        if v >= threshold as f32 {
-            v = 255.0;
-        } else {
-            v = 0.0;
-        }
+/// Threshold the luminance of an RGB pixel to 0 or 255.
+fn threshold_pixel(px: &[u8], threshold: u32) -> u8 {
+    let r = px[0] as f32;
+    let g = px[1] as f32;
+    let b = px[2] as f32;
+
+    let v = 0.2126 * r + 0.7152 * g + 0.072 * b;
+
+    if v >= threshold as f32 {
+        255
+    } else {
+        0
+    }
+}
+
+/// Greyscale conversions which `to_gray` can output as a compact single-channel image.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GrayscaleMode {
+    /// As `grayscale`.
+    Average = 1,
+    /// As `grayscale_human_corrected`.
+    HumanCorrected = 2,
+    /// As `desaturate`.
+    Desaturate = 3,
+    /// As `decompose_min`.
+    DecomposeMin = 4,
+    /// As `decompose_max`.
+    DecomposeMax = 5,
+    /// As `r_grayscale`.
+    Red = 6,
+    /// As `g_grayscale`.
+    Green = 7,
+    /// As `b_grayscale`.
+    Blue = 8,
+}
 
-        img.raw_pixels[i] = v as u8;
-        img.raw_pixels[i + 1] = v as u8;
-        img.raw_pixels[i + 2] = v as u8;
+/// Convert an image to a single-channel greyscale image, which takes a quarter of the
+/// memory of a PhotonImage.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `mode` - The greyscale conversion to use.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::monochrome::{to_gray, GrayscaleMode};
+/// use photon_rs::native::open_image;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let gray = to_gray(&img, GrayscaleMode::HumanCorrected);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn to_gray(photon_image: &PhotonImage, mode: GrayscaleMode) -> PhotonImageGray {
+    let mut img = photon_image.clone();
+    match mode {
+        GrayscaleMode::Average => grayscale(&mut img),
+        GrayscaleMode::HumanCorrected => grayscale_human_corrected(&mut img),
+        GrayscaleMode::Desaturate => desaturate(&mut img),
+        GrayscaleMode::DecomposeMin => decompose_min(&mut img),
+        GrayscaleMode::DecomposeMax => decompose_max(&mut img),
+        GrayscaleMode::Red => r_grayscale(&mut img),
+        GrayscaleMode::Green => g_grayscale(&mut img),
+        GrayscaleMode::Blue => b_grayscale(&mut img),
     }
+    PhotonImageGray::from_gray_rgba(&img)
+}
+
+/// Threshold an image into a single-channel image of 0 and 255 values, as `threshold` does.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `threshold` - The amount the image should be thresholded by from 0 to 255.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::monochrome::threshold_gray;
+/// use photon_rs::native::open_image;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let binary = threshold_gray(&img, 30_u32);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn threshold_gray(photon_image: &PhotonImage, threshold: u32) -> PhotonImageGray {
+    let raw_pixels = photon_image
+        .raw_pixels
+        .chunks(4)
+        .map(|px| threshold_pixel(px, threshold))
+        .collect();
+
+    PhotonImageGray::new(raw_pixels, photon_image.width, photon_image.height)
 }
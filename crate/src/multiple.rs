@@ -1,14 +1,21 @@
 //! Image manipulation with multiple images, including adding watermarks, changing backgrounds, etc.,
 
 use crate::channels::color_sim;
+use crate::gradient::{
+    apply_spread, gradient, line_position, ColorStop, GradientOptions, GradientShape,
+    SpreadMode,
+};
 use crate::iter::ImageIterator;
-use crate::{helpers, GenericImage, PhotonImage, Rgb};
+use crate::pyramid::{
+    collapse_pyramid, gaussian_levels, laplacian_levels, max_levels, FloatPlane,
+};
+use crate::transform::{sample_premultiplied, unpremultiply, Homography, Interpolation};
+use crate::{helpers, GenericImage, PhotonImage, Rgb, Rgba};
 use image::DynamicImage::ImageRgba8;
 use image::Pixel as ImagePixel;
-use image::{DynamicImage, GenericImageView, RgbaImage};
-use palette::{Blend, Gradient, Lab, Lch, LinSrgba, Srgb, Srgba};
-use palette::{FromColor, IntoColor};
-use std::cmp::{max, min};
+use image::{DynamicImage, GenericImageView};
+use palette::IntoColor;
+use palette::{Blend, Lab, LinSrgba, Srgb};
 
 #[cfg(feature = "enable_wasm")]
 use wasm_bindgen::prelude::*;
@@ -218,49 +225,105 @@ pub fn replace_background(
     photon_image.raw_pixels = raw_pixels;
 }
 
+/// Place an image into a quadrilateral on another image, warping it in perspective, such as
+/// a screenshot onto the screen of a device in a mockup photo.
+/// The placed image is blended over the background with its alpha, and antialiased at the edges.
+/// Returns whether the image was placed: nothing is placed if there aren't exactly four
+/// corners, or three of them lie on a line.
+///
+/// # Arguments
+/// * `img` - The background image, which is modified.
+/// * `overlay` - The image to place.
+/// * `corners` - Corners of the quadrilateral, as `[x, y]` pairs for where the top left, top
+///   right, bottom right and bottom left corners of `overlay` are placed.
+/// * `interpolation` - How pixels of `overlay` are sampled.
+/// # Example
+///
+/// ```no_run
+/// // For example, to place a screenshot onto the screen of a laptop in a photo:
+/// use photon_rs::multiple::place_in_quad;
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::Interpolation;
+///
+/// let mut img = open_image("laptop.jpg").expect("File should open");
+/// let screenshot = open_image("screenshot.png").expect("File should open");
+/// let corners = vec![412.0, 180.0, 1180.0, 220.0, 1150.0, 690.0, 398.0, 640.0];
+/// let placed = place_in_quad(&mut img, &screenshot, corners, Interpolation::Bicubic);
+/// ```
 #[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
-pub fn create_gradient(width: u32, height: u32) -> PhotonImage {
-    let mut image = RgbaImage::new(width, height);
-
-    // Create a gradient.
-    let grad1 = Gradient::new(vec![
-        LinSrgba::new(1.0, 0.1, 0.1, 1.0),
-        LinSrgba::new(0.1, 0.1, 1.0, 1.0),
-        LinSrgba::new(0.1, 1.0, 0.1, 1.0),
-    ]);
-
-    let _grad3 = Gradient::new(vec![
-        Lch::from_color(LinSrgba::new(1.0, 0.1, 0.1, 1.0)),
-        Lch::from_color(LinSrgba::new(0.1, 0.1, 1.0, 1.0)),
-        Lch::from_color(LinSrgba::new(0.1, 1.0, 0.1, 1.0)),
-    ]);
-
-    for (i, c1) in grad1.take(width as usize).enumerate() {
-        let c1: Srgba<f32> = Srgba::from_linear(c1).into_format();
-        {
-            let mut sub_image = image.sub_image(i as u32, 0, 1, height);
-            for (x, y) in ImageIterator::with_dimension(&sub_image.dimensions()) {
-                let components = c1.into_components();
-                sub_image.put_pixel(
-                    x,
-                    y,
-                    image::Rgba([
-                        (components.0 * 255.0) as u8,
-                        (components.1 * 255.0) as u8,
-                        (components.2 * 255.0) as u8,
-                        255,
-                    ]),
-                );
+pub fn place_in_quad(
+    img: &mut PhotonImage,
+    overlay: &PhotonImage,
+    corners: Vec<f32>,
+    interpolation: Interpolation,
+) -> bool {
+    let (w, h) = (overlay.width as f32, overlay.height as f32);
+    let Some(inverse) =
+        Homography::from_points(vec![0.0, 0.0, w, 0.0, w, h, 0.0, h], corners.clone())
+            .and_then(|homography| homography.inverse())
+    else {
+        return false;
+    };
+
+    // Only the bounding box of the quadrilateral, plus a pixel for antialiasing, is covered.
+    let xs = corners.iter().step_by(2);
+    let ys = corners.iter().skip(1).step_by(2);
+    let min_x = xs.clone().fold(f32::INFINITY, |a, b| a.min(*b)) - 1.0;
+    let max_x = xs.fold(f32::NEG_INFINITY, |a, b| a.max(*b)) + 1.0;
+    let min_y = ys.clone().fold(f32::INFINITY, |a, b| a.min(*b)) - 1.0;
+    let max_y = ys.fold(f32::NEG_INFINITY, |a, b| a.max(*b)) + 1.0;
+    let clamp_x = |val: f32| val.clamp(0.0, img.width as f32) as u32;
+    let clamp_y = |val: f32| val.clamp(0.0, img.height as f32) as u32;
+    let (start_x, end_x) = (clamp_x(min_x.floor()), clamp_x(max_x.ceil()));
+    let (start_y, end_y) = (clamp_y(min_y.floor()), clamp_y(max_y.ceil()));
+
+    for y in start_y..end_y {
+        for x in start_x..end_x {
+            let Some((src_x, src_y)) = inverse.apply(x as f32 + 0.5, y as f32 + 0.5)
+            else {
+                continue;
+            };
+            let colour = sample_premultiplied(
+                overlay,
+                src_x - 0.5,
+                src_y - 0.5,
+                interpolation,
+                [0.0; 4],
+            );
+            if colour[3] <= 0.0 {
+                continue;
+            }
+
+            // Blend the premultiplied colour over the background.
+            let idx = ((y * img.width + x) * 4) as usize;
+            let pixel = &mut img.raw_pixels[idx..idx + 4];
+            let coverage = 1.0 - colour[3] / 255.0;
+            let alpha = pixel[3] as f32 / 255.0;
+            let mut blended = [0.0; 4];
+            for chan in 0..3 {
+                blended[chan] = colour[chan] + pixel[chan] as f32 * alpha * coverage;
             }
+            blended[3] = colour[3] + pixel[3] as f32 * coverage;
+            pixel.copy_from_slice(&unpremultiply(blended));
         }
     }
-    let rgba_img = ImageRgba8(image);
-    let raw_pixels = rgba_img.into_bytes();
-    PhotonImage {
-        raw_pixels,
+    true
+}
+
+/// Create a horizontal gradient running from red through blue to green.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn create_gradient(width: u32, height: u32) -> PhotonImage {
+    let stops = vec![
+        ColorStop::new(0.0, Rgba::new(255, 89, 89, 255)),
+        ColorStop::new(0.5, Rgba::new(89, 89, 255, 255)),
+        ColorStop::new(1.0, Rgba::new(89, 255, 89, 255)),
+    ];
+    gradient(
         width,
         height,
-    }
+        stops,
+        &GradientOptions::new(GradientShape::Linear),
+    )
 }
 
 /// Apply a gradient to an image.
@@ -271,191 +334,27 @@ pub fn apply_gradient(image: &mut PhotonImage) {
     blend(image, &gradient, "overlay");
 }
 
-/// Build a simple horizontal gradient.
-fn build_horizontal_gradient(
+/// Opacity of the first image at every pixel of a `fade`, in row-major order.
+pub(crate) fn fade_gradient(
     width: usize,
     height: usize,
     start_x: i32,
     end_x: i32,
-) -> Vec<f32> {
-    let min_x = min(start_x, end_x);
-    let max_x = max(start_x, end_x);
-    let total_grad_len = max_x - min_x;
-    let total_size = width * height;
-    let mut gradient = std::iter::repeat(0.0).take(total_size).collect::<Vec<_>>();
-    if total_grad_len <= 0 {
-        // Nothing to do. Return a vector filled with zeros.
-        return gradient;
-    }
-
-    // Fill every column from 0 to the leftmost x with zeros.
-    for row in 0..height {
-        for col in 0..min_x {
-            let pos = row * width + col as usize;
-            gradient[pos] = 0.0;
-        }
-    }
-
-    // Fill every column from the rightmost x to image width with ones.
-    // If the rightmost x is less than 0, start with 0.
-    let first_col = max(max_x, 0) as usize;
-    for row in 0..height {
-        for col in first_col..width {
-            let pos = row * width + col;
-            gradient[pos] = 1.0;
-        }
-    }
-
-    // Build gradient between the leftmost and the rightmost x.
-    // Clamp values in such a way that they belong to the visible area.
-    let first_col = max(min_x, 0);
-    let last_col = min(max_x, width as i32);
-    for row in 0..height {
-        for col in first_col..last_col {
-            let pos = row * width + col as usize;
-            let total_len_f32 = total_grad_len as f32;
-            let column_f32 = (col - min_x) as f32;
-            gradient[pos] = (column_f32 / total_len_f32).clamp(0.0, 1.0);
-        }
-    }
-
-    // Inverse values when start_x is on the right.
-    if start_x > end_x {
-        gradient.iter_mut().for_each(|grad| *grad = 1.0 - *grad);
-    }
-
-    gradient
-}
-
-/// Build a simple vertical gradient.
-fn build_vertical_gradient(
-    width: usize,
-    height: usize,
     start_y: i32,
     end_y: i32,
 ) -> Vec<f32> {
-    let min_y = min(start_y, end_y);
-    let max_y = max(start_y, end_y);
-    let total_grad_len = max_y - min_y;
-    let total_size = width * height;
-    let mut gradient = std::iter::repeat(0.0).take(total_size).collect::<Vec<_>>();
-    if total_grad_len <= 0 {
-        // Nothing to do. Return a vector filled with zeros.
-        return gradient;
-    }
-
-    // Fill every row from 0 to the top y with zeros.
-    for row in 0..min_y {
-        for col in 0..width {
-            let pos = (row as usize) * width + col;
-            gradient[pos] = 0.0;
-        }
-    }
-
-    // Fill every row from the bottom y to image height with ones.
-    // If the bottom y is less than 0, start with 0.
-    let first_row = max(max_y, 0) as usize;
-    for row in first_row..height {
-        for col in 0..width {
-            let pos = row * width + col;
-            gradient[pos] = 1.0;
-        }
-    }
-
-    // Build gradient between the top and the bottom y.
-    // Clamp values in such a way that they belong to the visible area.
-    let first_row = max(min_y, 0);
-    let last_row = min(max_y, height as i32);
-    for row in first_row..last_row {
-        for col in 0..width {
-            let pos = (row as usize) * width + col;
-            let total_len_f32 = total_grad_len as f32;
-            let row_f32 = (row - min_y) as f32;
-            gradient[pos] = (row_f32 / total_len_f32).clamp(0.0, 1.0);
-        }
-    }
-
-    // Inverse values when start_y is at the bottom.
-    if start_y > end_y {
-        gradient.iter_mut().for_each(|grad| *grad = 1.0 - *grad);
-    }
-
-    gradient
-}
-
-/// Build an axial gradient.
-fn build_axial_gradient(
-    width: usize,
-    height: usize,
-    start_x: i32,
-    end_x: i32,
-    start_y: i32,
-    end_y: i32,
-) -> Vec<f32> {
-    let len_x = (end_x - start_x) as f32;
-    let len_y = (end_y - start_y) as f32;
-    let total_grad_len = (len_x * len_x + len_y * len_y).sqrt();
-
-    let total_size = width * height;
-    let mut gradient = std::iter::repeat(0.0).take(total_size).collect::<Vec<_>>();
-    if total_grad_len <= 0.0 {
-        // Nothing to do. Return a vector filled with zeros.
-        return gradient;
-    }
-
-    let min_x = min(start_x, end_x) as f32;
-    let max_x = max(start_x, end_x) as f32;
-    let min_y = min(start_y, end_y) as f32;
-    let max_y = max(start_y, end_y) as f32;
-    let len_x_sq = len_x * len_x;
-    let len_y_sq = len_y * len_y;
-    let start_x_f32 = start_x as f32;
-    let start_y_f32 = start_y as f32;
-
-    // Build gradient between start_x, end_x, start_y and end_y.
-    // The idea is to find the foot of perpendicular from each point to the gradient line.
-    // If the foot belongs to the gradient line, find the distance from (start_x, start_y)
-    // to the foot point and divide it by total gradient length.
-    // If the foot exceeds gradient line bounds, fill it with zeros or ones in accordance with
-    // the direction of gradient vector.
-    for row in 0..height {
-        for col in 0..width {
-            let pos = row * width + col;
-            let col_f32 = col as f32;
-            let row_f32 = row as f32;
-            let foot_x = (start_x_f32 * len_y_sq
-                + col_f32 * len_x_sq
-                + len_x * len_y * (row_f32 - start_y_f32))
-                / (len_y_sq + len_x_sq);
-            let foot_y = (len_x * (col_f32 - foot_x)) / len_y + row_f32;
-
-            // Check that found coordinates do not exceed gradient bounds.
-            if min_x <= foot_x && foot_x <= max_x && min_y <= foot_y && foot_y <= max_y {
-                let norm_x = foot_x - start_x_f32;
-                let norm_y = foot_y - start_y_f32;
-                let grad_dist = (norm_x * norm_x + norm_y * norm_y).sqrt();
-                let total_len_f32 = total_grad_len;
-                gradient[pos] = (grad_dist / total_len_f32).clamp(0.0, 1.0);
-            } else {
-                let fill_bottom_right =
-                    start_x < end_x && start_y < end_y && foot_x > max_x;
-                let fill_bottom_left =
-                    start_x > end_x && start_y < end_y && foot_x < min_x;
-                let fill_top_right =
-                    start_x < end_x && start_y > end_y && foot_y < min_y;
-                let fill_top_left = start_x > end_x && start_y > end_y && foot_y < min_y;
-                if fill_bottom_right
-                    || fill_bottom_left
-                    || fill_top_right
-                    || fill_top_left
-                {
-                    gradient[pos] = 1.0;
-                }
-            }
-        }
-    }
-
-    gradient
+    // The opacity runs along the line from the start to the end point, and is
+    // padded with zeros before the start and ones after the end.
+    let start = (start_x as f32, start_y as f32);
+    let end = (end_x as f32, end_y as f32);
+    (0..height)
+        .flat_map(|row| {
+            (0..width).map(move |col| {
+                let t = line_position(start, end, col as f32, row as f32);
+                apply_spread(t, SpreadMode::Pad)
+            })
+        })
+        .collect()
 }
 
 /// Fades one image into another.
@@ -500,14 +399,7 @@ pub fn fade(
     let buf_img2 = &img2.raw_pixels;
     let mut buf_res = Vec::with_capacity(width * height * 4);
 
-    // Determine, which gradient must be built.
-    let gradient = if end_y == start_y {
-        build_horizontal_gradient(width, height, start_x, end_x)
-    } else if start_x == end_x {
-        build_vertical_gradient(width, height, start_y, end_y)
-    } else {
-        build_axial_gradient(width, height, start_x, end_x, start_y, end_y)
-    };
+    let gradient = fade_gradient(width, height, start_x, end_x, start_y, end_y);
 
     for row in 0..height {
         for col in 0..width {
@@ -544,3 +436,202 @@ pub fn fade(
 
     PhotonImage::new(buf_res, img1.width, img1.height)
 }
+
+/// Merge bracketed exposures of the same scene into one well-exposed image, using Mertens
+/// exposure fusion.
+///
+/// Every pixel of every image is weighted by its local contrast, its saturation and how
+/// close it is to mid-grey, and the images are blended with Laplacian pyramids so the
+/// result has no seams. No HDR intermediate or tone mapping is needed. The alpha channels
+/// are averaged with the same weights, without the pyramid.
+///
+/// # Arguments
+/// * `images` - Two or more aligned images of the same size, taken at different exposures.
+/// * `contrast_weight` - Importance of local contrast. 1 is the usual choice.
+/// * `saturation_weight` - Importance of saturation. 1 is the usual choice.
+/// * `exposure_weight` - Importance of being well exposed. 1 is the usual choice.
+///
+/// Returns `None` if fewer than two images are given, or their sizes differ.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::multiple::exposure_fusion;
+/// use photon_rs::native::open_image;
+///
+/// let images = vec![
+///     open_image("under.jpg").expect("File should open"),
+///     open_image("normal.jpg").expect("File should open"),
+///     open_image("over.jpg").expect("File should open"),
+/// ];
+/// let fused = exposure_fusion(images, 1.0, 1.0, 1.0).expect("Images should match");
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn exposure_fusion(
+    images: Vec<PhotonImage>,
+    contrast_weight: f32,
+    saturation_weight: f32,
+    exposure_weight: f32,
+) -> Option<PhotonImage> {
+    // Spread of the well-exposedness curve around mid-grey.
+    const EXPOSURE_SIGMA: f32 = 0.2;
+
+    if images.len() < 2 {
+        return None;
+    }
+    let width = images[0].width as usize;
+    let height = images[0].height as usize;
+    if images
+        .iter()
+        .any(|img| img.width as usize != width || img.height as usize != height)
+    {
+        return None;
+    }
+
+    // Raw weight maps.
+    let mut weights: Vec<Vec<f32>> = Vec::with_capacity(images.len());
+    for img in &images {
+        let rgb: Vec<[f32; 3]> = img
+            .raw_pixels
+            .chunks(4)
+            .map(|px| {
+                [
+                    px[0] as f32 / 255.0,
+                    px[1] as f32 / 255.0,
+                    px[2] as f32 / 255.0,
+                ]
+            })
+            .collect();
+        let gray: Vec<f32> =
+            rgb.iter().map(|px| (px[0] + px[1] + px[2]) / 3.0).collect();
+
+        let mut weight = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let idx = y * width + x;
+                let neighbour = |dx: isize, dy: isize| {
+                    let nx = (x as isize + dx).clamp(0, width as isize - 1) as usize;
+                    let ny = (y as isize + dy).clamp(0, height as isize - 1) as usize;
+                    gray[ny * width + nx]
+                };
+                let contrast = (neighbour(-1, 0)
+                    + neighbour(1, 0)
+                    + neighbour(0, -1)
+                    + neighbour(0, 1)
+                    - 4.0 * gray[idx])
+                    .abs();
+
+                let px = rgb[idx];
+                let mean = (px[0] + px[1] + px[2]) / 3.0;
+                let saturation = (px
+                    .iter()
+                    .map(|val| (val - mean) * (val - mean))
+                    .sum::<f32>()
+                    / 3.0)
+                    .sqrt();
+
+                let exposedness: f32 = px
+                    .iter()
+                    .map(|val| {
+                        (-(val - 0.5) * (val - 0.5)
+                            / (2.0 * EXPOSURE_SIGMA * EXPOSURE_SIGMA))
+                            .exp()
+                    })
+                    .product();
+
+                weight.push(
+                    contrast.powf(contrast_weight)
+                        * saturation.powf(saturation_weight)
+                        * exposedness.powf(exposure_weight)
+                        + 1e-12,
+                );
+            }
+        }
+        weights.push(weight);
+    }
+
+    // Normalise the weights so they sum to one at every pixel.
+    for idx in 0..width * height {
+        let total: f32 = weights.iter().map(|weight| weight[idx]).sum();
+        for weight in weights.iter_mut() {
+            weight[idx] /= total;
+        }
+    }
+
+    // Alpha is blended per pixel with the same weights, so it has no halo to hide.
+    let alpha: Vec<u8> = (0..width * height)
+        .map(|idx| {
+            let val: f32 = images
+                .iter()
+                .zip(weights.iter())
+                .map(|(img, weight)| img.raw_pixels[idx * 4 + 3] as f32 * weight[idx])
+                .sum();
+            val.round().clamp(0.0, 255.0) as u8
+        })
+        .collect();
+
+    let levels = max_levels(width, height);
+    let mut blended: Option<Vec<FloatPlane>> = None;
+
+    for (img, weight) in images.iter().zip(weights) {
+        let image_plane = FloatPlane {
+            data: img
+                .raw_pixels
+                .chunks(4)
+                .flat_map(|px| [px[0] as f32, px[1] as f32, px[2] as f32])
+                .collect(),
+            width,
+            height,
+            channels: 3,
+        };
+        let weight_plane = FloatPlane {
+            data: weight,
+            width,
+            height,
+            channels: 1,
+        };
+
+        let image_pyramid = laplacian_levels(image_plane, levels);
+        let weight_pyramid = gaussian_levels(weight_plane, levels);
+
+        let contribution: Vec<FloatPlane> = image_pyramid
+            .into_iter()
+            .zip(weight_pyramid)
+            .map(|(mut level, weight)| {
+                for (px, w) in level.data.chunks_mut(3).zip(weight.data) {
+                    px.iter_mut().for_each(|val| *val *= w);
+                }
+                level
+            })
+            .collect();
+
+        blended = Some(match blended {
+            None => contribution,
+            Some(mut sum) => {
+                for (sum_level, level) in sum.iter_mut().zip(contribution) {
+                    for (val, add) in sum_level.data.iter_mut().zip(level.data) {
+                        *val += add;
+                    }
+                }
+                sum
+            }
+        });
+    }
+
+    let result = collapse_pyramid(blended?);
+    let raw_pixels = result
+        .data
+        .chunks(3)
+        .zip(alpha)
+        .flat_map(|(px, alpha)| {
+            [
+                px[0].round().clamp(0.0, 255.0) as u8,
+                px[1].round().clamp(0.0, 255.0) as u8,
+                px[2].round().clamp(0.0, 255.0) as u8,
+                alpha,
+            ]
+        })
+        .collect();
+
+    Some(PhotonImage::new(raw_pixels, width as u32, height as u32))
+}
@@ -1,11 +1,26 @@
 //! Native-only functions.
 //! Includes functions that open images from the file-system, etc.,
 
-use image::DynamicImage::ImageRgba8;
-use image::{GenericImageView, ImageBuffer};
-use std::io;
+use image::codecs::hdr::HdrDecoder;
+use image::error::{ParameterError, ParameterErrorKind};
+use image::DynamicImage::{
+    self, ImageLuma16, ImageLuma8, ImageRgb8, ImageRgba16, ImageRgba8,
+};
+use image::{GenericImageView, ImageBuffer, ImageFormat};
+use std::io::{self, BufReader, Read, Seek};
 use std::path::Path;
 // use wasm_bindgen::prelude::*;
+use crate::animation::{Animation, AnimationError};
+use crate::encode::{encode, EncodeError, EncodeOptions, OutputFormat};
+use crate::icc::{convert_colour_profile, IccError, IccProfile};
+use crate::linear::LinearImage;
+use crate::lut::{Lut, LutError};
+use crate::metadata::{apply_orientation, embed_metadata, read_metadata};
+use crate::metadata::{ImageMetadata, MetadataError};
+use crate::storage::{
+    decode_srgb, encode_srgb, PhotonImage16, PhotonImageF32, PhotonImageGray,
+    PhotonImageGray16, PhotonImageRgb,
+};
 use crate::PhotonImage;
 use thiserror::Error;
 
@@ -16,6 +31,28 @@ pub enum Error {
 
     #[error(transparent)]
     IoError(#[from] io::Error),
+
+    #[error(transparent)]
+    LutError(#[from] LutError),
+
+    #[error(transparent)]
+    MetadataError(#[from] MetadataError),
+
+    #[error(transparent)]
+    IccError(#[from] IccError),
+
+    #[error(transparent)]
+    EncodeError(#[from] EncodeError),
+
+    #[error(transparent)]
+    AnimationError(#[from] AnimationError),
+}
+
+/// The error returned when a pixel buffer does not match the dimensions of its image.
+fn dimension_mismatch() -> Error {
+    Error::ImageError(image::ImageError::Parameter(ParameterError::from_kind(
+        ParameterErrorKind::DimensionMismatch,
+    )))
 }
 
 /// Open an image at a given path from the filesystem.
@@ -95,18 +132,267 @@ pub fn save_image<P>(img: PhotonImage, img_path: P) -> Result<(), Error>
 where
     P: AsRef<Path>,
 {
+    if OutputFormat::from_path(&img_path).is_some() {
+        return save_image_with_options(img, img_path, &EncodeOptions::new());
+    }
+
+    // Other formats the image crate can write, such as PNM and TGA.
     let raw_pixels = img.raw_pixels;
     let width = img.width;
     let height = img.height;
 
-    let img_buffer = ImageBuffer::from_vec(width, height, raw_pixels).unwrap();
+    let img_buffer = ImageBuffer::from_vec(width, height, raw_pixels)
+        .ok_or_else(dimension_mismatch)?;
     let dynimage = ImageRgba8(img_buffer);
 
     dynimage.save(img_path)?;
     Ok(())
 }
 
-/// Save the image to a vector of bytes
+/// Save the image to the filesystem at a given path, with options for its format.
+///
+/// The format is chosen from the extension of the path, and can be PNG, JPEG, WebP, GIF,
+/// BMP, TIFF or ICO.
+///
+/// # Arguments
+/// * `img` - The PhotonImage you wish to save.
+/// * `img_path` - Path for the outputted image.
+/// * `options` - Options for the format.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::encode::EncodeOptions;
+/// use photon_rs::native::{open_image, save_image_with_options};
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let mut options = EncodeOptions::new();
+/// options.set_jpeg_quality(75);
+/// save_image_with_options(img, "manipulated_image.jpg", &options).expect("Save failed");
+/// ```
+pub fn save_image_with_options<P>(
+    img: PhotonImage,
+    img_path: P,
+    options: &EncodeOptions,
+) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    let format = output_format(&img_path)?;
+    std::fs::write(img_path, encode(&img, format, options)?)?;
+    Ok(())
+}
+
+/// The format to encode to for the extension of a path.
+fn output_format<P: AsRef<Path>>(img_path: P) -> Result<OutputFormat, Error> {
+    OutputFormat::from_path(&img_path).ok_or_else(|| {
+        EncodeError::Unsupported(format!(
+            "can't choose an output format for {}",
+            img_path.as_ref().display()
+        ))
+        .into()
+    })
+}
+
+/// Open an image at a given path from the filesystem, along with its metadata.
+///
+/// With `auto_orient`, the image is rotated and flipped upright according to its EXIF
+/// orientation, and the returned metadata has its orientation reset to 1.
+///
+/// # Arguments
+/// * `img_path` - Path to the image you wish to edit.
+/// * `auto_orient` - Whether to apply the EXIF orientation to the pixels.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::native::open_image_with_metadata;
+///
+/// let (img, metadata) =
+///     open_image_with_metadata("img.jpg", true).expect("File should open");
+/// println!("Taken on {:?}", metadata.get_date_time());
+/// ```
+pub fn open_image_with_metadata<P>(
+    img_path: P,
+    auto_orient: bool,
+) -> Result<(PhotonImage, ImageMetadata), Error>
+where
+    P: AsRef<Path>,
+{
+    let bytes = std::fs::read(img_path)?;
+    let mut metadata = read_metadata(&bytes);
+    let mut img = open_image_from_bytes(&bytes)?;
+
+    if auto_orient {
+        img = apply_orientation(&img, metadata.get_orientation());
+        metadata.set_orientation(1);
+    }
+    Ok((img, metadata))
+}
+
+/// Save the image to the filesystem at a given path, writing the given metadata into it.
+///
+/// Metadata can be written to JPEG, PNG and WebP files.
+///
+/// # Arguments
+/// * `img` - The PhotonImage you wish to save.
+/// * `img_path` - The path you wish to save the image to.
+/// * `metadata` - The metadata to write, for example as returned by `open_image_with_metadata`.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::native::{open_image_with_metadata, save_image_with_metadata};
+///
+/// let (img, metadata) =
+///     open_image_with_metadata("img.jpg", true).expect("File should open");
+/// save_image_with_metadata(img, "new_image.jpg", &metadata)
+///     .expect("Save failed");
+/// ```
+pub fn save_image_with_metadata<P>(
+    img: PhotonImage,
+    img_path: P,
+    metadata: &ImageMetadata,
+) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    let format = output_format(&img_path)?;
+    let encoded = encode(&img, format, &EncodeOptions::new())?;
+    std::fs::write(img_path, embed_metadata(encoded, metadata)?)?;
+    Ok(())
+}
+
+/// Open an image at a given path from the filesystem, converting its pixels from its
+/// embedded ICC profile into a working colour space.
+///
+/// Images without an embedded profile are treated as sRGB, as are images whose profile
+/// can't be used for conversion, such as CMYK, LUT-based or truncated profiles. The profile
+/// the pixels were converted from is returned.
+///
+/// # Arguments
+/// * `img_path` - Path to the image you wish to edit.
+/// * `working_space` - The colour space to convert the pixels to, usually `IccProfile::srgb()`.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::icc::IccProfile;
+/// use photon_rs::native::open_image_with_profile;
+///
+/// let (img, profile) = open_image_with_profile("img.jpg", &IccProfile::srgb())
+///     .expect("File should open");
+/// println!("Converted from {}", profile.get_description());
+/// ```
+pub fn open_image_with_profile<P>(
+    img_path: P,
+    working_space: &IccProfile,
+) -> Result<(PhotonImage, IccProfile), Error>
+where
+    P: AsRef<Path>,
+{
+    let bytes = std::fs::read(img_path)?;
+    let profile = read_metadata(&bytes)
+        .get_icc_profile()
+        .and_then(|icc| IccProfile::from_bytes(&icc).ok())
+        .unwrap_or_else(IccProfile::srgb);
+
+    let mut img = open_image_from_bytes(&bytes)?;
+    convert_colour_profile(&mut img, &profile, working_space);
+    Ok((img, profile))
+}
+
+/// Save the image to the filesystem at a given path, embedding an ICC profile.
+///
+/// The pixels are written as they are, so they should already be in the profile's colour
+/// space. Profiles can be embedded in JPEG, PNG and WebP files.
+///
+/// # Arguments
+/// * `img` - The PhotonImage you wish to save.
+/// * `img_path` - The path you wish to save the image to.
+/// * `profile` - The colour profile of the image's pixels.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::icc::{convert_colour_profile, IccProfile};
+/// use photon_rs::native::{open_image_with_profile, save_image_with_profile};
+///
+/// let (mut img, profile) = open_image_with_profile("img.jpg", &IccProfile::srgb())
+///     .expect("File should open");
+/// // ... image editing functionality here ...
+/// convert_colour_profile(&mut img, &IccProfile::srgb(), &profile);
+/// save_image_with_profile(img, "new_image.jpg", &profile).expect("Save failed");
+/// ```
+pub fn save_image_with_profile<P>(
+    img: PhotonImage,
+    img_path: P,
+    profile: &IccProfile,
+) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    let mut metadata = ImageMetadata::default();
+    metadata.set_icc_profile(profile.to_bytes());
+    save_image_with_metadata(img, img_path, &metadata)
+}
+
+/// Open an animated GIF, PNG or WebP at a given path from the filesystem.
+/// # Arguments
+/// * `img_path` - Path to the animation you wish to edit.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::native::open_animation;
+///
+/// let animation = open_animation("animation.gif").expect("File should open");
+/// ```
+pub fn open_animation<P>(img_path: P) -> Result<Animation, Error>
+where
+    P: AsRef<Path>,
+{
+    Ok(Animation::decode(&std::fs::read(img_path)?)?)
+}
+
+/// Save an animation to the filesystem at a given path, as a GIF or an animated PNG
+/// depending on the extension.
+/// # Arguments
+/// * `animation` - The Animation you wish to save.
+/// * `img_path` - Path for the outputted animation, ending in `.gif`, `.png` or `.apng`.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::monochrome::grayscale;
+/// use photon_rs::native::{open_animation, save_animation};
+///
+/// let mut animation = open_animation("animation.gif").expect("File should open");
+/// animation.map_frames(grayscale);
+/// save_animation(&animation, "grayscale.png").expect("Save failed");
+/// ```
+pub fn save_animation<P>(animation: &Animation, img_path: P) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    let extension = img_path
+        .as_ref()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    let bytes = match extension.as_deref() {
+        Some("gif") => animation.to_gif()?,
+        Some("png") | Some("apng") => animation.to_apng()?,
+        _ => {
+            return Err(EncodeError::Unsupported(format!(
+                "animations can only be saved as GIF or PNG, not {}",
+                img_path.as_ref().display()
+            ))
+            .into())
+        }
+    };
+    std::fs::write(img_path, bytes)?;
+    Ok(())
+}
+
+/// Save the image to a vector of bytes, encoded as a PNG.
+///
+/// Use `encode::encode` for other formats, or `PhotonImage::get_raw_pixels` for the raw
+/// RGBA pixels.
+///
 /// # Arguments
 /// * img: The PhotonImage you wish to save.
 ///
@@ -117,13 +403,356 @@ where
 /// let img = open_image("img.jpg").expect("File should open");
 /// // Save the image at a vec<u8>
 /// let byt = image_to_bytes(img);
+/// std::fs::write("manipulated_image.png", byt).expect("Save failed");
 /// ```
 pub fn image_to_bytes(img: PhotonImage) -> Vec<u8> {
-    let raw_pixels = img.raw_pixels;
-    let width = img.width;
-    let height = img.height;
+    encode(&img, OutputFormat::Png, &EncodeOptions::new())
+        .expect("PNG encoding into memory should not fail")
+}
 
-    let img_buffer = ImageBuffer::from_vec(width, height, raw_pixels).unwrap();
-    let dynimage = ImageRgba8(img_buffer);
-    dynimage.into_bytes()
+/// Open an image from a reader, such as a socket or an entry in an archive.
+/// The format is guessed from the image data.
+///
+/// # Arguments
+/// * `reader` - Where to read the encoded image from.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::native::open_image_from_reader;
+///
+/// let file = std::fs::File::open("img.jpg").expect("File should open");
+/// let img = open_image_from_reader(file).expect("Image should decode");
+/// ```
+pub fn open_image_from_reader<R: Read + Seek>(reader: R) -> Result<PhotonImage, Error> {
+    let img = image::io::Reader::new(BufReader::new(reader))
+        .with_guessed_format()?
+        .decode()?;
+    let (width, height) = img.dimensions();
+    let raw_pixels = img.to_rgba8().to_vec();
+
+    Ok(PhotonImage {
+        raw_pixels,
+        width,
+        height,
+    })
+}
+
+/// Open an image at a given path from the filesystem, keeping 16 bits per channel.
+/// 8-bit images are widened, so any image format can be opened.
+/// # Arguments
+/// * `img_path` - Path to the image you wish to edit.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::native::open_image16;
+///
+/// let img = open_image16("img.png").expect("File should open");
+/// ```
+pub fn open_image16<P>(img_path: P) -> Result<PhotonImage16, Error>
+where
+    P: AsRef<Path>,
+{
+    let img = image::open(img_path)?;
+    let (width, height) = img.dimensions();
+    let raw_pixels = img.to_rgba16().into_raw();
+
+    Ok(PhotonImage16 {
+        raw_pixels,
+        width,
+        height,
+    })
+}
+
+/// Open an image at a given path from the filesystem as a single-channel greyscale image,
+/// without expanding it to RGBA first. Colour images are converted with the Rec. 709
+/// luminance weights.
+/// # Arguments
+/// * `img_path` - Path to the image you wish to edit.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::native::open_image_gray;
+///
+/// let img = open_image_gray("scan.png").expect("File should open");
+/// ```
+pub fn open_image_gray<P>(img_path: P) -> Result<PhotonImageGray, Error>
+where
+    P: AsRef<Path>,
+{
+    let img = image::open(img_path)?;
+    let (width, height) = img.dimensions();
+    let raw_pixels = img.into_luma8().into_raw();
+
+    Ok(PhotonImageGray {
+        raw_pixels,
+        width,
+        height,
+    })
+}
+
+/// Open an image at a given path from the filesystem as an RGB image, without expanding it
+/// to RGBA first. Any alpha channel is dropped.
+/// # Arguments
+/// * `img_path` - Path to the image you wish to edit.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::native::open_image_rgb;
+///
+/// let img = open_image_rgb("img.jpg").expect("File should open");
+/// ```
+pub fn open_image_rgb<P>(img_path: P) -> Result<PhotonImageRgb, Error>
+where
+    P: AsRef<Path>,
+{
+    let img = image::open(img_path)?;
+    let (width, height) = img.dimensions();
+    let raw_pixels = img.into_rgb8().into_raw();
+
+    Ok(PhotonImageRgb {
+        raw_pixels,
+        width,
+        height,
+    })
+}
+
+/// Decode an image file to RGBA `f32` values. Returns whether the values are linear, as
+/// for high dynamic range formats, rather than sRGB-encoded.
+fn open_rgba32f<P>(img_path: P) -> Result<(Vec<f32>, u32, u32, bool), Error>
+where
+    P: AsRef<Path>,
+{
+    let buffer = std::fs::read(&img_path)?;
+
+    // The generic decoder clamps Radiance HDR files to 8 bits, so decode them directly.
+    if image::guess_format(&buffer).ok() == Some(ImageFormat::Hdr) {
+        let decoder = HdrDecoder::new(io::Cursor::new(buffer))?;
+        let metadata = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()?
+            .into_iter()
+            .flat_map(|px| [px[0], px[1], px[2], 1.0])
+            .collect();
+        return Ok((pixels, metadata.width, metadata.height, true));
+    }
+
+    // Formats without magic bytes, such as TGA, can only be told apart by their extension.
+    let img = match image::guess_format(&buffer) {
+        Ok(format) => image::load_from_memory_with_format(&buffer, format)?,
+        Err(_) => image::open(img_path)?,
+    };
+    let (width, height) = img.dimensions();
+    let is_linear = matches!(
+        img,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    );
+    Ok((img.to_rgba32f().into_raw(), width, height, is_linear))
+}
+
+/// Open an image at a given path from the filesystem as floating-point pixels.
+///
+/// Values are sRGB-encoded, as in a PhotonImage. Linear HDR formats such as Radiance HDR
+/// are encoded on load, and keep values above 1.
+/// # Arguments
+/// * `img_path` - Path to the image you wish to edit.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::native::open_image_f32;
+///
+/// let img = open_image_f32("img.tiff").expect("File should open");
+/// ```
+pub fn open_image_f32<P>(img_path: P) -> Result<PhotonImageF32, Error>
+where
+    P: AsRef<Path>,
+{
+    let (mut raw_pixels, width, height, is_linear) = open_rgba32f(img_path)?;
+
+    if is_linear {
+        for px in raw_pixels.chunks_mut(4) {
+            for val in px.iter_mut().take(3) {
+                *val = encode_srgb(*val);
+            }
+        }
+    }
+
+    Ok(PhotonImageF32 {
+        raw_pixels,
+        width,
+        height,
+    })
+}
+
+/// Open an image at a given path from the filesystem in linear light.
+///
+/// High dynamic range formats such as Radiance HDR keep their values above 1, ready for
+/// `tonemap::tonemap`. Other formats are decoded from sRGB.
+/// # Arguments
+/// * `img_path` - Path to the image you wish to edit.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::native::open_image_linear;
+///
+/// let hdr = open_image_linear("scene.hdr").expect("File should open");
+/// ```
+pub fn open_image_linear<P>(img_path: P) -> Result<LinearImage, Error>
+where
+    P: AsRef<Path>,
+{
+    let (mut pixels, width, height, is_linear) = open_rgba32f(img_path)?;
+
+    if !is_linear {
+        for px in pixels.chunks_mut(4) {
+            for val in px.iter_mut().take(3) {
+                *val = decode_srgb(*val);
+            }
+        }
+    }
+
+    Ok(LinearImage::new(pixels, width, height))
+}
+
+/// Save a 16-bit image to the filesystem at a given path.
+/// Use a format which supports 16 bits per channel, such as PNG or TIFF, to keep the full
+/// precision.
+/// # Arguments
+/// * `img` - The PhotonImage16 you wish to save.
+/// * `img_path` - Path for the outputted image.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::native::{open_image16, save_image16};
+///
+/// let img = open_image16("img.png").expect("File should open");
+/// save_image16(&img, "manipulated_image.png").expect("Save failed");
+/// ```
+pub fn save_image16<P>(img: &PhotonImage16, img_path: P) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    let img_buffer =
+        ImageBuffer::from_vec(img.width, img.height, img.raw_pixels.clone())
+            .ok_or_else(dimension_mismatch)?;
+    let dynimage = ImageRgba16(img_buffer);
+
+    dynimage.save(img_path)?;
+    Ok(())
+}
+
+/// Save a greyscale image to the filesystem at a given path, with a single channel.
+/// # Arguments
+/// * `img` - The PhotonImageGray you wish to save.
+/// * `img_path` - Path for the outputted image.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::monochrome::threshold_gray;
+/// use photon_rs::native::{open_image, save_image_gray};
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let binary = threshold_gray(&img, 128);
+/// save_image_gray(&binary, "binary.png").expect("Save failed");
+/// ```
+pub fn save_image_gray<P>(img: &PhotonImageGray, img_path: P) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    let img_buffer =
+        ImageBuffer::from_vec(img.width, img.height, img.raw_pixels.clone())
+            .ok_or_else(dimension_mismatch)?;
+    ImageLuma8(img_buffer).save(img_path)?;
+    Ok(())
+}
+
+/// Save a 16-bit greyscale image to the filesystem at a given path, with a single channel.
+/// # Arguments
+/// * `img` - The PhotonImageGray16 you wish to save.
+/// * `img_path` - Path for the outputted image.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::native::{open_image16, save_image_gray16};
+/// use photon_rs::storage::PhotonImageGray16;
+///
+/// let img = open_image16("img.png").expect("File should open");
+/// let gray = PhotonImageGray16::from_image16(&img);
+/// save_image_gray16(&gray, "gray.png").expect("Save failed");
+/// ```
+pub fn save_image_gray16<P>(img: &PhotonImageGray16, img_path: P) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    let img_buffer =
+        ImageBuffer::from_vec(img.width, img.height, img.raw_pixels.clone())
+            .ok_or_else(dimension_mismatch)?;
+    ImageLuma16(img_buffer).save(img_path)?;
+    Ok(())
+}
+
+/// Save an RGB image to the filesystem at a given path, without an alpha channel.
+/// # Arguments
+/// * `img` - The PhotonImageRgb you wish to save.
+/// * `img_path` - Path for the outputted image.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::native::{open_image, save_image_rgb};
+/// use photon_rs::storage::PhotonImageRgb;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// save_image_rgb(&PhotonImageRgb::from_photon_image(&img), "opaque.png")
+///     .expect("Save failed");
+/// ```
+pub fn save_image_rgb<P>(img: &PhotonImageRgb, img_path: P) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    let img_buffer =
+        ImageBuffer::from_vec(img.width, img.height, img.raw_pixels.clone())
+            .ok_or_else(dimension_mismatch)?;
+    ImageRgb8(img_buffer).save(img_path)?;
+    Ok(())
+}
+
+/// Open a LUT from an Adobe `.cube` file.
+/// # Arguments
+/// * `lut_path` - Path to the `.cube` file.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::lut::{apply_lut, LutInterpolation};
+/// use photon_rs::native::{open_image, open_lut};
+///
+/// let lut = open_lut("look.cube").expect("LUT should open");
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// apply_lut(&mut img, &lut, LutInterpolation::Tetrahedral, 1.0);
+/// ```
+pub fn open_lut<P>(lut_path: P) -> Result<Lut, Error>
+where
+    P: AsRef<Path>,
+{
+    let text = std::fs::read_to_string(lut_path)?;
+    Ok(Lut::from_cube(&text)?)
+}
+
+/// Save a LUT to the filesystem as an Adobe `.cube` file.
+/// # Arguments
+/// * `lut` - The LUT you wish to save.
+/// * `lut_path` - Path for the outputted `.cube` file.
+///
+/// # Example
+/// ```no_run
+/// use photon_rs::lut::lut_from_filter;
+/// use photon_rs::native::save_lut;
+///
+/// let lut = lut_from_filter("firenze", 33).expect("Filter should exist");
+/// save_lut(&lut, "firenze.cube").expect("Save failed");
+/// ```
+pub fn save_lut<P>(lut: &Lut, lut_path: P) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    std::fs::write(lut_path, lut.to_cube())?;
+    Ok(())
 }
@@ -0,0 +1,358 @@
+//! Gaussian and Laplacian image pyramids, and multi-band blending built on them.
+//!
+//! A Gaussian pyramid holds an image at successively halved resolutions. A Laplacian
+//! pyramid holds the detail lost between each of those levels, plus the coarsest level,
+//! and reconstructs the original image exactly.
+
+use crate::PhotonImage;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "enable_wasm")]
+use wasm_bindgen::prelude::*;
+
+/// A float image used while building pyramids, with `channels` interleaved values per
+/// pixel in row-major order.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct FloatPlane {
+    pub(crate) data: Vec<f32>,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) channels: usize,
+}
+
+impl FloatPlane {
+    fn from_photon_image(photon_image: &PhotonImage) -> FloatPlane {
+        FloatPlane {
+            data: photon_image
+                .raw_pixels
+                .iter()
+                .map(|val| *val as f32)
+                .collect(),
+            width: photon_image.width as usize,
+            height: photon_image.height as usize,
+            channels: 4,
+        }
+    }
+
+    /// Round an RGBA plane to a PhotonImage, adding `offset` to the colour channels.
+    fn to_photon_image(&self, offset: f32) -> PhotonImage {
+        let raw_pixels = self
+            .data
+            .chunks(4)
+            .flat_map(|px| {
+                [
+                    (px[0] + offset).round().clamp(0.0, 255.0) as u8,
+                    (px[1] + offset).round().clamp(0.0, 255.0) as u8,
+                    (px[2] + offset).round().clamp(0.0, 255.0) as u8,
+                    px[3].round().clamp(0.0, 255.0) as u8,
+                ]
+            })
+            .collect();
+
+        PhotonImage::new(raw_pixels, self.width as u32, self.height as u32)
+    }
+
+    fn at(&self, x: usize, y: usize, chan: usize) -> f32 {
+        self.data[(y * self.width + x) * self.channels + chan]
+    }
+
+    /// Blur with a 5-tap binomial kernel, then drop every other row and column.
+    fn downsample(&self) -> FloatPlane {
+        const KERNEL: [f32; 5] =
+            [1.0 / 16.0, 4.0 / 16.0, 6.0 / 16.0, 4.0 / 16.0, 1.0 / 16.0];
+        let (width, height, channels) = (self.width, self.height, self.channels);
+        let clamp = |pos: isize, len: usize| pos.clamp(0, len as isize - 1) as usize;
+
+        let mut horizontal = vec![0.0; self.data.len()];
+        for y in 0..height {
+            for x in 0..width {
+                for chan in 0..channels {
+                    horizontal[(y * width + x) * channels + chan] = KERNEL
+                        .iter()
+                        .enumerate()
+                        .map(|(k, weight)| {
+                            weight
+                                * self.at(
+                                    clamp(x as isize + k as isize - 2, width),
+                                    y,
+                                    chan,
+                                )
+                        })
+                        .sum();
+                }
+            }
+        }
+
+        let new_width = width.div_ceil(2);
+        let new_height = height.div_ceil(2);
+        let mut data = Vec::with_capacity(new_width * new_height * channels);
+        for y in 0..new_height {
+            for x in 0..new_width {
+                for chan in 0..channels {
+                    let val: f32 = KERNEL
+                        .iter()
+                        .enumerate()
+                        .map(|(k, weight)| {
+                            let src_y = clamp((2 * y) as isize + k as isize - 2, height);
+                            weight
+                                * horizontal[(src_y * width + 2 * x) * channels + chan]
+                        })
+                        .sum();
+                    data.push(val);
+                }
+            }
+        }
+
+        FloatPlane {
+            data,
+            width: new_width,
+            height: new_height,
+            channels,
+        }
+    }
+
+    /// Bilinearly enlarge to the given size, which is at most twice the current size.
+    fn upsample(&self, width: usize, height: usize) -> FloatPlane {
+        let channels = self.channels;
+        let mut data = Vec::with_capacity(width * height * channels);
+        for y in 0..height {
+            let src_y = ((y as f32 - 0.5) / 2.0).max(0.0);
+            let y0 = (src_y as usize).min(self.height - 1);
+            let y1 = (y0 + 1).min(self.height - 1);
+            let fy = src_y - y0 as f32;
+            for x in 0..width {
+                let src_x = ((x as f32 - 0.5) / 2.0).max(0.0);
+                let x0 = (src_x as usize).min(self.width - 1);
+                let x1 = (x0 + 1).min(self.width - 1);
+                let fx = src_x - x0 as f32;
+                for chan in 0..channels {
+                    let top =
+                        self.at(x0, y0, chan) * (1.0 - fx) + self.at(x1, y0, chan) * fx;
+                    let bottom =
+                        self.at(x0, y1, chan) * (1.0 - fx) + self.at(x1, y1, chan) * fx;
+                    data.push(top * (1.0 - fy) + bottom * fy);
+                }
+            }
+        }
+
+        FloatPlane {
+            data,
+            width,
+            height,
+            channels,
+        }
+    }
+}
+
+/// The number of levels needed to halve an image down to a single pixel.
+pub(crate) fn max_levels(width: usize, height: usize) -> usize {
+    (width.max(height).max(1) as f32).log2().ceil() as usize + 1
+}
+
+pub(crate) fn gaussian_levels(base: FloatPlane, levels: usize) -> Vec<FloatPlane> {
+    let levels = levels.clamp(1, max_levels(base.width, base.height));
+    let mut pyramid = vec![base];
+    for _ in 1..levels {
+        let next = pyramid[pyramid.len() - 1].downsample();
+        pyramid.push(next);
+    }
+    pyramid
+}
+
+pub(crate) fn laplacian_levels(base: FloatPlane, levels: usize) -> Vec<FloatPlane> {
+    let mut pyramid = gaussian_levels(base, levels);
+    for level in 0..pyramid.len() - 1 {
+        let (width, height) = (pyramid[level].width, pyramid[level].height);
+        let expanded = pyramid[level + 1].upsample(width, height);
+        for (val, coarse) in pyramid[level].data.iter_mut().zip(expanded.data) {
+            *val -= coarse;
+        }
+    }
+    pyramid
+}
+
+pub(crate) fn collapse_pyramid(mut pyramid: Vec<FloatPlane>) -> FloatPlane {
+    let mut result = pyramid.pop().unwrap();
+    while let Some(mut level) = pyramid.pop() {
+        let expanded = result.upsample(level.width, level.height);
+        for (val, coarse) in level.data.iter_mut().zip(expanded.data) {
+            *val += coarse;
+        }
+        result = level;
+    }
+    result
+}
+
+/// A Laplacian pyramid of an image, from the full-resolution detail level down to the
+/// coarsest level.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LaplacianPyramid {
+    levels: Vec<FloatPlane>,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl LaplacianPyramid {
+    /// Get the number of levels in the pyramid.
+    pub fn get_levels(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    /// Get a level of the pyramid as an image, for inspection.
+    /// Detail levels are signed, so they are shown offset around mid-grey. The last level
+    /// is the coarsest image itself, and is returned as is.
+    pub fn get_level(&self, level: u32) -> PhotonImage {
+        let level = (level as usize).min(self.levels.len() - 1);
+        let offset = if level == self.levels.len() - 1 {
+            0.0
+        } else {
+            128.0
+        };
+        self.levels[level].to_photon_image(offset)
+    }
+
+    /// Scale the detail of one level of the pyramid, for example to sharpen or soften
+    /// features of a particular size. The coarsest level can't be scaled.
+    pub fn scale_level(&mut self, level: u32, factor: f32) {
+        let level = level as usize;
+        if level + 1 >= self.levels.len() {
+            return;
+        }
+        for px in self.levels[level].data.chunks_mut(4) {
+            for val in px.iter_mut().take(3) {
+                *val *= factor;
+            }
+        }
+    }
+
+    /// Rebuild the image from the pyramid.
+    pub fn reconstruct(&self) -> PhotonImage {
+        collapse_pyramid(self.levels.clone()).to_photon_image(0.0)
+    }
+}
+
+/// Build a Gaussian pyramid of an image, each level half the size of the one before.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `levels` - Number of levels, including the original image. Capped at the number of
+/// levels needed to reach a single pixel.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::native::open_image;
+/// use photon_rs::pyramid::gaussian_pyramid;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let levels = gaussian_pyramid(&img, 4);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn gaussian_pyramid(photon_image: &PhotonImage, levels: u32) -> Vec<PhotonImage> {
+    gaussian_levels(FloatPlane::from_photon_image(photon_image), levels as usize)
+        .iter()
+        .map(|level| level.to_photon_image(0.0))
+        .collect()
+}
+
+/// Build a Laplacian pyramid of an image.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `levels` - Number of levels, including the coarsest image. Capped at the number of
+/// levels needed to reach a single pixel.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::native::open_image;
+/// use photon_rs::pyramid::laplacian_pyramid;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let mut pyramid = laplacian_pyramid(&img, 5);
+/// // Boost the finest details.
+/// pyramid.scale_level(0, 1.5);
+/// let sharpened = pyramid.reconstruct();
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn laplacian_pyramid(photon_image: &PhotonImage, levels: u32) -> LaplacianPyramid {
+    LaplacianPyramid {
+        levels: laplacian_levels(
+            FloatPlane::from_photon_image(photon_image),
+            levels as usize,
+        ),
+    }
+}
+
+/// Blend two images through a mask, blending each band of detail separately so that
+/// the seam is wide for coarse features and narrow for fine ones.
+///
+/// # Arguments
+/// * `img1` - Image shown where the mask is white. Must be the same size as img2.
+/// * `img2` - Image shown where the mask is black. Must be the same size as img1.
+/// * `mask` - Greyscale mask, the same size as the images. Only the red channel is read.
+/// * `levels` - Number of pyramid levels. More levels give smoother transitions.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::native::open_image;
+/// use photon_rs::pyramid::multiband_blend;
+///
+/// let apple = open_image("apple.jpg").expect("File should open");
+/// let orange = open_image("orange.jpg").expect("File should open");
+/// let mask = open_image("left_half.png").expect("File should open");
+/// let orapple = multiband_blend(&apple, &orange, &mask, 6);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn multiband_blend(
+    img1: &PhotonImage,
+    img2: &PhotonImage,
+    mask: &PhotonImage,
+    levels: u32,
+) -> PhotonImage {
+    if img1.width != img2.width
+        || img1.height != img2.height
+        || img1.width != mask.width
+        || img1.height != mask.height
+    {
+        panic!("Images and mask must have the same size.");
+    }
+
+    let levels = levels as usize;
+    let pyramid1 = laplacian_levels(FloatPlane::from_photon_image(img1), levels);
+    let pyramid2 = laplacian_levels(FloatPlane::from_photon_image(img2), levels);
+    let mask_plane = FloatPlane {
+        data: mask
+            .raw_pixels
+            .iter()
+            .step_by(4)
+            .map(|val| *val as f32 / 255.0)
+            .collect(),
+        width: mask.width as usize,
+        height: mask.height as usize,
+        channels: 1,
+    };
+    let mask_pyramid = gaussian_levels(mask_plane, levels);
+
+    let blended = pyramid1
+        .into_iter()
+        .zip(pyramid2)
+        .zip(mask_pyramid)
+        .map(|((mut level1, level2), mask_level)| {
+            for ((px1, px2), weight) in level1
+                .data
+                .chunks_mut(4)
+                .zip(level2.data.chunks(4))
+                .zip(mask_level.data)
+            {
+                for (val1, val2) in px1.iter_mut().zip(px2) {
+                    *val1 = *val1 * weight + val2 * (1.0 - weight);
+                }
+            }
+            level1
+        })
+        .collect();
+
+    collapse_pyramid(blended).to_photon_image(0.0)
+}
@@ -0,0 +1,407 @@
+//! Colour quantization: generate palettes from images, and remap images to a palette.
+
+use crate::dither::{dither_palette, DitherAlgorithm, DitherOptions};
+use crate::{PhotonImage, Rgb};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[cfg(feature = "enable_wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Maximum number of k-means iterations when refining a palette.
+const KMEANS_ITERATIONS: usize = 10;
+
+/// Algorithm used to choose a palette for an image.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuantizeMethod {
+    /// Repeatedly split the box of colours with the widest range at its median.
+    MedianCut = 1,
+    /// Merge the least common branches of an octree of colours.
+    Octree = 2,
+    /// Refine a median cut palette with k-means clustering. Slowest, but most accurate.
+    KMeans = 3,
+}
+
+/// A palette of colours.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    colours: Vec<[u8; 3]>,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl Palette {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create a palette from a list of colours.
+    pub fn new(colours: Vec<Rgb>) -> Palette {
+        Palette {
+            colours: colours
+                .iter()
+                .map(|colour| [colour.get_red(), colour.get_green(), colour.get_blue()])
+                .collect(),
+        }
+    }
+
+    /// The 216 colours of the web-safe palette.
+    pub fn web_safe() -> Palette {
+        let levels = [0, 51, 102, 153, 204, 255];
+        let mut colours = Vec::with_capacity(216);
+        for r in levels {
+            for g in levels {
+                for b in levels {
+                    colours.push([r, g, b]);
+                }
+            }
+        }
+        Palette { colours }
+    }
+
+    /// The four greens of the original Game Boy.
+    pub fn game_boy() -> Palette {
+        Palette {
+            colours: vec![[15, 56, 15], [48, 98, 48], [139, 172, 15], [155, 188, 15]],
+        }
+    }
+
+    /// The 16 colours of the CGA palette.
+    pub fn cga() -> Palette {
+        let colours = [
+            0x000000, 0x0000AA, 0x00AA00, 0x00AAAA, 0xAA0000, 0xAA00AA, 0xAA5500,
+            0xAAAAAA, 0x555555, 0x5555FF, 0x55FF55, 0x55FFFF, 0xFF5555, 0xFF55FF,
+            0xFFFF55, 0xFFFFFF,
+        ];
+        Palette {
+            colours: colours
+                .iter()
+                .map(|hex: &u32| [(hex >> 16) as u8, (hex >> 8) as u8, *hex as u8])
+                .collect(),
+        }
+    }
+
+    /// Get the colours of the palette.
+    pub fn get_colours(&self) -> Vec<Rgb> {
+        self.colours
+            .iter()
+            .map(|[r, g, b]| Rgb::new(*r, *g, *b))
+            .collect()
+    }
+
+    /// Get the number of colours in the palette.
+    pub fn len(&self) -> usize {
+        self.colours.len()
+    }
+
+    /// Whether the palette has no colours.
+    pub fn is_empty(&self) -> bool {
+        self.colours.is_empty()
+    }
+}
+
+impl Palette {
+    /// The colours of the palette as RGB triples.
+    pub(crate) fn entries(&self) -> &[[u8; 3]] {
+        &self.colours
+    }
+
+    /// Index of the palette colour nearest to `colour`.
+    pub(crate) fn nearest(&self, colour: [f32; 3]) -> usize {
+        let mut best = (0, f32::MAX);
+        for (idx, entry) in self.colours.iter().enumerate() {
+            let dist: f32 = (0..3)
+                .map(|chan| (entry[chan] as f32 - colour[chan]).powi(2))
+                .sum();
+            if dist < best.1 {
+                best = (idx, dist);
+            }
+        }
+        best.0
+    }
+}
+
+/// The distinct colours of an image, with how many pixels have each colour.
+fn histogram(photon_image: &PhotonImage) -> Vec<([u8; 3], u32)> {
+    let mut counts: HashMap<[u8; 3], u32> = HashMap::new();
+    for px in photon_image.raw_pixels.chunks_exact(4) {
+        *counts.entry([px[0], px[1], px[2]]).or_insert(0) += 1;
+    }
+    let mut colours: Vec<([u8; 3], u32)> = counts.into_iter().collect();
+    // Sort so that palettes don't depend on the hash map's ordering.
+    colours.sort_unstable();
+    colours
+}
+
+/// Weighted mean of a set of colours.
+fn mean_colour(colours: &[([u8; 3], u32)]) -> [u8; 3] {
+    let mut sums = [0_u64; 3];
+    let mut total = 0_u64;
+    for (colour, count) in colours {
+        for chan in 0..3 {
+            sums[chan] += colour[chan] as u64 * *count as u64;
+        }
+        total += *count as u64;
+    }
+    let total = total.max(1);
+    [
+        ((sums[0] + total / 2) / total) as u8,
+        ((sums[1] + total / 2) / total) as u8,
+        ((sums[2] + total / 2) / total) as u8,
+    ]
+}
+
+fn median_cut(mut colours: Vec<([u8; 3], u32)>, size: usize) -> Vec<[u8; 3]> {
+    // Boxes are ranges of `colours`, which is reordered as they are split.
+    let mut boxes = Vec::with_capacity(size);
+    boxes.push(0..colours.len());
+    while boxes.len() < size {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, range)| range.len() > 1)
+            .map(|(idx, range)| {
+                let slice = &colours[range.clone()];
+                let (chan, extent) = (0..3)
+                    .map(|chan| {
+                        let min = slice.iter().map(|(c, _)| c[chan]).min().unwrap();
+                        let max = slice.iter().map(|(c, _)| c[chan]).max().unwrap();
+                        (chan, max - min)
+                    })
+                    .max_by_key(|(_, extent)| *extent)
+                    .unwrap();
+                (idx, chan, extent)
+            })
+            .max_by_key(|(_, _, extent)| *extent);
+        let Some((idx, chan, _)) = widest else {
+            break;
+        };
+
+        let range = boxes.swap_remove(idx);
+        let slice = &mut colours[range.clone()];
+        slice.sort_unstable_by_key(|(colour, _)| colour[chan]);
+        // Split at the median pixel, keeping at least one colour on each side.
+        let half = slice.iter().map(|(_, count)| *count as u64).sum::<u64>() / 2;
+        let mut seen = 0;
+        let mut split = 1;
+        for (pos, (_, count)) in slice.iter().enumerate() {
+            seen += *count as u64;
+            if seen >= half {
+                split = (pos + 1).clamp(1, slice.len() - 1);
+                break;
+            }
+        }
+        boxes.push(range.start..range.start + split);
+        boxes.push(range.start + split..range.end);
+    }
+
+    boxes
+        .into_iter()
+        .map(|range| mean_colour(&colours[range]))
+        .collect()
+}
+
+#[derive(Default, Clone)]
+struct OctreeNode {
+    children: [Option<usize>; 8],
+    sums: [u64; 3],
+    count: u64,
+    leaf: bool,
+}
+
+fn octree(colours: &[([u8; 3], u32)], size: usize) -> Vec<[u8; 3]> {
+    const DEPTH: usize = 8;
+    let mut nodes = vec![OctreeNode::default()];
+    // Nodes at each depth, used to merge the deepest branches first.
+    let mut levels: Vec<Vec<usize>> = vec![Vec::new(); DEPTH];
+
+    for (colour, count) in colours {
+        let mut node = 0;
+        for depth in 0..=DEPTH {
+            let current = &mut nodes[node];
+            for (sum, val) in current.sums.iter_mut().zip(colour) {
+                *sum += *val as u64 * *count as u64;
+            }
+            current.count += *count as u64;
+            if depth == DEPTH {
+                current.leaf = true;
+                break;
+            }
+
+            let shift = 7 - depth;
+            let child = (((colour[0] >> shift) & 1) << 2
+                | ((colour[1] >> shift) & 1) << 1
+                | ((colour[2] >> shift) & 1)) as usize;
+            node = match nodes[node].children[child] {
+                Some(existing) => existing,
+                None => {
+                    nodes.push(OctreeNode::default());
+                    let created = nodes.len() - 1;
+                    nodes[node].children[child] = Some(created);
+                    if depth + 1 < DEPTH {
+                        levels[depth + 1].push(created);
+                    }
+                    created
+                }
+            };
+        }
+    }
+    levels[0].push(0);
+
+    let mut leaves = colours.len();
+    for depth in (0..DEPTH).rev() {
+        // Merge the least common branches into their parents until few enough remain.
+        let mut level = std::mem::take(&mut levels[depth]);
+        level.sort_by_key(|idx| nodes[*idx].count);
+        for idx in level {
+            if leaves <= size {
+                break;
+            }
+            let children: Vec<usize> =
+                nodes[idx].children.iter().flatten().copied().collect();
+            leaves = leaves + 1 - children.len();
+            nodes[idx].children = [None; 8];
+            nodes[idx].leaf = true;
+        }
+    }
+
+    let mut palette = Vec::with_capacity(leaves);
+    let mut stack = vec![0];
+    while let Some(idx) = stack.pop() {
+        let node = &nodes[idx];
+        if node.leaf {
+            let count = node.count.max(1);
+            palette.push([
+                ((node.sums[0] + count / 2) / count) as u8,
+                ((node.sums[1] + count / 2) / count) as u8,
+                ((node.sums[2] + count / 2) / count) as u8,
+            ]);
+        } else {
+            stack.extend(node.children.iter().flatten());
+        }
+    }
+    palette
+}
+
+fn kmeans(colours: &[([u8; 3], u32)], size: usize) -> Vec<[u8; 3]> {
+    let mut palette = Palette {
+        colours: median_cut(colours.to_vec(), size),
+    };
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut clusters: Vec<Vec<([u8; 3], u32)>> = vec![Vec::new(); palette.len()];
+        for (colour, count) in colours {
+            let nearest = palette.nearest(colour.map(|val| val as f32));
+            clusters[nearest].push((*colour, *count));
+        }
+        let refined: Vec<[u8; 3]> = clusters
+            .iter()
+            .zip(&palette.colours)
+            .map(|(cluster, old)| {
+                if cluster.is_empty() {
+                    *old
+                } else {
+                    mean_colour(cluster)
+                }
+            })
+            .collect();
+        if refined == palette.colours {
+            break;
+        }
+        palette.colours = refined;
+    }
+    palette.colours
+}
+
+/// Generate a palette of up to `colours` colours which represents an image well.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `colours` - The maximum number of colours in the palette, from 1 to 256.
+/// * `method` - The quantization algorithm.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::native::open_image;
+/// use photon_rs::quantize::{generate_palette, QuantizeMethod};
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let palette = generate_palette(&img, 16, QuantizeMethod::KMeans);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn generate_palette(
+    photon_image: &PhotonImage,
+    colours: u32,
+    method: QuantizeMethod,
+) -> Palette {
+    let size = colours.clamp(1, 256) as usize;
+    let histogram = histogram(photon_image);
+    if histogram.len() <= size {
+        return Palette {
+            colours: histogram.into_iter().map(|(colour, _)| colour).collect(),
+        };
+    }
+
+    let mut colours = match method {
+        QuantizeMethod::MedianCut => median_cut(histogram, size),
+        QuantizeMethod::Octree => octree(&histogram, size),
+        QuantizeMethod::KMeans => kmeans(&histogram, size),
+    };
+    colours.sort_unstable();
+    colours.dedup();
+    Palette { colours }
+}
+
+/// Replace every pixel of an image with a colour from a palette. Alpha is unchanged.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `palette` - The palette to use, generated or fixed.
+/// * `dither` - The dithering algorithm, which hides banding between palette colours.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::dither::DitherAlgorithm;
+/// use photon_rs::native::open_image;
+/// use photon_rs::quantize::{remap_to_palette, Palette};
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// remap_to_palette(&mut img, &Palette::game_boy(), DitherAlgorithm::Bayer4);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn remap_to_palette(
+    photon_image: &mut PhotonImage,
+    palette: &Palette,
+    dither: DitherAlgorithm,
+) {
+    dither_palette(photon_image, palette, &DitherOptions::new(dither));
+}
+
+/// Reduce an image to at most `colours` colours, generating a palette and remapping the
+/// image to it.
+///
+/// # Arguments
+/// * `photon_image` - A PhotonImage.
+/// * `colours` - The maximum number of colours, from 1 to 256.
+/// * `method` - The quantization algorithm.
+/// * `dither` - The dithering algorithm.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::dither::DitherAlgorithm;
+/// use photon_rs::native::open_image;
+/// use photon_rs::quantize::{quantize, QuantizeMethod};
+///
+/// let mut img = open_image("img.jpg").expect("File should open");
+/// quantize(&mut img, 8, QuantizeMethod::Octree, DitherAlgorithm::FloydSteinberg);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn quantize(
+    photon_image: &mut PhotonImage,
+    colours: u32,
+    method: QuantizeMethod,
+    dither: DitherAlgorithm,
+) {
+    let palette = generate_palette(photon_image, colours, method);
+    remap_to_palette(photon_image, &palette, dither);
+}
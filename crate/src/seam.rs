@@ -0,0 +1,334 @@
+//! Seam carving engine: finds and removes or inserts low-energy seams, with protection and
+//! removal masks, and forward or backward energy.
+
+use crate::PhotonImage;
+
+/// Bias added to protected pixels, so seams only pass through them when there's no other way.
+const PROTECT_BIAS: f32 = 1e6;
+
+/// Bias subtracted from pixels marked for removal, so seams pass through them first.
+const REMOVE_BIAS: f32 = 1e7;
+
+/// An image being carved, with the per-pixel state needed to find seams. Seams always run
+/// from top to bottom; rows are carved by transposing first.
+pub(crate) struct Carver {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+    /// Luminance of every pixel.
+    gray: Vec<f32>,
+    /// Gradient magnitude of every pixel, only kept up to date for backward energy.
+    energy: Vec<f32>,
+    /// Protection and removal bias of every pixel.
+    bias: Vec<f32>,
+    forward: bool,
+}
+
+/// Remove one element per row of a row-major buffer, at the column given by the seam.
+fn remove_seam_from<T: Copy>(data: &mut Vec<T>, width: usize, seam: &[usize]) {
+    let mut write = 0;
+    for (y, skip) in seam.iter().enumerate() {
+        for x in 0..width {
+            if x != *skip {
+                data[write] = data[y * width + x];
+                write += 1;
+            }
+        }
+    }
+    data.truncate(write);
+}
+
+impl Carver {
+    /// Set up an image for carving. Masks select pixels where they are light (over half
+    /// brightness) and opaque.
+    pub(crate) fn new(
+        photon_img: &PhotonImage,
+        protect: Option<&PhotonImage>,
+        remove: Option<&PhotonImage>,
+        forward: bool,
+    ) -> Carver {
+        let width = photon_img.get_width() as usize;
+        let height = photon_img.get_height() as usize;
+        let pixels: Vec<[u8; 4]> = photon_img
+            .raw_pixels
+            .chunks_exact(4)
+            .map(|px| [px[0], px[1], px[2], px[3]])
+            .collect();
+        let gray = pixels
+            .iter()
+            .map(|px| 0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32)
+            .collect();
+
+        let mask = |mask: Option<&PhotonImage>| -> Vec<bool> {
+            match mask {
+                Some(mask) => {
+                    if mask.get_width() as usize != width
+                        || mask.get_height() as usize != height
+                    {
+                        panic!("Masks must have the same size as the image.");
+                    }
+                    mask.raw_pixels
+                        .chunks_exact(4)
+                        .map(|px| {
+                            px[0] as u32 + px[1] as u32 + px[2] as u32 >= 384
+                                && px[3] >= 128
+                        })
+                        .collect()
+                }
+                None => vec![false; width * height],
+            }
+        };
+        let bias = mask(protect)
+            .into_iter()
+            .zip(mask(remove))
+            .map(|(protect, remove)| match (protect, remove) {
+                (_, true) => -REMOVE_BIAS,
+                (true, false) => PROTECT_BIAS,
+                (false, false) => 0.0,
+            })
+            .collect();
+
+        let mut carver = Carver {
+            width,
+            height,
+            pixels,
+            gray,
+            energy: Vec::new(),
+            bias,
+            forward,
+        };
+        carver.compute_energy();
+        carver
+    }
+
+    /// Get the carved image.
+    pub(crate) fn into_image(self) -> PhotonImage {
+        PhotonImage::new(
+            self.pixels.into_iter().flatten().collect(),
+            self.width as u32,
+            self.height as u32,
+        )
+    }
+
+    /// Swap rows and columns, so seams run the other way.
+    pub(crate) fn transpose(&mut self) {
+        fn transposed<T: Copy>(data: &[T], width: usize, height: usize) -> Vec<T> {
+            (0..width)
+                .flat_map(|x| (0..height).map(move |y| data[y * width + x]))
+                .collect()
+        }
+        let (width, height) = (self.width, self.height);
+        self.pixels = transposed(&self.pixels, width, height);
+        self.gray = transposed(&self.gray, width, height);
+        if !self.forward {
+            self.energy = transposed(&self.energy, width, height);
+        }
+        self.bias = transposed(&self.bias, width, height);
+        self.width = height;
+        self.height = width;
+    }
+
+    fn energy_at(&self, x: usize, y: usize) -> f32 {
+        let gray = |x: usize, y: usize| self.gray[y * self.width + x];
+        let (left, right) = (x.saturating_sub(1), (x + 1).min(self.width - 1));
+        let (up, down) = (y.saturating_sub(1), (y + 1).min(self.height - 1));
+        (gray(right, y) - gray(left, y)).abs() + (gray(x, down) - gray(x, up)).abs()
+    }
+
+    fn compute_energy(&mut self) {
+        if self.forward {
+            return;
+        }
+        self.energy = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.energy_at(x, y))
+            .collect();
+    }
+
+    /// Find the vertical seam with the lowest total cost, as the column in every row.
+    fn find_seam(&self) -> Vec<usize> {
+        let (width, height) = (self.width, self.height);
+        let gray = |x: usize, y: usize| self.gray[y * width + x] as f64;
+        let mut cost = vec![0.0_f64; width * height];
+        let mut from = vec![0_u8; width * height];
+
+        for y in 0..height {
+            for x in 0..width {
+                let idx = y * width + x;
+                let (left, right) = (x.saturating_sub(1), (x + 1).min(width - 1));
+                // Forward energy is the cost of the new edges created by removing the pixel,
+                // which depends on which pixel above the seam comes from.
+                let (cost_left, cost_up, cost_right) = if self.forward {
+                    let up = (gray(right, y) - gray(left, y)).abs();
+                    if y == 0 {
+                        (up, up, up)
+                    } else {
+                        (
+                            up + (gray(x, y - 1) - gray(left, y)).abs(),
+                            up,
+                            up + (gray(x, y - 1) - gray(right, y)).abs(),
+                        )
+                    }
+                } else {
+                    let energy = self.energy[idx] as f64;
+                    (energy, energy, energy)
+                };
+
+                let mut best = (0.0, 1);
+                if y > 0 {
+                    let above = (y - 1) * width;
+                    best = (cost[above + x] + cost_up, 1);
+                    if x > 0 && cost[above + x - 1] + cost_left < best.0 {
+                        best = (cost[above + x - 1] + cost_left, 0);
+                    }
+                    if x + 1 < width && cost[above + x + 1] + cost_right < best.0 {
+                        best = (cost[above + x + 1] + cost_right, 2);
+                    }
+                } else {
+                    best.0 = cost_up;
+                }
+                cost[idx] = best.0 + self.bias[idx] as f64;
+                from[idx] = best.1;
+            }
+        }
+
+        let last = (height - 1) * width;
+        let mut x = (0..width)
+            .min_by(|a, b| cost[last + a].total_cmp(&cost[last + b]))
+            .unwrap();
+        let mut seam = vec![0; height];
+        for y in (0..height).rev() {
+            seam[y] = x;
+            x = x + from[y * width + x] as usize - 1;
+        }
+        seam
+    }
+
+    /// Remove a seam, updating the energy of only the pixels next to it.
+    fn remove_seam(&mut self, seam: &[usize]) {
+        let width = self.width;
+        remove_seam_from(&mut self.pixels, width, seam);
+        remove_seam_from(&mut self.gray, width, seam);
+        remove_seam_from(&mut self.bias, width, seam);
+        self.width -= 1;
+        if self.forward {
+            return;
+        }
+
+        remove_seam_from(&mut self.energy, width, seam);
+        for y in 0..self.height {
+            // The neighbours of pixels near the seam in this row, or the rows next to it,
+            // have changed.
+            let rows = y.saturating_sub(1)..(y + 2).min(self.height);
+            let start = seam[rows.clone()].iter().min().unwrap().saturating_sub(1);
+            let end = (seam[rows].iter().max().unwrap() + 1).min(self.width);
+            for x in start..end {
+                self.energy[y * self.width + x] = self.energy_at(x, y);
+            }
+        }
+    }
+
+    /// Whether any pixels marked for removal remain.
+    fn has_marked(&self) -> bool {
+        self.bias.iter().any(|bias| *bias < 0.0)
+    }
+
+    /// Remove seams until no pixels marked for removal remain. Seams run along rows rather
+    /// than columns if the marked area is wider than it is tall, which removes fewer seams.
+    pub(crate) fn remove_marked(&mut self) {
+        let (mut min_x, mut max_x, mut min_y, mut max_y) =
+            (usize::MAX, 0, usize::MAX, 0);
+        for (idx, bias) in self.bias.iter().enumerate() {
+            if *bias < 0.0 {
+                let (x, y) = (idx % self.width, idx / self.width);
+                min_x = min_x.min(x);
+                max_x = max_x.max(x);
+                min_y = min_y.min(y);
+                max_y = max_y.max(y);
+            }
+        }
+        if min_x > max_x {
+            return;
+        }
+
+        let rows = max_x - min_x > max_y - min_y;
+        if rows {
+            self.transpose();
+        }
+        while self.has_marked() && self.width > 1 {
+            let seam = self.find_seam();
+            self.remove_seam(&seam);
+        }
+        if rows {
+            self.transpose();
+        }
+    }
+
+    /// Remove or insert vertical seams until the image has the given width.
+    pub(crate) fn resize_width(&mut self, width: usize) {
+        let width = width.max(1);
+        while self.width > width {
+            let seam = self.find_seam();
+            self.remove_seam(&seam);
+        }
+        while self.width < width {
+            // Inserting a seam next to one which was just inserted would stretch the same
+            // area, so at most half the width is inserted at a time.
+            let count = (width - self.width).min((self.width / 2).max(1));
+            self.insert_seams(count);
+        }
+    }
+
+    /// Insert the `count` lowest energy seams, each next to the seam it was found at.
+    fn insert_seams(&mut self, count: usize) {
+        // Find the seams on a copy, tracking the original column of every pixel.
+        let mut copy = Carver {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.clone(),
+            gray: self.gray.clone(),
+            energy: self.energy.clone(),
+            bias: self.bias.clone(),
+            forward: self.forward,
+        };
+        let mut origin: Vec<usize> =
+            (0..self.height).flat_map(|_| 0..self.width).collect();
+        let mut duplicate = vec![false; self.width * self.height];
+        for _ in 0..count {
+            let seam = copy.find_seam();
+            for (y, x) in seam.iter().enumerate() {
+                duplicate[y * self.width + origin[y * copy.width + x]] = true;
+            }
+            remove_seam_from(&mut origin, copy.width, &seam);
+            copy.remove_seam(&seam);
+        }
+
+        let new_width = self.width + count;
+        let mut pixels = Vec::with_capacity(new_width * self.height);
+        let mut gray = Vec::with_capacity(new_width * self.height);
+        let mut bias = Vec::with_capacity(new_width * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let idx = y * self.width + x;
+                pixels.push(self.pixels[idx]);
+                gray.push(self.gray[idx]);
+                bias.push(self.bias[idx]);
+                if duplicate[idx] {
+                    // The new pixel is the average of the seam and its right neighbour.
+                    let next = y * self.width + (x + 1).min(self.width - 1);
+                    let (a, b) = (self.pixels[idx], self.pixels[next]);
+                    pixels.push(
+                        [0, 1, 2, 3].map(|c| ((a[c] as u16 + b[c] as u16) / 2) as u8),
+                    );
+                    gray.push((self.gray[idx] + self.gray[next]) / 2.0);
+                    bias.push(self.bias[idx]);
+                }
+            }
+        }
+        self.pixels = pixels;
+        self.gray = gray;
+        self.bias = bias;
+        self.width = new_width;
+        self.compute_energy();
+    }
+}
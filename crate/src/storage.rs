@@ -0,0 +1,473 @@
+//! Images with more precision than the 8 bits per channel of `PhotonImage`.
+//!
+//! `PhotonImage16` holds 16-bit RGBA pixels, and keeps the full precision of 16-bit PNG and
+//! TIFF files. `PhotonImageF32` holds floating-point RGBA pixels, and is the format to use
+//! when chaining several edits, since rounding to 8 bits after every step causes banding.
+//! Both store sRGB-encoded values, like `PhotonImage`; see `linear::LinearImage` for linear
+//! light.
+//!
+//! The `_f32` edits in `colour_spaces`, `effects` and `lut` sit next to, and share their
+//! formulas with, the 8-bit functions of the same name. To chain edits on a
+//! `PhotonImage16`, convert it with `to_image_f32`, apply them, and convert back with
+//! `PhotonImage16::from_image_f32`, which keeps the full 16-bit precision.
+//!
+//! `PhotonImageGray`, `PhotonImageGray16` and `PhotonImageRgb` drop the channels a
+//! greyscale or opaque image doesn't need, so they take a quarter or three quarters of
+//! the memory of a PhotonImage.
+
+use crate::linear::LinearImage;
+use crate::PhotonImage;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "enable_wasm")]
+use wasm_bindgen::prelude::*;
+
+/// An image with 16-bit RGBA pixels.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PhotonImage16 {
+    pub(crate) raw_pixels: Vec<u16>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl PhotonImage16 {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create a new PhotonImage16 from a Vec of 16-bit RGBA values.
+    ///
+    /// Panics if `raw_pixels` does not hold exactly `width * height * 4` values.
+    pub fn new(raw_pixels: Vec<u16>, width: u32, height: u32) -> PhotonImage16 {
+        if raw_pixels.len() != width as usize * height as usize * 4 {
+            panic!("Pixel buffer must hold width * height * 4 values.");
+        }
+        PhotonImage16 {
+            raw_pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Widen an 8-bit PhotonImage to 16 bits per channel.
+    pub fn from_photon_image(photon_image: &PhotonImage) -> PhotonImage16 {
+        let raw_pixels = photon_image
+            .raw_pixels
+            .iter()
+            .map(|val| *val as u16 * 257)
+            .collect();
+
+        PhotonImage16 {
+            raw_pixels,
+            width: photon_image.width,
+            height: photon_image.height,
+        }
+    }
+
+    /// Round the image to 8 bits per channel.
+    pub fn to_photon_image(&self) -> PhotonImage {
+        let raw_pixels = self
+            .raw_pixels
+            .iter()
+            .map(|val| ((*val as u32 + 128) / 257) as u8)
+            .collect();
+
+        PhotonImage::new(raw_pixels, self.width, self.height)
+    }
+
+    /// Convert a floating-point image to 16 bits per channel, clamping values outside 0 to 1.
+    pub fn from_image_f32(img: &PhotonImageF32) -> PhotonImage16 {
+        let raw_pixels = img
+            .raw_pixels
+            .iter()
+            .map(|val| (val.clamp(0.0, 1.0) * 65535.0).round() as u16)
+            .collect();
+
+        PhotonImage16 {
+            raw_pixels,
+            width: img.width,
+            height: img.height,
+        }
+    }
+
+    /// Convert the image to floating point, without any loss of precision.
+    pub fn to_image_f32(&self) -> PhotonImageF32 {
+        let raw_pixels = self
+            .raw_pixels
+            .iter()
+            .map(|val| *val as f32 / 65535.0)
+            .collect();
+
+        PhotonImageF32::new(raw_pixels, self.width, self.height)
+    }
+
+    /// Get the width of the image.
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the image.
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the 16-bit RGBA values of the image.
+    pub fn get_raw_pixels(&self) -> Vec<u16> {
+        self.raw_pixels.clone()
+    }
+}
+
+/// An image with floating-point RGBA pixels, nominally from 0 to 1.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PhotonImageF32 {
+    pub(crate) raw_pixels: Vec<f32>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl PhotonImageF32 {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create a new PhotonImageF32 from a Vec of sRGB-encoded RGBA values.
+    ///
+    /// Panics if `raw_pixels` does not hold exactly `width * height * 4` values.
+    pub fn new(raw_pixels: Vec<f32>, width: u32, height: u32) -> PhotonImageF32 {
+        if raw_pixels.len() != width as usize * height as usize * 4 {
+            panic!("Pixel buffer must hold width * height * 4 values.");
+        }
+        PhotonImageF32 {
+            raw_pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Convert an 8-bit PhotonImage to floating point.
+    pub fn from_photon_image(photon_image: &PhotonImage) -> PhotonImageF32 {
+        let raw_pixels = photon_image
+            .raw_pixels
+            .iter()
+            .map(|val| *val as f32 / 255.0)
+            .collect();
+
+        PhotonImageF32::new(raw_pixels, photon_image.width, photon_image.height)
+    }
+
+    /// Round the image to 8 bits per channel, clamping values outside 0 to 1.
+    pub fn to_photon_image(&self) -> PhotonImage {
+        let raw_pixels = self
+            .raw_pixels
+            .iter()
+            .map(|val| (val.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect();
+
+        PhotonImage::new(raw_pixels, self.width, self.height)
+    }
+
+    /// Convert a linear-light image to sRGB-encoded floating point.
+    /// Values above 1 are kept.
+    pub fn from_linear(img: &LinearImage) -> PhotonImageF32 {
+        let raw_pixels = img
+            .pixels
+            .chunks(4)
+            .flat_map(|px| {
+                [
+                    encode_srgb(px[0]),
+                    encode_srgb(px[1]),
+                    encode_srgb(px[2]),
+                    px[3],
+                ]
+            })
+            .collect();
+
+        PhotonImageF32::new(raw_pixels, img.width, img.height)
+    }
+
+    /// Convert the image to linear light. Values above 1 are kept.
+    pub fn to_linear(&self) -> LinearImage {
+        let pixels = self
+            .raw_pixels
+            .chunks(4)
+            .flat_map(|px| {
+                [
+                    decode_srgb(px[0]),
+                    decode_srgb(px[1]),
+                    decode_srgb(px[2]),
+                    px[3],
+                ]
+            })
+            .collect();
+
+        LinearImage::new(pixels, self.width, self.height)
+    }
+
+    /// Get the width of the image.
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the image.
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the RGBA values of the image.
+    pub fn get_raw_pixels(&self) -> Vec<f32> {
+        self.raw_pixels.clone()
+    }
+}
+
+/// An image with a single 8-bit luma channel.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PhotonImageGray {
+    pub(crate) raw_pixels: Vec<u8>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl PhotonImageGray {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create a new PhotonImageGray from a Vec of luma values.
+    ///
+    /// Panics if `raw_pixels` does not hold exactly `width * height` values.
+    pub fn new(raw_pixels: Vec<u8>, width: u32, height: u32) -> PhotonImageGray {
+        if raw_pixels.len() != width as usize * height as usize {
+            panic!("Pixel buffer must hold width * height values.");
+        }
+        PhotonImageGray {
+            raw_pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Convert a PhotonImage to greyscale using Rec. 709 luma weights, dropping alpha.
+    /// Images which are already grey keep their exact values.
+    pub fn from_photon_image(photon_image: &PhotonImage) -> PhotonImageGray {
+        let raw_pixels = photon_image
+            .raw_pixels
+            .chunks(4)
+            .map(|px| {
+                let luma = 0.2126 * px[0] as f32
+                    + 0.7152 * px[1] as f32
+                    + 0.0722 * px[2] as f32;
+                luma.round().clamp(0.0, 255.0) as u8
+            })
+            .collect();
+
+        PhotonImageGray::new(raw_pixels, photon_image.width, photon_image.height)
+    }
+
+    /// Expand the image to an opaque RGBA PhotonImage.
+    pub fn to_photon_image(&self) -> PhotonImage {
+        let raw_pixels = self
+            .raw_pixels
+            .iter()
+            .flat_map(|val| [*val, *val, *val, 255])
+            .collect();
+
+        PhotonImage::new(raw_pixels, self.width, self.height)
+    }
+
+    /// Widen the image to 16 bits per channel.
+    pub fn to_gray16(&self) -> PhotonImageGray16 {
+        let raw_pixels = self
+            .raw_pixels
+            .iter()
+            .map(|val| *val as u16 * 257)
+            .collect();
+        PhotonImageGray16::new(raw_pixels, self.width, self.height)
+    }
+
+    /// Get the width of the image.
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the image.
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the luma values of the image.
+    pub fn get_raw_pixels(&self) -> Vec<u8> {
+        self.raw_pixels.clone()
+    }
+}
+
+impl PhotonImageGray {
+    /// Pack an image whose red, green and blue channels are all equal, such as the output
+    /// of the `monochrome` functions, by keeping its red channel.
+    pub(crate) fn from_gray_rgba(photon_image: &PhotonImage) -> PhotonImageGray {
+        let raw_pixels = photon_image.raw_pixels.iter().step_by(4).copied().collect();
+        PhotonImageGray::new(raw_pixels, photon_image.width, photon_image.height)
+    }
+}
+
+/// An image with a single 16-bit luma channel.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PhotonImageGray16 {
+    pub(crate) raw_pixels: Vec<u16>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl PhotonImageGray16 {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create a new PhotonImageGray16 from a Vec of 16-bit luma values.
+    ///
+    /// Panics if `raw_pixels` does not hold exactly `width * height` values.
+    pub fn new(raw_pixels: Vec<u16>, width: u32, height: u32) -> PhotonImageGray16 {
+        if raw_pixels.len() != width as usize * height as usize {
+            panic!("Pixel buffer must hold width * height values.");
+        }
+        PhotonImageGray16 {
+            raw_pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Convert a 16-bit image to greyscale using Rec. 709 luma weights, dropping alpha.
+    pub fn from_image16(img: &PhotonImage16) -> PhotonImageGray16 {
+        let raw_pixels = img
+            .raw_pixels
+            .chunks(4)
+            .map(|px| {
+                let luma = 0.2126 * px[0] as f32
+                    + 0.7152 * px[1] as f32
+                    + 0.0722 * px[2] as f32;
+                luma.round().clamp(0.0, 65535.0) as u16
+            })
+            .collect();
+
+        PhotonImageGray16::new(raw_pixels, img.width, img.height)
+    }
+
+    /// Expand the image to an opaque 16-bit RGBA image.
+    pub fn to_image16(&self) -> PhotonImage16 {
+        let raw_pixels = self
+            .raw_pixels
+            .iter()
+            .flat_map(|val| [*val, *val, *val, 65535])
+            .collect();
+
+        PhotonImage16::new(raw_pixels, self.width, self.height)
+    }
+
+    /// Round the image to 8 bits per channel.
+    pub fn to_gray(&self) -> PhotonImageGray {
+        let raw_pixels = self
+            .raw_pixels
+            .iter()
+            .map(|val| ((*val as u32 + 128) / 257) as u8)
+            .collect();
+
+        PhotonImageGray::new(raw_pixels, self.width, self.height)
+    }
+
+    /// Get the width of the image.
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the image.
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the 16-bit luma values of the image.
+    pub fn get_raw_pixels(&self) -> Vec<u16> {
+        self.raw_pixels.clone()
+    }
+}
+
+/// An image with 8-bit RGB pixels and no alpha channel.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PhotonImageRgb {
+    pub(crate) raw_pixels: Vec<u8>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl PhotonImageRgb {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create a new PhotonImageRgb from a Vec of RGB values.
+    ///
+    /// Panics if `raw_pixels` does not hold exactly `width * height * 3` values.
+    pub fn new(raw_pixels: Vec<u8>, width: u32, height: u32) -> PhotonImageRgb {
+        if raw_pixels.len() != width as usize * height as usize * 3 {
+            panic!("Pixel buffer must hold width * height * 3 values.");
+        }
+        PhotonImageRgb {
+            raw_pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Drop the alpha channel of a PhotonImage.
+    pub fn from_photon_image(photon_image: &PhotonImage) -> PhotonImageRgb {
+        let raw_pixels = photon_image
+            .raw_pixels
+            .chunks(4)
+            .flat_map(|px| [px[0], px[1], px[2]])
+            .collect();
+
+        PhotonImageRgb::new(raw_pixels, photon_image.width, photon_image.height)
+    }
+
+    /// Expand the image to an opaque RGBA PhotonImage.
+    pub fn to_photon_image(&self) -> PhotonImage {
+        let raw_pixels = self
+            .raw_pixels
+            .chunks(3)
+            .flat_map(|px| [px[0], px[1], px[2], 255])
+            .collect();
+
+        PhotonImage::new(raw_pixels, self.width, self.height)
+    }
+
+    /// Get the width of the image.
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the image.
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the RGB values of the image.
+    pub fn get_raw_pixels(&self) -> Vec<u8> {
+        self.raw_pixels.clone()
+    }
+}
+
+/// Apply the sRGB transfer function, extended to values outside 0 to 1.
+pub(crate) fn encode_srgb(val: f32) -> f32 {
+    let abs = val.abs();
+    let encoded = if abs <= 0.003_130_8 {
+        abs * 12.92
+    } else {
+        1.055 * abs.powf(1.0 / 2.4) - 0.055
+    };
+    val.signum() * encoded
+}
+
+/// Undo the sRGB transfer function, extended to values outside 0 to 1.
+pub(crate) fn decode_srgb(val: f32) -> f32 {
+    let abs = val.abs();
+    let decoded = if abs <= 0.040_45 {
+        abs / 12.92
+    } else {
+        ((abs + 0.055) / 1.055).powf(2.4)
+    };
+    val.signum() * decoded
+}
@@ -3,10 +3,56 @@ mod test {
 
     use image::ImageBuffer;
 
+    use crate::animation::{Animation, AnimationError, AnimationFrame, FrameDisposal};
     use crate::channels::*;
     use crate::colour_spaces::*;
-    use crate::transform::{resample, seam_carve};
-    use crate::PhotonImage;
+    use crate::conv::{detect_edges_gray, EdgeDetector};
+    use crate::dither::{
+        dither_channels, dither_monochrome, dither_palette, DitherAlgorithm,
+        DitherOptions,
+    };
+    use crate::effects::{
+        adjust_contrast, adjust_contrast_f32, clarity, dither, gradient_map,
+        shadows_highlights, LuminanceFormula,
+    };
+    use crate::encode::{
+        encode, encode_to_writer, ChromaSubsampling, EncodeOptions, OutputFormat,
+    };
+    use crate::gradient::{
+        gradient, ColorStop, GradientInterpolation, GradientOptions, GradientShape,
+        SpreadMode,
+    };
+    use crate::icc::{convert_colour_profile, convert_to_srgb, IccProfile};
+    use crate::linear::{fade_linear, gaussian_blur_linear, resize_linear, LinearImage};
+    use crate::lut::{apply_lut, hald_identity, lut_from_filter, Lut, LutInterpolation};
+    use crate::metadata::{embed_metadata, read_metadata, strip_metadata};
+    use crate::monochrome::{grayscale, threshold_gray, to_gray, GrayscaleMode};
+    use crate::multiple::{create_gradient, exposure_fusion, fade, place_in_quad};
+    use crate::native::{
+        image_to_bytes, open_image16, open_image_f32, open_image_from_reader,
+        open_image_gray, open_image_linear, open_image_rgb, open_image_with_profile,
+        save_image, save_image16, save_image_gray, save_image_rgb,
+        save_image_with_profile,
+    };
+    use crate::pyramid::{gaussian_pyramid, laplacian_pyramid, multiband_blend};
+    use crate::quantize::{
+        generate_palette, quantize, remap_to_palette, Palette, QuantizeMethod,
+    };
+    use crate::storage::{PhotonImage16, PhotonImageF32, PhotonImageRgb};
+    use crate::tonemap::{tonemap, ToneMapOperator};
+    use crate::transform::{
+        affine_transform, correct_perspective, resample, resize, resize_contain,
+        resize_cover, resize_fit, resize_shrink, rotate, rotate_with_options, scale,
+        seam_carve, seam_carve_with_options, shearx, smart_crop, thumbnail, trim,
+        trim_region, AffineMatrix, CanvasSize, Gravity, Homography, Interpolation,
+        SamplingFilter, SeamCarveOptions,
+    };
+    use crate::{PhotonImage, Rgb};
+
+    /// A path in the temporary directory which concurrent test runs do not share.
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("photon_test_{}_{}", std::process::id(), name))
+    }
 
     #[test]
     fn test_alter_red_channel() {
@@ -315,11 +361,17 @@ mod test {
         {
             // Un-carved image
             // Will return the same image
-            let result: PhotonImage = seam_carve(&photon_image, 100_u32, 100_u32);
+            let result: PhotonImage = seam_carve(&photon_image, width, height);
             assert_eq!(result.get_width(), width);
             assert_eq!(result.get_height(), height);
             assert_eq!(result.get_raw_pixels(), raw_pix);
         }
+        {
+            // Enlarged image, from 4x4 --> 100x100
+            let result: PhotonImage = seam_carve(&photon_image, 100_u32, 100_u32);
+            assert_eq!(result.get_width(), 100);
+            assert_eq!(result.get_height(), 100);
+        }
         {
             // Carved Image, from 4x4 --> 3x2
             let new_w = 3_u32;
@@ -329,6 +381,14 @@ mod test {
             assert_eq!(result.get_height(), new_h);
             assert_eq!(result.get_raw_pixels(), correct_pix);
         }
+        {
+            // Empty image
+            // Has no seams, so is returned unchanged
+            let empty = PhotonImage::new(vec![], 0, 4);
+            let result: PhotonImage = seam_carve(&empty, 3_u32, 2_u32);
+            assert_eq!(result.get_width(), 0);
+            assert_eq!(result.get_height(), 4);
+        }
     }
 
     #[test]
@@ -405,4 +465,1460 @@ mod test {
             assert_eq!(result.get_raw_pixels(), correct_pix);
         }
     }
+
+    #[test]
+    fn test_linear_gradient() {
+        let stops = vec![
+            ColorStop::new(0.0, crate::Rgba::new(0, 0, 0, 255)),
+            ColorStop::new(1.0, crate::Rgba::new(255, 255, 255, 255)),
+        ];
+        let mut options = GradientOptions::new(GradientShape::Linear);
+        options.set_interpolation(GradientInterpolation::Srgb);
+
+        let result = gradient(256, 2, stops.clone(), &options);
+        let pixels = result.get_raw_pixels();
+        assert_eq!(result.get_width(), 256);
+        assert_eq!(result.get_height(), 2);
+        // Pixel centres of a 256px wide image sample 0.5/256 .. 255.5/256 of the ramp.
+        assert_eq!(&pixels[0..4], &[0, 0, 0, 255]);
+        assert_eq!(&pixels[255 * 4..256 * 4], &[255, 255, 255, 255]);
+        assert!(pixels[128 * 4] == 127 || pixels[128 * 4] == 128);
+
+        // Rotating the gradient by 180 degrees reverses it.
+        options.set_angle(180.0);
+        let reversed = gradient(256, 2, stops, &options);
+        assert_eq!(&reversed.get_raw_pixels()[0..4], &[255, 255, 255, 255]);
+
+        // Without any stops, the gradient is transparent.
+        let empty = gradient(4, 4, Vec::new(), &options);
+        assert!(empty.get_raw_pixels().iter().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn test_fade() {
+        let black = PhotonImage::new([0, 0, 0, 255].repeat(5), 5, 1);
+        let white = PhotonImage::new([255, 255, 255, 255].repeat(5), 5, 1);
+
+        // The opacity of the first image is 0 before the start and 1 past the end.
+        let faded = fade(&white, &black, 1, 3, 0, 0);
+        let reds: Vec<u8> = faded.get_raw_pixels().chunks(4).map(|px| px[0]).collect();
+        assert_eq!(reds, vec![0, 0, 127, 255, 255]);
+
+        // Swapping the start and the end reverses the fade.
+        let reversed = fade(&white, &black, 3, 1, 0, 0);
+        let reds: Vec<u8> = reversed
+            .get_raw_pixels()
+            .chunks(4)
+            .map(|px| px[0])
+            .collect();
+        assert_eq!(reds, vec![255, 255, 127, 0, 0]);
+
+        let gradient = create_gradient(3, 1);
+        assert_eq!(gradient.get_width(), 3);
+        assert!(gradient.get_raw_pixels().chunks(4).all(|px| px[3] == 255));
+    }
+
+    #[test]
+    fn test_radial_gradient_spread() {
+        let stops = vec![
+            ColorStop::new(0.0, crate::Rgba::new(255, 0, 0, 255)),
+            ColorStop::new(1.0, crate::Rgba::new(0, 0, 255, 0)),
+        ];
+        let mut options = GradientOptions::new(GradientShape::Radial);
+        options.set_radius(0.25, 0.25);
+
+        // Padded: the centre keeps the first stop, the corners the last one.
+        let padded = gradient(64, 64, stops.clone(), &options);
+        let centre = (32 * 64 + 32) * 4;
+        let padded_pixels = padded.get_raw_pixels();
+        assert!(padded_pixels[centre] > 240);
+        assert_eq!(&padded_pixels[0..4], &[0, 0, 255, 0]);
+
+        // Reflected: twice the radius away from the centre, the first stop comes back.
+        options.set_spread(SpreadMode::Reflect);
+        let reflected = gradient(64, 64, stops, &options);
+        let px = (32 * 64 + 63) * 4;
+        assert!(reflected.get_raw_pixels()[px] > 200);
+    }
+
+    #[test]
+    fn test_gradient_map() {
+        let raw_pix = vec![0, 0, 0, 255, 255, 255, 255, 128, 128, 128, 128, 255];
+        let stops = vec![
+            ColorStop::new(0.0, crate::Rgba::new(0, 0, 255, 255)),
+            ColorStop::new(0.5, crate::Rgba::new(255, 0, 0, 255)),
+            ColorStop::new(1.0, crate::Rgba::new(255, 255, 0, 255)),
+        ];
+
+        let mut photon_image = PhotonImage::new(raw_pix.clone(), 3, 1);
+        gradient_map(
+            &mut photon_image,
+            stops.clone(),
+            GradientInterpolation::Srgb,
+            LuminanceFormula::Rec709,
+            1.0,
+        );
+        let pixels = photon_image.get_raw_pixels();
+        // Black and white take the end stops, alpha is preserved.
+        assert_eq!(&pixels[0..4], &[0, 0, 255, 255]);
+        assert_eq!(&pixels[4..8], &[255, 255, 0, 128]);
+        // Mid grey lands on the middle stop.
+        assert!(pixels[8] >= 250 && pixels[9] <= 5 && pixels[10] <= 5);
+
+        // Zero opacity leaves the image untouched.
+        let mut photon_image = PhotonImage::new(raw_pix.clone(), 3, 1);
+        gradient_map(
+            &mut photon_image,
+            stops,
+            GradientInterpolation::Lab,
+            LuminanceFormula::Average,
+            0.0,
+        );
+        assert_eq!(photon_image.get_raw_pixels(), raw_pix);
+    }
+
+    #[test]
+    fn test_lut() {
+        // A 2x2x2 LUT which inverts every colour.
+        let cube = "TITLE \"Invert\"\n# comment\nLUT_3D_SIZE 2\n\
+                    1 1 1\n0 1 1\n1 0 1\n0 0 1\n1 1 0\n0 1 0\n1 0 0\n0 0 0\n";
+        let lut = Lut::from_cube(cube).expect("LUT should parse");
+        assert_eq!(lut.get_size(), 2);
+        assert_eq!(lut.get_title().as_deref(), Some("Invert"));
+
+        let raw_pix = vec![10, 200, 128, 77, 0, 255, 40, 255];
+        for interpolation in [LutInterpolation::Trilinear, LutInterpolation::Tetrahedral]
+        {
+            let mut photon_image = PhotonImage::new(raw_pix.clone(), 2, 1);
+            apply_lut(&mut photon_image, &lut, interpolation, 1.0);
+            assert_eq!(
+                photon_image.get_raw_pixels(),
+                vec![245, 55, 127, 77, 255, 0, 215, 255]
+            );
+        }
+
+        // Exporting and re-parsing keeps the table.
+        let reparsed = Lut::from_cube(&lut.to_cube()).expect("LUT should parse");
+        let mut photon_image = PhotonImage::new(raw_pix.clone(), 2, 1);
+        apply_lut(
+            &mut photon_image,
+            &reparsed,
+            LutInterpolation::Tetrahedral,
+            0.0,
+        );
+        assert_eq!(photon_image.get_raw_pixels(), raw_pix);
+
+        // An identity Hald CLUT leaves colours untouched.
+        let hald = Lut::from_hald_clut(&hald_identity(4)).expect("Hald should parse");
+        let mut photon_image = PhotonImage::new(raw_pix.clone(), 2, 1);
+        apply_lut(&mut photon_image, &hald, LutInterpolation::Trilinear, 1.0);
+        assert_eq!(photon_image.get_raw_pixels(), raw_pix);
+
+        // Presets can be sampled into a LUT.
+        let firenze = lut_from_filter("firenze", 9).expect("Filter should exist");
+        assert_eq!(firenze.get_size(), 9);
+        assert!(firenze
+            .to_cube()
+            .starts_with("TITLE \"firenze\"\nLUT_3D_SIZE 9\n"));
+        assert!(lut_from_filter("not_a_filter", 9).is_err());
+
+        // A level 1 Hald CLUT would only hold a single entry.
+        let tiny = PhotonImage::new(vec![0, 0, 0, 255], 1, 1);
+        assert!(Lut::from_hald_clut(&tiny).is_err());
+
+        assert!(Lut::from_cube("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn test_white_balance() {
+        let raw_pix = vec![200, 180, 140, 255, 120, 108, 84, 255, 60, 60, 60, 128];
+
+        // The neutral temperature leaves the image unchanged.
+        let mut photon_image = PhotonImage::new(raw_pix.clone(), 3, 1);
+        white_balance(&mut photon_image, 6500.0, 0.0);
+        let pixels = photon_image.get_raw_pixels();
+        for (out, orig) in pixels.iter().zip(raw_pix.iter()) {
+            assert!((*out as i16 - *orig as i16).abs() <= 1);
+        }
+
+        // Correcting for tungsten light cools the image down.
+        let mut photon_image = PhotonImage::new(raw_pix.clone(), 3, 1);
+        white_balance(&mut photon_image, 3200.0, 0.0);
+        let pixels = photon_image.get_raw_pixels();
+        assert!(pixels[0] < 200 && pixels[2] > 140);
+        assert_eq!(pixels[11], 128);
+
+        // Picking the warm colour as neutral makes it grey.
+        let mut photon_image = PhotonImage::new(raw_pix.clone(), 3, 1);
+        white_balance_from_neutral(&mut photon_image, Rgb::new(200, 180, 140));
+        let pixels = photon_image.get_raw_pixels();
+        assert!((pixels[0] as i16 - pixels[2] as i16).abs() <= 2);
+        assert!((pixels[1] as i16 - pixels[2] as i16).abs() <= 2);
+
+        // Gray world on a single-colour image also makes it grey.
+        let mut photon_image = PhotonImage::new(vec![200, 180, 140, 255], 1, 1);
+        auto_white_balance(&mut photon_image, WhiteBalanceMethod::GrayWorld);
+        let pixels = photon_image.get_raw_pixels();
+        assert!((pixels[0] as i16 - pixels[2] as i16).abs() <= 2);
+    }
+
+    #[test]
+    fn test_tonal_adjustments() {
+        // Vibrance boosts a muted colour more than a saturated one.
+        let raw_pix = vec![120, 100, 140, 255, 0, 0, 255, 255];
+        let mut photon_image = PhotonImage::new(raw_pix.clone(), 2, 1);
+        vibrance(&mut photon_image, 1.0);
+        let pixels = photon_image.get_raw_pixels();
+        assert!(pixels[2] - pixels[1] > raw_pix[2] - raw_pix[1]);
+        assert!(pixels[6] >= 250 && pixels[4] <= 5);
+
+        // Dark left half, bright right half.
+        let (width, height) = (16, 16);
+        let mut raw_pix = Vec::new();
+        for _ in 0..height {
+            for x in 0..width {
+                let val = if x < width / 2 { 40 } else { 220 };
+                raw_pix.extend_from_slice(&[val, val, val, 255]);
+            }
+        }
+
+        let mut photon_image = PhotonImage::new(raw_pix.clone(), width, height);
+        shadows_highlights(&mut photon_image, 1.0, 1.0, 2);
+        let pixels = photon_image.get_raw_pixels();
+        assert!(pixels[0] > 40);
+        assert!(pixels[pixels.len() - 4] < 220);
+        assert_eq!(pixels[3], 255);
+
+        // Clarity increases the contrast across the edge, and leaves flat areas alone.
+        let mut photon_image = PhotonImage::new(raw_pix.clone(), width, height);
+        clarity(&mut photon_image, 1.0, 2);
+        let pixels = photon_image.get_raw_pixels();
+        let left_of_edge = ((width / 2 - 1) * 4) as usize;
+        let right_of_edge = ((width / 2) * 4) as usize;
+        assert!(pixels[left_of_edge] < 40);
+        assert!(pixels[right_of_edge] > 220);
+        assert_eq!(pixels[0], 40);
+    }
+
+    #[test]
+    fn test_hsl_mixer() {
+        // Pure red, pure blue and mid grey.
+        let raw_pix = vec![255, 0, 0, 255, 0, 0, 255, 255, 128, 128, 128, 255];
+
+        for space in [MixerSpace::Hsl, MixerSpace::Lch] {
+            // An untouched mixer leaves the image unchanged.
+            let mut photon_image = PhotonImage::new(raw_pix.clone(), 3, 1);
+            hsl_mixer(&mut photon_image, &HslMixer::new(space));
+            let pixels = photon_image.get_raw_pixels();
+            for (out, orig) in pixels.iter().zip(raw_pix.iter()) {
+                assert!((*out as i16 - *orig as i16).abs() <= 1);
+            }
+
+            // Desaturating the reds greys out red, and leaves blue and grey alone.
+            let mut mixer = HslMixer::new(space);
+            mixer.set_saturation(HueBand::Red, -1.0);
+            let mut photon_image = PhotonImage::new(raw_pix.clone(), 3, 1);
+            hsl_mixer(&mut photon_image, &mixer);
+            let pixels = photon_image.get_raw_pixels();
+            assert!((pixels[0] as i16 - pixels[1] as i16).abs() <= 2);
+            assert!(pixels[6] >= 250 && pixels[4] <= 5);
+            assert_eq!(&pixels[8..12], &[128, 128, 128, 255]);
+        }
+    }
+
+    #[test]
+    fn test_linear_checkerboard() {
+        // A black and white checkerboard averages to 50% linear light, which is 188 in sRGB.
+        let (width, height) = (8, 8);
+        let mut raw_pix = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let val = if (x + y) % 2 == 0 { 0 } else { 255 };
+                raw_pix.extend_from_slice(&[val, val, val, 255]);
+            }
+        }
+        let photon_image = PhotonImage::new(raw_pix.clone(), width, height);
+        let linear = LinearImage::from_photon_image(&photon_image);
+        assert_eq!(linear.to_photon_image().get_raw_pixels(), raw_pix);
+
+        let resized =
+            resize_linear(&linear, 1, 1, SamplingFilter::Triangle).to_photon_image();
+        assert_eq!(resized.get_raw_pixels(), vec![188, 188, 188, 255]);
+
+        // Resizing the encoded values directly gives the darker, incorrect result.
+        let naive = resize(&photon_image, 1, 1, SamplingFilter::Triangle);
+        assert!(naive.get_raw_pixels()[0] <= 128);
+
+        let mut blurred = linear.clone();
+        gaussian_blur_linear(&mut blurred, 2);
+        // Away from the edges, which repeat the outermost pixels.
+        let pixels = blurred.to_photon_image().get_raw_pixels();
+        for y in 3..5 {
+            for x in 3..5 {
+                let idx = ((y * width + x) * 4) as usize;
+                assert!((pixels[idx] as i16 - 188).abs() <= 2);
+            }
+        }
+
+        // The middle of a fade between black and white is also 188.
+        let black = LinearImage::new([0.0, 0.0, 0.0, 1.0].repeat(3), 3, 1);
+        let white = LinearImage::new([1.0, 1.0, 1.0, 1.0].repeat(3), 3, 1);
+        let faded = fade_linear(&black, &white, 0, 2, 0, 0).to_photon_image();
+        assert_eq!(&faded.get_raw_pixels()[4..8], &[188, 188, 188, 255]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Pixel buffer must hold width * height * 4 values.")]
+    fn test_linear_image_size() {
+        LinearImage::new(vec![0.0; 12], 2, 2);
+    }
+
+    #[test]
+    fn test_high_bit_depth() {
+        // A smooth ramp survives darkening and restoring it with gamma in floating point,
+        // but bands when rounded to 8 bits in between.
+        let raw_pix: Vec<u8> = (0..=255).flat_map(|val| [val, val, val, 255]).collect();
+        let photon_image = PhotonImage::new(raw_pix.clone(), 256, 1);
+
+        let mut float_image = PhotonImageF32::from_photon_image(&photon_image);
+        gamma_correction_f32(&mut float_image, 0.25, 0.25, 0.25);
+        gamma_correction_f32(&mut float_image, 4.0, 4.0, 4.0);
+        let restored = float_image.to_photon_image().get_raw_pixels();
+        for (out, orig) in restored.iter().zip(raw_pix.iter()) {
+            assert!((*out as i16 - *orig as i16).abs() <= 1);
+        }
+
+        let mut banded = photon_image.clone();
+        gamma_correction(&mut banded, 0.25, 0.25, 0.25);
+        gamma_correction(&mut banded, 4.0, 4.0, 4.0);
+        let mut levels: Vec<u8> =
+            banded.get_raw_pixels().into_iter().step_by(4).collect();
+        levels.dedup();
+        assert!(levels.len() < 150);
+
+        // 16-bit PNG round-trips losslessly.
+        let pixels16: Vec<u16> = (0..64_u16).map(|val| val * 1021 + 3).collect();
+        let img16 = PhotonImage16::new(pixels16.clone(), 4, 4);
+        let path = temp_path("16bit.png");
+        save_image16(&img16, &path).expect("Save failed");
+        let reopened = open_image16(&path).expect("File should open");
+        std::fs::remove_file(&path).ok();
+        assert_eq!(reopened.get_raw_pixels(), pixels16);
+
+        // So does 16-bit TIFF.
+        let path = temp_path("16bit.tiff");
+        save_image16(&img16, &path).expect("Save failed");
+        let reopened = open_image16(&path).expect("File should open");
+        std::fs::remove_file(&path).ok();
+        assert_eq!(reopened.get_raw_pixels(), pixels16);
+
+        // Formats without magic bytes, such as TGA, are opened by their extension.
+        let path = temp_path("float.tga");
+        save_image(photon_image.clone(), &path).expect("Save failed");
+        let reopened = open_image_f32(&path).expect("File should open");
+        std::fs::remove_file(&path).ok();
+        assert_eq!(reopened.to_photon_image().get_raw_pixels(), raw_pix);
+
+        let widened = PhotonImage16::from_photon_image(&photon_image);
+        assert_eq!(widened.to_photon_image().get_raw_pixels(), raw_pix);
+        assert_eq!(widened.to_image_f32().get_raw_pixels()[4 * 255], 1.0);
+
+        // 16-bit edits go through the floating-point functions, which match the 8-bit ones.
+        let mut edited = widened.to_image_f32();
+        adjust_contrast_f32(&mut edited, 40.0);
+        let edited = PhotonImage16::from_image_f32(&edited).to_photon_image();
+        let mut expected = photon_image.clone();
+        adjust_contrast(&mut expected, 40.0);
+        for (out, exp) in edited
+            .get_raw_pixels()
+            .iter()
+            .zip(expected.get_raw_pixels())
+        {
+            assert!((*out as i16 - exp as i16).abs() <= 1);
+        }
+
+        let mut mixed = PhotonImageF32::from_photon_image(&photon_image);
+        mix_with_colour_f32(&mut mixed, Rgb::new(50, 255, 254), 0.4);
+        let mut expected = photon_image.clone();
+        mix_with_colour(&mut expected, Rgb::new(50, 255, 254), 0.4);
+        for (out, exp) in mixed
+            .to_photon_image()
+            .get_raw_pixels()
+            .iter()
+            .zip(expected.get_raw_pixels())
+        {
+            assert!((*out as i16 - exp as i16).abs() <= 1);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Pixel buffer must hold width * height * 3 values.")]
+    fn test_compact_storage_size() {
+        PhotonImageRgb::new(vec![0; 8], 2, 2);
+    }
+
+    #[test]
+    fn test_compact_storage() {
+        let raw_pix = vec![10, 200, 30, 255, 250, 240, 230, 128];
+        let photon_image = PhotonImage::new(raw_pix.clone(), 2, 1);
+
+        let binary = threshold_gray(&photon_image, 128);
+        assert_eq!(binary.get_raw_pixels(), vec![255, 255]);
+
+        let mut expected = photon_image.clone();
+        grayscale(&mut expected);
+        let gray = to_gray(&photon_image, GrayscaleMode::Average);
+        assert_eq!(gray.get_raw_pixels().len(), 2);
+        assert_eq!(
+            gray.to_photon_image().get_raw_pixels()[0..3],
+            expected.get_raw_pixels()[0..3]
+        );
+
+        let rgb = PhotonImageRgb::from_photon_image(&photon_image);
+        assert_eq!(rgb.get_raw_pixels(), vec![10, 200, 30, 250, 240, 230]);
+        assert_eq!(rgb.to_photon_image().get_raw_pixels()[7], 255);
+
+        // Compact images can be opened without an RGBA copy.
+        let path = temp_path("compact.png");
+        save_image_rgb(&rgb, &path).expect("Save failed");
+        let reopened = open_image_rgb(&path).expect("File should open");
+        assert_eq!(reopened.get_raw_pixels(), rgb.get_raw_pixels());
+        save_image_gray(&binary, &path).expect("Save failed");
+        let reopened = open_image_gray(&path).expect("File should open");
+        std::fs::remove_file(&path).ok();
+        assert_eq!(reopened.get_raw_pixels(), binary.get_raw_pixels());
+
+        let edges = detect_edges_gray(&photon_image, EdgeDetector::SobelGlobal);
+        assert_eq!(edges.get_raw_pixels().len(), 2);
+    }
+
+    #[test]
+    fn test_tonemap() {
+        // A row of greys from black to well past white.
+        let levels = [0.0, 0.05, 0.5, 2.0, 16.0];
+        let pixels: Vec<f32> = levels
+            .iter()
+            .flat_map(|val| [*val, *val, *val, 1.0])
+            .collect();
+        let hdr = LinearImage::new(pixels, levels.len() as u32, 1);
+
+        for operator in [
+            ToneMapOperator::Reinhard,
+            ToneMapOperator::ReinhardLocal,
+            ToneMapOperator::Aces,
+            ToneMapOperator::Hable,
+            ToneMapOperator::Drago,
+        ] {
+            let img = tonemap(&hdr, operator, 0.0, 16.0);
+            let out: Vec<u8> = img.get_raw_pixels().into_iter().step_by(4).collect();
+            assert_eq!(out[0], 0);
+            assert!(out.windows(2).all(|pair| pair[0] < pair[1]));
+            // The white point maps to white.
+            assert!(out[4] >= 250);
+        }
+
+        // Radiance HDR files keep their values above 1.
+        let path = temp_path("tonemap.hdr");
+        let file = std::fs::File::create(&path).expect("File should be created");
+        image::codecs::hdr::HdrEncoder::new(file)
+            .encode(&[image::Rgb([4.0_f32, 0.5, 0.25])], 1, 1)
+            .expect("HDR should encode");
+        let hdr = open_image_linear(&path).expect("File should open");
+        std::fs::remove_file(&path).ok();
+        let pixels = hdr.get_pixels();
+        assert!((pixels[0] - 4.0).abs() < 0.1 && (pixels[1] - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_exposure_fusion() {
+        // An under- and an over-exposed version of a horizontal ramp.
+        let (width, height) = (32, 16);
+        let ramp = |offset: i32| {
+            let mut raw_pix = Vec::new();
+            for _ in 0..height {
+                for x in 0..width {
+                    let val = (x * 8 + offset).clamp(0, 255) as u8;
+                    raw_pix.extend_from_slice(&[val, val / 2, val, 255]);
+                }
+            }
+            PhotonImage::new(raw_pix, width as u32, height as u32)
+        };
+        let under = ramp(-120);
+        let over = ramp(120);
+
+        // Fusing an image with itself gives it back.
+        let same = exposure_fusion(vec![under.clone(), under.clone()], 1.0, 1.0, 1.0)
+            .expect("Images should match");
+        for (out, orig) in same.get_raw_pixels().iter().zip(under.get_raw_pixels()) {
+            assert!((*out as i16 - orig as i16).abs() <= 1);
+        }
+
+        // The fused image keeps detail where either exposure clipped.
+        let fused = exposure_fusion(vec![under.clone(), over.clone()], 1.0, 1.0, 1.0)
+            .expect("Images should match");
+        let pixels = fused.get_raw_pixels();
+        let row: Vec<u8> = pixels[..(width * 4) as usize]
+            .iter()
+            .step_by(4)
+            .copied()
+            .collect();
+        assert!(row[0] > 0 && row[row.len() - 1] < 255);
+        assert!(row.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        // Transparency is kept rather than made opaque.
+        let mut translucent = under.clone();
+        translucent
+            .raw_pixels
+            .chunks_mut(4)
+            .for_each(|px| px[3] = 100);
+        let fused =
+            exposure_fusion(vec![translucent.clone(), translucent], 1.0, 1.0, 1.0)
+                .expect("Images should match");
+        assert!(fused.get_raw_pixels().chunks(4).all(|px| px[3] == 100));
+
+        // Fewer than two images, or images of different sizes, can't be fused.
+        assert!(exposure_fusion(vec![], 1.0, 1.0, 1.0).is_none());
+        assert!(exposure_fusion(vec![under.clone()], 1.0, 1.0, 1.0).is_none());
+        let small = PhotonImage::new(vec![0; 16], 2, 2);
+        assert!(exposure_fusion(vec![under, over, small], 1.0, 1.0, 1.0).is_none());
+    }
+
+    #[test]
+    fn test_pyramids() {
+        let (width, height) = (20, 12);
+        let raw_pix: Vec<u8> = (0..width * height)
+            .flat_map(|idx| [(idx * 7 % 256) as u8, (idx * 13 % 256) as u8, 90, 255])
+            .collect();
+        let photon_image = PhotonImage::new(raw_pix.clone(), width, height);
+
+        let gaussian = gaussian_pyramid(&photon_image, 3);
+        assert_eq!(gaussian.len(), 3);
+        assert_eq!((gaussian[1].get_width(), gaussian[1].get_height()), (10, 6));
+        assert_eq!((gaussian[2].get_width(), gaussian[2].get_height()), (5, 3));
+
+        // A Laplacian pyramid reconstructs the image exactly.
+        let pyramid = laplacian_pyramid(&photon_image, 10);
+        assert_eq!(pyramid.get_levels(), 6);
+        assert_eq!(pyramid.reconstruct().get_raw_pixels(), raw_pix);
+
+        // Blending through a left-half mask keeps each image away from the seam.
+        let black = PhotonImage::new([0, 0, 0, 255].repeat(16 * 16), 16, 16);
+        let white = PhotonImage::new([255, 255, 255, 255].repeat(16 * 16), 16, 16);
+        let mask_pixels: Vec<u8> = (0..16 * 16)
+            .flat_map(|idx| {
+                if idx % 16 < 8 {
+                    [255; 4]
+                } else {
+                    [0, 0, 0, 255]
+                }
+            })
+            .collect();
+        let mask = PhotonImage::new(mask_pixels, 16, 16);
+        let blended = multiband_blend(&white, &black, &mask, 3).get_raw_pixels();
+        let row_start = 8 * 16 * 4;
+        assert!(blended[row_start] > 240);
+        assert!(blended[row_start + 15 * 4] < 15);
+        assert!(blended[row_start + 7 * 4] > blended[row_start + 8 * 4]);
+    }
+
+    #[test]
+    fn test_metadata() {
+        // A 3x2 PNG with a distinct red value in every pixel.
+        let raw_pix: Vec<u8> = (0..6).flat_map(|idx| [idx * 40, 0, 0, 255]).collect();
+        let buffer = ImageBuffer::from_vec(3, 2, raw_pix).unwrap();
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgba8(buffer)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let png = png.into_inner();
+        assert_eq!(read_metadata(&png).get_orientation(), 1);
+
+        // Little-endian EXIF with a camera make and an orientation of 6 (rotate 90° CW).
+        let mut exif = vec![b'I', b'I', 42, 0, 8, 0, 0, 0, 2, 0];
+        exif.extend_from_slice(&[0x0F, 0x01, 2, 0, 7, 0, 0, 0, 38, 0, 0, 0]);
+        exif.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]);
+        exif.extend_from_slice(&[0, 0, 0, 0]);
+        exif.extend_from_slice(b"Photon\0");
+
+        let mut metadata = read_metadata(&png);
+        metadata.set_exif(exif);
+        let tagged = embed_metadata(png, &metadata).unwrap();
+        let read_back = read_metadata(&tagged);
+        assert_eq!(read_back.get_orientation(), 6);
+        assert_eq!(read_back.get_make().as_deref(), Some("Photon"));
+
+        // The bottom-left pixel ends up top-left after rotating clockwise.
+        let oriented = PhotonImage::new_from_byteslice_oriented(tagged.clone());
+        assert_eq!((oriented.get_width(), oriented.get_height()), (2, 3));
+        assert_eq!(oriented.get_raw_pixels()[0], 120);
+
+        let mut upright = read_back;
+        upright.set_orientation(1);
+        let rewritten = embed_metadata(tagged.clone(), &upright).unwrap();
+        assert_eq!(read_metadata(&rewritten).get_orientation(), 1);
+        assert_eq!(
+            read_metadata(&rewritten).get_make().as_deref(),
+            Some("Photon")
+        );
+
+        let stripped = strip_metadata(tagged).unwrap();
+        assert!(read_metadata(&stripped).get_exif().is_none());
+
+        // Without EXIF data, setting the orientation creates a minimal EXIF block.
+        let mut rotated = read_metadata(&stripped);
+        rotated.set_orientation(3);
+        let retagged = embed_metadata(stripped.clone(), &rotated).unwrap();
+        assert_eq!(read_metadata(&retagged).get_orientation(), 3);
+
+        // EXIF data without an orientation entry has one added.
+        let mut exif = vec![b'I', b'I', 42, 0, 8, 0, 0, 0, 1, 0];
+        exif.extend_from_slice(&[0x0F, 0x01, 2, 0, 7, 0, 0, 0, 26, 0, 0, 0]);
+        exif.extend_from_slice(&[0, 0, 0, 0]);
+        exif.extend_from_slice(b"Photon\0");
+        let mut untagged = read_metadata(&stripped);
+        untagged.set_exif(exif);
+        assert_eq!(untagged.get_orientation(), 1);
+        untagged.set_orientation(8);
+        let retagged = embed_metadata(stripped, &untagged).unwrap();
+        let read_back = read_metadata(&retagged);
+        assert_eq!(read_back.get_orientation(), 8);
+        assert_eq!(read_back.get_make().as_deref(), Some("Photon"));
+    }
+
+    #[test]
+    fn test_icc_profiles() {
+        let srgb = IccProfile::srgb();
+        let parsed = IccProfile::from_bytes(&srgb.to_bytes()).unwrap();
+        assert_eq!(parsed.get_description(), "sRGB");
+
+        // sRGB red is inside Display P3, so it survives a round trip.
+        let mut img = PhotonImage::new(vec![255, 0, 0, 255, 128, 128, 128, 255], 2, 1);
+        convert_colour_profile(&mut img, &parsed, &IccProfile::display_p3());
+        let p3 = img.get_raw_pixels();
+        assert!(p3[0] < 240 && p3[1] > 40 && p3[2] > 10);
+        // Both spaces share a white point and tone curve, so greys are unchanged.
+        assert_eq!(&p3[4..7], &[128, 128, 128]);
+
+        convert_to_srgb(&mut img, &IccProfile::display_p3());
+        let back = img.get_raw_pixels();
+        assert!(back[0] >= 254 && back[1] <= 1 && back[2] <= 1);
+
+        // Adobe RGB green is outside sRGB and is clipped.
+        let mut green = PhotonImage::new(vec![0, 255, 0, 255], 1, 1);
+        convert_to_srgb(&mut green, &IccProfile::adobe_rgb());
+        assert_eq!(green.get_raw_pixels()[1], 255);
+        assert_eq!(green.get_raw_pixels()[0], 0);
+
+        // The embedded profile is read back on open.
+        let path = temp_path("icc.png");
+        let p3_img = PhotonImage::new(p3.clone(), 2, 1);
+        save_image_with_profile(p3_img, &path, &IccProfile::display_p3()).unwrap();
+        let (opened, profile) = open_image_with_profile(&path, &srgb).unwrap();
+        assert_eq!(profile.get_description(), "Display P3");
+        assert!(opened.get_raw_pixels()[0] >= 254);
+
+        // Profiles which can't be converted from are treated as sRGB.
+        let mut cmyk = srgb.to_bytes();
+        cmyk[16..20].copy_from_slice(b"CMYK");
+        let mut metadata = read_metadata(&std::fs::read(&path).unwrap());
+        metadata.set_icc_profile(cmyk);
+        let png = image_to_bytes(PhotonImage::new(p3.clone(), 2, 1));
+        std::fs::write(&path, embed_metadata(png, &metadata).unwrap()).unwrap();
+        let (opened, profile) = open_image_with_profile(&path, &srgb).unwrap();
+        assert_eq!(profile.get_description(), "sRGB");
+        assert_eq!(opened.get_raw_pixels(), p3);
+
+        // So are truncated profiles.
+        metadata.set_icc_profile(srgb.to_bytes()[..100].to_vec());
+        let png = image_to_bytes(PhotonImage::new(p3.clone(), 2, 1));
+        std::fs::write(&path, embed_metadata(png, &metadata).unwrap()).unwrap();
+        let (opened, profile) = open_image_with_profile(&path, &srgb).unwrap();
+        assert_eq!(profile.get_description(), "sRGB");
+        assert_eq!(opened.get_raw_pixels(), p3);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_encode() {
+        let (width, height) = (16, 8);
+        let raw_pix: Vec<u8> = (0..width * height)
+            .flat_map(|idx| {
+                [(idx % width * 16) as u8, (idx / width * 32) as u8, 60, 255]
+            })
+            .collect();
+        let photon_image = PhotonImage::new(raw_pix.clone(), width, height);
+        let mut options = EncodeOptions::new();
+
+        // Lossless formats decode to the same pixels.
+        for format in [OutputFormat::Png, OutputFormat::WebP, OutputFormat::Tiff] {
+            let bytes = encode(&photon_image, format, &options).unwrap();
+            let decoded = image::load_from_memory(&bytes).unwrap().to_rgba8();
+            assert_eq!(decoded.into_raw(), raw_pix);
+        }
+        for format in [OutputFormat::Gif, OutputFormat::Bmp, OutputFormat::Ico] {
+            let bytes = encode(&photon_image, format, &options).unwrap();
+            let decoded = image::load_from_memory(&bytes).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (width, height));
+        }
+
+        // The start of frame marker gives the JPEG mode and the luma sampling factors.
+        let frame = |jpeg: &[u8]| {
+            let pos = jpeg
+                .windows(2)
+                .position(|pair| pair[0] == 0xFF && (pair[1] == 0xC0 || pair[1] == 0xC2))
+                .unwrap();
+            (jpeg[pos + 1], jpeg[pos + 11])
+        };
+        options.set_jpeg_quality(95);
+        let baseline = encode(&photon_image, OutputFormat::Jpeg, &options).unwrap();
+        assert_eq!(frame(&baseline), (0xC0, 0x22));
+        options.set_jpeg_subsampling(ChromaSubsampling::Yuv444);
+        options.set_jpeg_progressive(true);
+        let progressive = encode(&photon_image, OutputFormat::Jpeg, &options).unwrap();
+        assert_eq!(frame(&progressive), (0xC2, 0x11));
+        let decoded = image::load_from_memory(&progressive).unwrap().to_rgba8();
+        assert!(decoded
+            .into_raw()
+            .iter()
+            .zip(&raw_pix)
+            .all(|(a, b)| (*a as i32 - *b as i32).abs() < 12));
+
+        options.set_webp_quality(80.0);
+        let lossy = encode(&photon_image, OutputFormat::WebP, &options);
+        assert_eq!(lossy.is_ok(), cfg!(feature = "webp-lossy"));
+
+        assert!(photon_image
+            .get_base64()
+            .starts_with("data:image/png;base64,"));
+        assert!(photon_image
+            .encode_base64(OutputFormat::Jpeg, &EncodeOptions::new())
+            .unwrap()
+            .starts_with("data:image/jpeg;base64,"));
+        assert_eq!(OutputFormat::from_path("out.JPG"), Some(OutputFormat::Jpeg));
+
+        // Images JPEG can't hold give an error, or no bytes from the infallible getters.
+        let wide = PhotonImage::new(vec![0; 70_000 * 4], 70_000, 1);
+        assert!(wide.encode(OutputFormat::Jpeg, &options).is_err());
+        assert!(wide.get_bytes_jpeg(90).is_empty());
+    }
+
+    #[test]
+    fn test_encode_streams() {
+        let raw_pix: Vec<u8> = (0..48_u8).map(|val| val * 5).collect();
+        let photon_image = PhotonImage::new(raw_pix.clone(), 4, 3);
+
+        // image_to_bytes gives an encoded PNG rather than raw pixels.
+        let png = image_to_bytes(photon_image.clone());
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+        let reopened = open_image_from_reader(std::io::Cursor::new(png)).unwrap();
+        assert_eq!(reopened.get_raw_pixels(), raw_pix);
+
+        let options = EncodeOptions::new();
+        for format in [OutputFormat::Tiff, OutputFormat::WebP, OutputFormat::Gif] {
+            let mut written = Vec::new();
+            encode_to_writer(&photon_image, &mut written, format, &options).unwrap();
+            assert_eq!(written, encode(&photon_image, format, &options).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_animation() {
+        let mut animation = Animation::new();
+        for (idx, colour) in [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]]
+            .iter()
+            .enumerate()
+        {
+            let image = PhotonImage::new(colour.repeat(16), 4, 4);
+            animation.add_frame(AnimationFrame::new(image, 100 * (idx as u32 + 1)));
+        }
+        animation.set_loop_count(3);
+
+        let apng = Animation::decode(&animation.to_apng().unwrap()).unwrap();
+        assert_eq!(apng.get_frame_count(), 3);
+        assert_eq!(apng.get_loop_count(), 3);
+        let frame = apng.get_frame(1).unwrap();
+        assert_eq!(frame.get_delay(), 200);
+        assert_eq!(&frame.get_image().get_raw_pixels()[..4], &[0, 255, 0, 255]);
+
+        animation.map_frames(invert);
+        let gif = Animation::decode(&animation.to_gif().unwrap()).unwrap();
+        assert_eq!(gif.get_loop_count(), 3);
+        assert_eq!(gif.get_frame(2).unwrap().get_delay(), 300);
+        let inverted = gif.get_frame(0).unwrap().get_image().get_raw_pixels();
+        assert_eq!(&inverted[..4], &[0, 255, 255, 255]);
+
+        // A smaller second frame is composited over the first, which is kept.
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut bytes, 4, 4, &[]).unwrap();
+            let mut red = [255, 0, 0, 255].repeat(16);
+            let mut first = gif::Frame::from_rgba(4, 4, &mut red);
+            first.dispose = gif::DisposalMethod::Keep;
+            encoder.write_frame(&first).unwrap();
+            let mut blue = [0, 0, 255, 255].repeat(4);
+            let mut second = gif::Frame::from_rgba(2, 2, &mut blue);
+            second.left = 2;
+            second.top = 2;
+            encoder.write_frame(&second).unwrap();
+        }
+        let composited = Animation::from_gif(&bytes).unwrap();
+        assert_eq!(composited.get_loop_count(), 1);
+        assert_eq!(
+            composited.get_frame(0).unwrap().get_disposal(),
+            FrameDisposal::Keep
+        );
+        let pixels = composited
+            .get_frame(1)
+            .unwrap()
+            .get_image()
+            .get_raw_pixels();
+        assert_eq!(&pixels[..4], &[255, 0, 0, 255]);
+        assert_eq!(&pixels[60..], &[0, 0, 255, 255]);
+
+        // GIF dimensions are 16-bit, unlike APNG ones.
+        let mut wide = Animation::new();
+        wide.add_frame(AnimationFrame::new(
+            PhotonImage::new(vec![0; 70_000 * 4], 70_000, 1),
+            100,
+        ));
+        assert!(matches!(wide.to_gif(), Err(AnimationError::GifSize)));
+        assert!(wide.to_apng().is_ok());
+
+        // A tiny GIF whose header declares a huge canvas is rejected before allocating it.
+        let mut huge = b"GIF89a".to_vec();
+        huge.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0]);
+        // A single 1x1 frame with a two-colour local palette.
+        huge.extend_from_slice(&[0x2C, 0, 0, 0, 0, 1, 0, 1, 0, 0x80]);
+        huge.extend_from_slice(&[0, 0, 0, 255, 255, 255]);
+        huge.extend_from_slice(&[2, 2, 0x44, 0x01, 0, 0x3B]);
+        assert!(matches!(
+            Animation::from_gif(&huge),
+            Err(AnimationError::CanvasSize(65535, 65535))
+        ));
+    }
+
+    #[test]
+    fn test_quantize() {
+        // Four clusters of slightly varying colours.
+        let centres = [[200, 30, 30], [30, 200, 30], [30, 30, 200], [230, 230, 230]];
+        let raw_pix: Vec<u8> = (0..64)
+            .flat_map(|idx: usize| {
+                let centre = centres[idx % 4];
+                let jitter = (idx / 4 % 5) as u8;
+                [
+                    centre[0] + jitter,
+                    centre[1] + jitter,
+                    centre[2] + jitter,
+                    255,
+                ]
+            })
+            .collect();
+        let photon_image = PhotonImage::new(raw_pix, 8, 8);
+
+        for method in [
+            QuantizeMethod::MedianCut,
+            QuantizeMethod::Octree,
+            QuantizeMethod::KMeans,
+        ] {
+            let palette = generate_palette(&photon_image, 4, method);
+            assert_eq!(palette.len(), 4);
+            for centre in centres {
+                assert!(palette.get_colours().iter().any(|colour| {
+                    (colour.get_red() as i32 - centre[0] as i32).abs() <= 6
+                        && (colour.get_green() as i32 - centre[1] as i32).abs() <= 6
+                }));
+            }
+        }
+
+        let mut reduced = photon_image.clone();
+        quantize(
+            &mut reduced,
+            2,
+            QuantizeMethod::KMeans,
+            DitherAlgorithm::None,
+        );
+        let distinct: std::collections::HashSet<&[u8]> =
+            reduced.get_raw_pixels_slice().chunks(4).collect();
+        assert_eq!(distinct.len(), 2);
+
+        assert_eq!(Palette::web_safe().len(), 216);
+        assert_eq!(Palette::cga().len(), 16);
+
+        // Mid grey dithered to black and white covers about half the pixels in white.
+        let black_white = Palette::new(vec![Rgb::new(0, 0, 0), Rgb::new(255, 255, 255)]);
+        let grey = PhotonImage::new([128, 128, 128, 255].repeat(256), 16, 16);
+        let white_count = |dither| {
+            let mut img = grey.clone();
+            remap_to_palette(&mut img, &black_white, dither);
+            img.get_raw_pixels()
+                .chunks(4)
+                .filter(|px| px[0] == 255)
+                .count()
+        };
+        assert_eq!(white_count(DitherAlgorithm::None), 256);
+        assert_eq!(white_count(DitherAlgorithm::Bayer4), 128);
+        assert!((120..=136).contains(&white_count(DitherAlgorithm::FloydSteinberg)));
+        assert!((100..=156).contains(&white_count(DitherAlgorithm::Atkinson)));
+
+        let mut animation = Animation::new();
+        animation.add_frame(AnimationFrame::new(photon_image, 100));
+        let gif = animation
+            .to_gif_with_palette(&Palette::game_boy(), DitherAlgorithm::FloydSteinberg)
+            .unwrap();
+        let decoded = Animation::decode(&gif).unwrap().get_frame(0).unwrap();
+        let game_boy = Palette::game_boy().get_colours();
+        assert!(decoded
+            .get_image()
+            .get_raw_pixels()
+            .chunks(4)
+            .all(|px| { game_boy.iter().any(|colour| colour.get_green() == px[1]) }));
+    }
+
+    #[test]
+    fn test_dither() {
+        // Every pixel is dithered, including the last row and column.
+        let mut img = PhotonImage::new([100, 100, 100, 255].repeat(35), 7, 5);
+        dither(&mut img, 1);
+        assert!(img.get_raw_pixels()[..3]
+            .iter()
+            .all(|val| *val == 0 || *val == 255));
+        assert!(img.get_raw_pixels()[136..139]
+            .iter()
+            .all(|val| *val == 0 || *val == 255));
+
+        // Mid grey dithered to black and white covers about half the pixels in white.
+        let grey = PhotonImage::new([128, 128, 128, 255].repeat(1024), 32, 32);
+        let white_count = |options: &DitherOptions| {
+            let mut img = grey.clone();
+            dither_monochrome(&mut img, options);
+            let pixels = img.get_raw_pixels();
+            assert!(pixels.chunks(4).all(|px| px[0] == px[1] && px[1] == px[2]));
+            assert!(pixels.chunks(4).all(|px| px[0] == 0 || px[0] == 255));
+            pixels.chunks(4).filter(|px| px[0] == 255).count()
+        };
+        for algorithm in [
+            DitherAlgorithm::FloydSteinberg,
+            DitherAlgorithm::JarvisJudiceNinke,
+            DitherAlgorithm::Stucki,
+            DitherAlgorithm::Atkinson,
+            DitherAlgorithm::Sierra,
+            DitherAlgorithm::Bayer2,
+            DitherAlgorithm::Bayer4,
+            DitherAlgorithm::Bayer8,
+            DitherAlgorithm::BlueNoise,
+        ] {
+            let mut options = DitherOptions::new(algorithm);
+            assert!(
+                (460..=564).contains(&white_count(&options)),
+                "{algorithm:?}"
+            );
+            options.set_serpentine(true);
+            assert!(
+                (460..=564).contains(&white_count(&options)),
+                "{algorithm:?}"
+            );
+        }
+        assert_eq!(
+            white_count(&DitherOptions::new(DitherAlgorithm::None)),
+            1024
+        );
+
+        let mut gradient = PhotonImage::new(
+            (0..1024_u32)
+                .flat_map(|i| [(i / 4) as u8, (i % 32 * 8) as u8, 60, 200])
+                .collect(),
+            32,
+            32,
+        );
+        // Serpentine scanning changes the pattern of error diffusion.
+        let mut forward = gradient.clone();
+        let mut serpentine = gradient.clone();
+        let mut options = DitherOptions::new(DitherAlgorithm::FloydSteinberg);
+        dither_monochrome(&mut forward, &options);
+        options.set_serpentine(true);
+        dither_monochrome(&mut serpentine, &options);
+        assert_ne!(forward.get_raw_pixels(), serpentine.get_raw_pixels());
+        let mut levels = gradient.clone();
+        dither_channels(&mut levels, 4, &DitherOptions::new(DitherAlgorithm::Stucki));
+        assert!(levels.get_raw_pixels().chunks(4).all(|px| {
+            px[..3].iter().all(|val| [0, 85, 170, 255].contains(val)) && px[3] == 200
+        }));
+
+        dither_palette(
+            &mut gradient,
+            &Palette::cga(),
+            &DitherOptions::new(DitherAlgorithm::BlueNoise),
+        );
+        let cga = Palette::cga().get_colours();
+        assert!(gradient.get_raw_pixels().chunks(4).all(|px| {
+            px[3] == 200
+                && cga.iter().any(|colour| {
+                    [colour.get_red(), colour.get_green(), colour.get_blue()] == px[..3]
+                })
+        }));
+
+        // Palettes of more than 256 colours keep indices past 255.
+        let large = Palette::new(
+            (0..300)
+                .map(|i| Rgb::new((i % 256) as u8, (i / 256) as u8, 7))
+                .collect(),
+        );
+        let mut last = PhotonImage::new(vec![43, 1, 7, 255], 1, 1);
+        dither_palette(
+            &mut last,
+            &large,
+            &DitherOptions::new(DitherAlgorithm::None),
+        );
+        assert_eq!(last.get_raw_pixels(), vec![43, 1, 7, 255]);
+    }
+
+    #[test]
+    fn test_affine_transform() {
+        // A 3x2 image with a distinct colour in every pixel.
+        let photon_image = PhotonImage::new(
+            (0..6_u8)
+                .flat_map(|i| [i * 40, 255 - i * 40, i, 255])
+                .collect(),
+            3,
+            2,
+        );
+        let pixel = |img: &PhotonImage, x: u32, y: u32| {
+            let idx = ((y * img.get_width() + x) * 4) as usize;
+            img.get_raw_pixels()[idx..idx + 4].to_vec()
+        };
+
+        // Right angles are exact, and angles are normalised without truncation.
+        let quarter = rotate(&photon_image, 90.0);
+        assert_eq!((quarter.get_width(), quarter.get_height()), (2, 3));
+        assert_eq!(pixel(&quarter, 1, 0), pixel(&photon_image, 0, 0));
+        assert_eq!(pixel(&quarter, 0, 2), pixel(&photon_image, 2, 1));
+        assert_eq!(
+            rotate(&photon_image, -270.0).get_raw_pixels(),
+            quarter.get_raw_pixels()
+        );
+        assert_ne!(
+            rotate(&photon_image, 30.5).get_raw_pixels(),
+            rotate(&photon_image, 30.0).get_raw_pixels()
+        );
+        assert_eq!(
+            rotate(&photon_image, 0.0).get_raw_pixels(),
+            photon_image.get_raw_pixels()
+        );
+
+        // Translating keeps the original size, and fills the uncovered area.
+        let background = crate::Rgba::new(1, 2, 3, 255);
+        let moved = affine_transform(
+            &photon_image,
+            &AffineMatrix::translation(1.0, 1.0),
+            Interpolation::Nearest,
+            background.clone(),
+            CanvasSize::Original,
+        );
+        assert_eq!((moved.get_width(), moved.get_height()), (3, 2));
+        assert_eq!(pixel(&moved, 1, 1), pixel(&photon_image, 0, 0));
+        assert_eq!(pixel(&moved, 0, 0), vec![1, 2, 3, 255]);
+
+        // A rotated square fits inside its expanded canvas, with transparent corners.
+        let square = PhotonImage::new([200, 100, 50, 255].repeat(400), 20, 20);
+        let rotated = rotate(&square, 45.0);
+        assert_eq!((rotated.get_width(), rotated.get_height()), (29, 29));
+        assert_eq!(pixel(&rotated, 0, 0)[3], 0);
+        assert_eq!(pixel(&rotated, 14, 14), vec![200, 100, 50, 255]);
+        let kept = rotate_with_options(
+            &square,
+            45.0,
+            Interpolation::Bicubic,
+            background.clone(),
+            CanvasSize::Original,
+        );
+        assert_eq!((kept.get_width(), kept.get_height()), (20, 20));
+        assert_eq!(pixel(&kept, 0, 0), vec![1, 2, 3, 255]);
+        assert_eq!(pixel(&kept, 10, 10), vec![200, 100, 50, 255]);
+
+        let doubled = scale(&photon_image, 2.0, 2.0, Interpolation::Nearest);
+        assert_eq!((doubled.get_width(), doubled.get_height()), (6, 4));
+        assert_eq!(pixel(&doubled, 5, 3), pixel(&photon_image, 2, 1));
+
+        let sheared = shearx(&square, 0.5);
+        assert_eq!((sheared.get_width(), sheared.get_height()), (30, 20));
+
+        let collapsed = affine_transform(
+            &photon_image,
+            &AffineMatrix::scaling(0.0, 1.0),
+            Interpolation::Bilinear,
+            background,
+            CanvasSize::Original,
+        );
+        assert!(collapsed
+            .get_raw_pixels()
+            .chunks(4)
+            .all(|px| px == [1, 2, 3, 255]));
+        let round_trip = AffineMatrix::rotation(30.0)
+            .then(&AffineMatrix::rotation(30.0).inverse().unwrap())
+            .apply(5.0, 3.0);
+        assert!((round_trip.0 - 5.0).abs() < 1e-4 && (round_trip.1 - 3.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_perspective() {
+        let corners = vec![8.0, 6.0, 33.0, 10.0, 30.0, 34.0, 5.0, 28.0];
+        let rect = vec![0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0];
+        let homography = Homography::from_points(rect.clone(), corners.clone()).unwrap();
+        for (src, dst) in rect.chunks(2).zip(corners.chunks(2)) {
+            let (x, y) = homography.apply(src[0], src[1]).unwrap();
+            assert!((x - dst[0]).abs() < 1e-3 && (y - dst[1]).abs() < 1e-3);
+        }
+        let collinear = vec![0.0, 0.0, 5.0, 5.0, 10.0, 10.0, 0.0, 10.0];
+        assert!(Homography::from_points(rect, collinear).is_none());
+
+        // A red square placed into a quadrilateral on a blue background.
+        let mut img = PhotonImage::new([0, 0, 255, 255].repeat(1600), 40, 40);
+        let red = PhotonImage::new([255, 0, 0, 255].repeat(100), 10, 10);
+        assert!(place_in_quad(
+            &mut img,
+            &red,
+            corners.clone(),
+            Interpolation::Bilinear
+        ));
+        let pixel = |img: &PhotonImage, x: u32, y: u32| {
+            let idx = ((y * img.get_width() + x) * 4) as usize;
+            img.get_raw_pixels()[idx..idx + 4].to_vec()
+        };
+        assert_eq!(pixel(&img, 19, 19), vec![255, 0, 0, 255]);
+        assert_eq!(pixel(&img, 2, 2), vec![0, 0, 255, 255]);
+        assert_eq!(pixel(&img, 37, 37), vec![0, 0, 255, 255]);
+        // Antialiased edges blend the two colours.
+        assert!(img
+            .get_raw_pixels()
+            .chunks(4)
+            .any(|px| px[0] > 0 && px[2] > 0 && px[3] == 255));
+
+        // Correcting the quadrilateral recovers the square, sized from its edges.
+        let page =
+            correct_perspective(&img, corners.clone(), 0, 0, Interpolation::Bicubic)
+                .unwrap();
+        assert_eq!((page.get_width(), page.get_height()), (26, 24));
+        assert_eq!(pixel(&page, 13, 12), vec![255, 0, 0, 255]);
+        let page =
+            correct_perspective(&img, corners.clone(), 10, 10, Interpolation::Nearest)
+                .unwrap();
+        assert!(page.get_raw_pixels()[..]
+            .chunks(4)
+            .skip(11)
+            .take(8)
+            .all(|px| px[0] > 200));
+
+        // Anything other than four points is rejected rather than panicking.
+        let three = corners[..6].to_vec();
+        assert!(Homography::from_points(three.clone(), corners.clone()).is_none());
+        assert!(
+            correct_perspective(&img, three.clone(), 0, 0, Interpolation::Nearest)
+                .is_none()
+        );
+        let before = img.clone();
+        assert!(!place_in_quad(
+            &mut img,
+            &red,
+            three,
+            Interpolation::Nearest
+        ));
+        assert_eq!(img.get_raw_pixels(), before.get_raw_pixels());
+    }
+
+    #[test]
+    fn test_resize_modes() {
+        // A 40x20 image, red on the left half and blue on the right.
+        let photon_image = PhotonImage::new(
+            (0..800)
+                .flat_map(|i| {
+                    if i % 40 < 20 {
+                        [255, 0, 0, 255]
+                    } else {
+                        [0, 0, 255, 255]
+                    }
+                })
+                .collect(),
+            40,
+            20,
+        );
+        let size = |img: &PhotonImage| (img.get_width(), img.get_height());
+        let pixel = |img: &PhotonImage, x: u32, y: u32| {
+            let idx = ((y * img.get_width() + x) * 4) as usize;
+            img.get_raw_pixels()[idx..idx + 4].to_vec()
+        };
+
+        let fit = resize_fit(&photon_image, 100, 100, SamplingFilter::Triangle);
+        assert_eq!(size(&fit), (100, 50));
+        let shrunk = resize_shrink(&photon_image, 100, 100, SamplingFilter::Triangle);
+        assert_eq!(size(&shrunk), (40, 20));
+        let shrunk = resize_shrink(&photon_image, 10, 10, SamplingFilter::Triangle);
+        assert_eq!(size(&shrunk), (10, 5));
+
+        let background = crate::Rgba::new(0, 255, 0, 255);
+        let contained =
+            resize_contain(&photon_image, 20, 20, SamplingFilter::Nearest, background);
+        assert_eq!(size(&contained), (20, 20));
+        assert_eq!(pixel(&contained, 10, 0), vec![0, 255, 0, 255]);
+        assert_eq!(pixel(&contained, 10, 19), vec![0, 255, 0, 255]);
+        assert_eq!(pixel(&contained, 2, 10), vec![255, 0, 0, 255]);
+
+        // Covering a square keeps the side chosen by the gravity.
+        let west = resize_cover(
+            &photon_image,
+            10,
+            10,
+            SamplingFilter::Nearest,
+            Gravity::West,
+        );
+        assert_eq!(size(&west), (10, 10));
+        assert!(west
+            .get_raw_pixels()
+            .chunks(4)
+            .all(|px| px == [255, 0, 0, 255]));
+        let east = resize_cover(
+            &photon_image,
+            10,
+            10,
+            SamplingFilter::Nearest,
+            Gravity::East,
+        );
+        assert!(east
+            .get_raw_pixels()
+            .chunks(4)
+            .all(|px| px == [0, 0, 255, 255]));
+        let centre = resize_cover(
+            &photon_image,
+            10,
+            10,
+            SamplingFilter::Nearest,
+            Gravity::Centre,
+        );
+        assert_eq!(pixel(&centre, 0, 5), vec![255, 0, 0, 255]);
+        assert_eq!(pixel(&centre, 9, 5), vec![0, 0, 255, 255]);
+
+        let large = PhotonImage::new([90, 120, 150, 255].repeat(1000 * 600), 1000, 600);
+        let thumb = thumbnail(&large, 64, 64);
+        assert_eq!(size(&thumb), (64, 38));
+        assert!(thumb.get_raw_pixels().chunks(4).all(|px| px
+            .iter()
+            .zip([90, 120, 150, 255])
+            .all(|(a, b)| a.abs_diff(b) <= 1)));
+        assert_eq!(size(&thumbnail(&photon_image, 64, 64)), (40, 20));
+
+        // Zero-sized boxes give 1 pixel images rather than empty ones.
+        assert_eq!(size(&thumbnail(&large, 0, 0)), (1, 1));
+        assert_eq!(
+            size(&resize_fit(&photon_image, 0, 5, SamplingFilter::Nearest)),
+            (1, 1)
+        );
+        let background = crate::Rgba::new(0, 0, 0, 0);
+        let contained =
+            resize_contain(&photon_image, 0, 0, SamplingFilter::Nearest, background);
+        assert_eq!(size(&contained), (1, 1));
+        let covered = resize_cover(
+            &photon_image,
+            0,
+            0,
+            SamplingFilter::Nearest,
+            Gravity::Centre,
+        );
+        assert_eq!(size(&covered), (1, 1));
+    }
+
+    #[test]
+    fn test_smart_crop() {
+        // A flat grey image with a checkerboard near its right edge.
+        let grey = [128, 128, 128, 255];
+        let busy = PhotonImage::new(
+            (0..100 * 50)
+                .flat_map(|i| {
+                    let (x, y) = (i % 100, i / 100);
+                    if (70..90).contains(&x) && (x / 2 + y / 2) % 2 == 0 {
+                        [255, 255, 255, 255]
+                    } else {
+                        grey
+                    }
+                })
+                .collect(),
+            100,
+            50,
+        );
+        let result = smart_crop(&busy, 50, 50);
+        let region = result.get_region();
+        assert_eq!((region.get_width(), region.get_height()), (50, 50));
+        assert!(region.get_x() <= 70 && region.get_x() + 50 >= 90);
+        let image = result.into_image();
+        assert_eq!((image.get_width(), image.get_height()), (50, 50));
+
+        // A tall image with a patch of skin near the top, resized after cropping.
+        let portrait = PhotonImage::new(
+            (0..40 * 120)
+                .flat_map(|i| {
+                    let (x, y) = (i % 40, i / 40);
+                    if (10..30).contains(&x) && (5..25).contains(&y) {
+                        [224, 172, 105, 255]
+                    } else {
+                        grey
+                    }
+                })
+                .collect(),
+            40,
+            120,
+        );
+        let result = smart_crop(&portrait, 20, 20);
+        let region = result.get_region();
+        assert_eq!(
+            (region.get_x(), region.get_width(), region.get_height()),
+            (0, 40, 40)
+        );
+        assert!(region.get_y() <= 5);
+        assert_eq!(result.get_image().get_width(), 20);
+
+        // Without anything interesting, the centre is kept.
+        let flat = PhotonImage::new(grey.repeat(60 * 20), 60, 20);
+        assert_eq!(smart_crop(&flat, 20, 20).get_region().get_x(), 20);
+
+        // An empty image is returned as is.
+        let empty = PhotonImage::new(vec![], 0, 10);
+        let result = smart_crop(&empty, 20, 20);
+        let region = result.get_region();
+        assert_eq!((region.get_width(), region.get_height()), (0, 0));
+        assert_eq!(
+            (
+                result.get_image().get_width(),
+                result.get_image().get_height()
+            ),
+            (0, 10)
+        );
+    }
+
+    #[test]
+    fn test_trim() {
+        // A white scan with slight noise in the margins, and dark content.
+        let scan = PhotonImage::new(
+            (0..30 * 20)
+                .flat_map(|i| {
+                    let (x, y) = (i % 30, i / 30);
+                    if (5..22).contains(&x) && (3..15).contains(&y) {
+                        [20, 30, 40, 255]
+                    } else {
+                        [255 - (i % 3) as u8, 255, 253, 255]
+                    }
+                })
+                .collect(),
+            30,
+            20,
+        );
+        let result = trim(&scan, 8);
+        let region = result.get_region();
+        assert_eq!(
+            (
+                region.get_x(),
+                region.get_y(),
+                region.get_width(),
+                region.get_height()
+            ),
+            (5, 3, 17, 12)
+        );
+        let trimmed = result.get_image();
+        assert_eq!((trimmed.get_width(), trimmed.get_height()), (17, 12));
+        assert!(trimmed
+            .get_raw_pixels()
+            .chunks(4)
+            .all(|px| px == [20, 30, 40, 255]));
+        // Without tolerance, the noise counts as content.
+        assert_ne!(trim_region(&scan, 0), region);
+
+        // Transparent padding, with content of any colour.
+        let sticker = |x0: u32| {
+            PhotonImage::new(
+                (0..20 * 20)
+                    .flat_map(|i| {
+                        let (x, y) = (i % 20, i / 20);
+                        if (x0..x0 + 4).contains(&x) && (8..12).contains(&y) {
+                            [0, 0, 0, 128]
+                        } else {
+                            [255, 255, 255, 0]
+                        }
+                    })
+                    .collect(),
+                20,
+                20,
+            )
+        };
+        let first = trim_region(&sticker(2), 0);
+        assert_eq!((first.get_x(), first.get_y(), first.get_width()), (2, 8, 4));
+        let both = first.union(&trim_region(&sticker(10), 0));
+        assert_eq!(
+            (both.get_x(), both.get_width(), both.get_height()),
+            (2, 12, 4)
+        );
+
+        // An image with no content is kept whole.
+        let blank = PhotonImage::new([255, 255, 255, 255].repeat(100), 10, 10);
+        assert_eq!(trim(&blank, 0).get_image().get_width(), 10);
+    }
+
+    #[test]
+    fn test_seam_carve_with_options() {
+        // Red and grey with the same luminance, so every seam costs the same.
+        let red = [255, 0, 0, 255];
+        let grey = [76, 76, 76, 255];
+        let white = [255, 255, 255, 255];
+        let black = [0, 0, 0, 255];
+        let columns = |pick: &dyn Fn(u32) -> [u8; 4]| {
+            PhotonImage::new((0..200).flat_map(|i| pick(i % 20)).collect(), 20, 10)
+        };
+        let count = |img: &PhotonImage, colour: [u8; 4]| {
+            img.get_raw_pixels()
+                .chunks(4)
+                .filter(|px| *px == colour)
+                .count()
+        };
+
+        // Protected columns survive shrinking.
+        let photon_image = columns(&|x| if x < 5 { red } else { grey });
+        let mut options = SeamCarveOptions::new();
+        options.set_protect_mask(columns(&|x| if x < 5 { white } else { black }));
+        let result = seam_carve_with_options(&photon_image, 10, 10, &options);
+        assert_eq!((result.get_width(), result.get_height()), (10, 10));
+        assert_eq!(count(&result, red), 50);
+
+        // A removed object is carved out, and seams are inserted to restore the size.
+        let photon_image = columns(&|x| if (6..9).contains(&x) { red } else { grey });
+        let mut options = SeamCarveOptions::new();
+        options.set_remove_mask(columns(&|x| {
+            if (6..9).contains(&x) {
+                white
+            } else {
+                black
+            }
+        }));
+        let result = seam_carve_with_options(&photon_image, 20, 10, &options);
+        assert_eq!((result.get_width(), result.get_height()), (20, 10));
+        assert_eq!(count(&result, red), 0);
+
+        // Enlarging in both directions, with either energy.
+        let photon_image = PhotonImage::new(
+            (0..64_u32)
+                .flat_map(|i| [(i * 4) as u8, (i * 7 % 256) as u8, 90, 255])
+                .collect(),
+            8,
+            8,
+        );
+        for forward_energy in [true, false] {
+            let mut options = SeamCarveOptions::new();
+            options.set_forward_energy(forward_energy);
+            let result = seam_carve_with_options(&photon_image, 13, 11, &options);
+            assert_eq!((result.get_width(), result.get_height()), (13, 11));
+            let result = seam_carve_with_options(&photon_image, 5, 6, &options);
+            assert_eq!((result.get_width(), result.get_height()), (5, 6));
+            // Every remaining pixel comes from the original image.
+            assert!(result.get_raw_pixels().chunks(4).all(|px| photon_image
+                .get_raw_pixels()
+                .chunks(4)
+                .any(|orig| orig == px)));
+        }
+    }
 }
@@ -0,0 +1,160 @@
+//! Tone mapping, to display high dynamic range (HDR) images.
+//!
+//! Load an HDR image with `native::open_image_linear`, then map its unbounded linear values
+//! down to a displayable PhotonImage with `tonemap`.
+
+use crate::linear::{gaussian_blur_linear, LinearImage};
+use crate::PhotonImage;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "enable_wasm")]
+use wasm_bindgen::prelude::*;
+
+/// A tone mapping operator.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToneMapOperator {
+    /// Reinhard's global operator, with a burn-out white point.
+    Reinhard = 1,
+    /// Reinhard's operator compressing each pixel against its blurred surroundings, which
+    /// keeps more local contrast.
+    ReinhardLocal = 2,
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces = 3,
+    /// John Hable's filmic curve from Uncharted 2.
+    Hable = 4,
+    /// Drago's adaptive logarithmic mapping.
+    Drago = 5,
+}
+
+/// Rec. 709 luminance of a linear RGB pixel.
+fn luminance(px: &[f32]) -> f32 {
+    0.2126 * px[0] + 0.7152 * px[1] + 0.0722 * px[2]
+}
+
+fn aces(x: f32) -> f32 {
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+fn hable(x: f32) -> f32 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+/// Tone map a linear HDR image to a displayable PhotonImage.
+///
+/// # Arguments
+/// * `img` - A LinearImage, whose values may exceed 1.
+/// * `operator` - The tone mapping operator.
+/// * `exposure` - Exposure adjustment applied first, in stops. 0 leaves the image as is.
+/// * `white_point` - The smallest luminance, after exposure, mapped to pure white.
+/// Pass 0 to use the brightest pixel of the image.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::native::{open_image_linear, save_image};
+/// use photon_rs::tonemap::{tonemap, ToneMapOperator};
+///
+/// let hdr = open_image_linear("scene.hdr").expect("File should open");
+/// let img = tonemap(&hdr, ToneMapOperator::Aces, 0.5, 0.0);
+/// save_image(img, "scene.jpg").expect("Save failed");
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn tonemap(
+    img: &LinearImage,
+    operator: ToneMapOperator,
+    exposure: f32,
+    white_point: f32,
+) -> PhotonImage {
+    let scale = exposure.exp2();
+    let mut pixels = img.pixels.clone();
+    for px in pixels.chunks_mut(4) {
+        for val in px.iter_mut().take(3) {
+            *val = val.max(0.0) * scale;
+        }
+    }
+
+    let white_point = if white_point > 0.0 {
+        white_point
+    } else {
+        pixels
+            .chunks(4)
+            .map(luminance)
+            .fold(0.0_f32, f32::max)
+            .max(f32::EPSILON)
+    };
+
+    match operator {
+        ToneMapOperator::Reinhard | ToneMapOperator::Drago => {
+            // Drago's bias, which controls how much the darkest areas are brightened.
+            const BIAS: f32 = 0.85;
+            let drago_scale = 1.0 / (white_point + 1.0).log10();
+            let bias_power = BIAS.ln() / 0.5_f32.ln();
+
+            for px in pixels.chunks_mut(4) {
+                let lum = luminance(px);
+                if lum <= 0.0 {
+                    continue;
+                }
+                let mapped = if operator == ToneMapOperator::Reinhard {
+                    lum * (1.0 + lum / (white_point * white_point)) / (1.0 + lum)
+                } else {
+                    let denominator =
+                        (2.0 + 8.0 * (lum / white_point).powf(bias_power)).ln();
+                    drago_scale * (lum + 1.0).ln() / denominator
+                };
+                for val in px.iter_mut().take(3) {
+                    *val *= mapped / lum;
+                }
+            }
+        }
+        ToneMapOperator::ReinhardLocal => {
+            let lums: Vec<f32> = pixels.chunks(4).map(luminance).collect();
+            let mut surround = LinearImage::new(
+                lums.iter()
+                    .flat_map(|lum| [*lum, *lum, *lum, 1.0])
+                    .collect(),
+                img.width,
+                img.height,
+            );
+            let radius = (img.width.min(img.height) / 50).max(1) as i32;
+            gaussian_blur_linear(&mut surround, radius);
+
+            for ((px, lum), local) in pixels
+                .chunks_mut(4)
+                .zip(lums)
+                .zip(surround.pixels.chunks(4).map(|px| px[0]))
+            {
+                if lum <= 0.0 {
+                    continue;
+                }
+                let mapped =
+                    lum * (1.0 + lum / (white_point * white_point)) / (1.0 + local);
+                for val in px.iter_mut().take(3) {
+                    *val *= mapped / lum;
+                }
+            }
+        }
+        ToneMapOperator::Aces | ToneMapOperator::Hable => {
+            let curve = if operator == ToneMapOperator::Aces {
+                aces
+            } else {
+                hable
+            };
+            let white = curve(white_point);
+            for px in pixels.chunks_mut(4) {
+                for val in px.iter_mut().take(3) {
+                    *val = curve(*val) / white;
+                }
+            }
+        }
+    }
+
+    LinearImage::new(pixels, img.width, img.height).to_photon_image()
+}
@@ -1,12 +1,14 @@
 //! Image transformations, ie: scale, crop, resize, etc.,
 
+use crate::conv::sobel_global;
 use crate::helpers;
 use crate::iter::ImageIterator;
+use crate::seam::Carver;
 use crate::{PhotonImage, Rgba};
 use image::imageops::FilterType;
 use image::DynamicImage::ImageRgba8;
-use image::{GenericImageView, ImageBuffer, Pixel, RgbaImage};
-use std::cmp::min;
+use image::{GenericImageView, ImageBuffer, RgbaImage};
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "enable_wasm")]
 use wasm_bindgen::prelude::*;
@@ -15,6 +17,14 @@ use wasm_bindgen::{Clamped, JsCast};
 #[cfg(all(feature = "enable_wasm", target_arch = "wasm32"))]
 use web_sys::{HtmlCanvasElement, ImageData};
 
+/// Longest side of the downscaled image which `smart_crop` analyses.
+const SMART_CROP_ANALYSIS_SIZE: u32 = 256;
+
+/// Importance of edges, saturated colours and skin tones when choosing a smart crop.
+const SMART_CROP_EDGE_WEIGHT: f32 = 1.0;
+const SMART_CROP_SATURATION_WEIGHT: f32 = 0.3;
+const SMART_CROP_SKIN_WEIGHT: f32 = 1.8;
+
 /// Crop an image.
 ///
 /// # Arguments
@@ -179,7 +189,9 @@ pub enum SamplingFilter {
     Lanczos3 = 5,
 }
 
-fn filter_type_from_sampling_filter(sampling_filter: SamplingFilter) -> FilterType {
+pub(crate) fn filter_type_from_sampling_filter(
+    sampling_filter: SamplingFilter,
+) -> FilterType {
     match sampling_filter {
         SamplingFilter::Nearest => FilterType::Nearest,
         SamplingFilter::Triangle => FilterType::Triangle,
@@ -281,9 +293,270 @@ pub fn resize(
     }
 }
 
+/// Position of the part of an image which is kept when it's cropped.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gravity {
+    Centre = 1,
+    North = 2,
+    NorthEast = 3,
+    East = 4,
+    SouthEast = 5,
+    South = 6,
+    SouthWest = 7,
+    West = 8,
+    NorthWest = 9,
+}
+
+impl Gravity {
+    /// Horizontal and vertical position, from 0 (left or top) to 1 (right or bottom).
+    fn position(&self) -> (f32, f32) {
+        match self {
+            Gravity::Centre => (0.5, 0.5),
+            Gravity::North => (0.5, 0.0),
+            Gravity::NorthEast => (1.0, 0.0),
+            Gravity::East => (1.0, 0.5),
+            Gravity::SouthEast => (1.0, 1.0),
+            Gravity::South => (0.5, 1.0),
+            Gravity::SouthWest => (0.0, 1.0),
+            Gravity::West => (0.0, 0.5),
+            Gravity::NorthWest => (0.0, 0.0),
+        }
+    }
+}
+
+/// Largest size with the aspect ratio of the image which fits within a box, or the smallest
+/// which covers it.
+fn scaled_dimensions(
+    photon_img: &PhotonImage,
+    width: u32,
+    height: u32,
+    cover: bool,
+) -> (u32, u32) {
+    let scale_x = width as f64 / photon_img.get_width() as f64;
+    let scale_y = height as f64 / photon_img.get_height() as f64;
+    let scale = if cover {
+        scale_x.max(scale_y)
+    } else {
+        scale_x.min(scale_y)
+    };
+    let scaled_width = (photon_img.get_width() as f64 * scale).round().max(1.0) as u32;
+    let scaled_height = (photon_img.get_height() as f64 * scale).round().max(1.0) as u32;
+    if cover {
+        (scaled_width.max(width), scaled_height.max(height))
+    } else {
+        (scaled_width.min(width), scaled_height.min(height))
+    }
+}
+
+/// Resize an image to fit within a box, keeping its aspect ratio.
+/// The result is as large as possible, but may be smaller than the box in one dimension.
+///
+/// # Arguments
+/// * `img` - A PhotonImage.
+/// * `width` - Width of the box. 0 is treated as 1.
+/// * `height` - Height of the box. 0 is treated as 1.
+/// * `sampling_filter` - Nearest = 1, Triangle = 2, CatmullRom = 3, Gaussian = 4, Lanczos3 = 5
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to fit an image within 800x600:
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::{resize_fit, SamplingFilter};
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let resized_img = resize_fit(&img, 800, 600, SamplingFilter::Lanczos3);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn resize_fit(
+    photon_img: &PhotonImage,
+    width: u32,
+    height: u32,
+    sampling_filter: SamplingFilter,
+) -> PhotonImage {
+    let (width, height) = (width.max(1), height.max(1));
+    let (width, height) = scaled_dimensions(photon_img, width, height, false);
+    resize(photon_img, width, height, sampling_filter)
+}
+
+/// Shrink an image to fit within a box, keeping its aspect ratio.
+/// Unlike `resize_fit`, images which already fit are returned unchanged, rather than enlarged.
+///
+/// # Arguments
+/// * `img` - A PhotonImage.
+/// * `width` - Width of the box. 0 is treated as 1.
+/// * `height` - Height of the box. 0 is treated as 1.
+/// * `sampling_filter` - Nearest = 1, Triangle = 2, CatmullRom = 3, Gaussian = 4, Lanczos3 = 5
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to limit an image to 2048x2048:
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::{resize_shrink, SamplingFilter};
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let resized_img = resize_shrink(&img, 2048, 2048, SamplingFilter::Lanczos3);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn resize_shrink(
+    photon_img: &PhotonImage,
+    width: u32,
+    height: u32,
+    sampling_filter: SamplingFilter,
+) -> PhotonImage {
+    if photon_img.get_width() <= width && photon_img.get_height() <= height {
+        return photon_img.clone();
+    }
+    resize_fit(photon_img, width, height, sampling_filter)
+}
+
+/// Resize an image to fit within a box, keeping its aspect ratio, and letterbox it to
+/// exactly the size of the box.
+///
+/// # Arguments
+/// * `img` - A PhotonImage.
+/// * `width` - Width of the result. 0 is treated as 1.
+/// * `height` - Height of the result. 0 is treated as 1.
+/// * `sampling_filter` - Nearest = 1, Triangle = 2, CatmullRom = 3, Gaussian = 4, Lanczos3 = 5
+/// * `background` - Colour of the letterbox bars.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to letterbox an image to 1920x1080 with black bars:
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::{resize_contain, SamplingFilter};
+/// use photon_rs::Rgba;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let black = Rgba::new(0, 0, 0, 255);
+/// let resized_img = resize_contain(&img, 1920, 1080, SamplingFilter::Lanczos3, black);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn resize_contain(
+    photon_img: &PhotonImage,
+    width: u32,
+    height: u32,
+    sampling_filter: SamplingFilter,
+    background: Rgba,
+) -> PhotonImage {
+    let (width, height) = (width.max(1), height.max(1));
+    let resized = resize_fit(photon_img, width, height, sampling_filter);
+    let pad_x = width - resized.get_width();
+    let pad_y = height - resized.get_height();
+    let padded = padding_left(&resized, pad_x / 2, background.clone());
+    let padded = padding_right(&padded, pad_x - pad_x / 2, background.clone());
+    let padded = padding_top(&padded, pad_y / 2, background.clone());
+    padding_bottom(&padded, pad_y - pad_y / 2, background)
+}
+
+/// Resize an image to cover a box, keeping its aspect ratio, and crop it to exactly the size
+/// of the box.
+///
+/// # Arguments
+/// * `img` - A PhotonImage.
+/// * `width` - Width of the result. 0 is treated as 1.
+/// * `height` - Height of the result. 0 is treated as 1.
+/// * `sampling_filter` - Nearest = 1, Triangle = 2, CatmullRom = 3, Gaussian = 4, Lanczos3 = 5
+/// * `gravity` - Which part of the image to keep when cropping.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to fill a 1200x630 social card, keeping the top of the image:
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::{resize_cover, Gravity, SamplingFilter};
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let resized_img =
+///     resize_cover(&img, 1200, 630, SamplingFilter::Lanczos3, Gravity::North);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn resize_cover(
+    photon_img: &PhotonImage,
+    width: u32,
+    height: u32,
+    sampling_filter: SamplingFilter,
+    gravity: Gravity,
+) -> PhotonImage {
+    let (width, height) = (width.max(1), height.max(1));
+    let (scaled_width, scaled_height) =
+        scaled_dimensions(photon_img, width, height, true);
+    let resized = resize(photon_img, scaled_width, scaled_height, sampling_filter);
+    let (pos_x, pos_y) = gravity.position();
+    let x = ((scaled_width - width) as f32 * pos_x).round() as u32;
+    let y = ((scaled_height - height) as f32 * pos_y).round() as u32;
+    crop(&resized, x, y, x + width, y + height)
+}
+
+/// Halve the size of an image by averaging each 2x2 block of pixels.
+fn halve(photon_img: &PhotonImage) -> PhotonImage {
+    let src_width = photon_img.get_width() as usize;
+    let src_height = photon_img.get_height() as usize;
+    let width = (src_width / 2).max(1);
+    let height = (src_height / 2).max(1);
+    let pixels = &photon_img.raw_pixels;
+
+    let mut raw_pixels = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let (y0, y1) = ((y * 2).min(src_height - 1), (y * 2 + 1).min(src_height - 1));
+        for x in 0..width {
+            let (x0, x1) = ((x * 2).min(src_width - 1), (x * 2 + 1).min(src_width - 1));
+            for chan in 0..4 {
+                let sum: u32 = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)]
+                    .iter()
+                    .map(|(px, py)| pixels[(py * src_width + px) * 4 + chan] as u32)
+                    .sum();
+                raw_pixels.push(((sum + 2) / 4) as u8);
+            }
+        }
+    }
+
+    PhotonImage::new(raw_pixels, width as u32, height as u32)
+}
+
+/// Create a thumbnail which fits within a box, keeping the image's aspect ratio.
+///
+/// Large images are first halved repeatedly, which is fast, then resized to the final size
+/// with a Lanczos filter. Images which already fit are returned unchanged.
+///
+/// # Arguments
+/// * `img` - A PhotonImage.
+/// * `width` - Maximum width of the thumbnail. 0 is treated as 1.
+/// * `height` - Maximum height of the thumbnail. 0 is treated as 1.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to create a thumbnail of at most 256x256:
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::thumbnail;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let thumb = thumbnail(&img, 256, 256);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn thumbnail(photon_img: &PhotonImage, width: u32, height: u32) -> PhotonImage {
+    let (width, height) = (width.max(1), height.max(1));
+    if photon_img.get_width() <= width && photon_img.get_height() <= height {
+        return photon_img.clone();
+    }
+    let (width, height) = scaled_dimensions(photon_img, width, height, false);
+
+    let mut thumb = photon_img.clone();
+    while thumb.get_width() >= width * 2 && thumb.get_height() >= height * 2 {
+        thumb = halve(&thumb);
+    }
+    resize(&thumb, width, height, SamplingFilter::Lanczos3)
+}
+
 /// Resize image using seam carver.
-/// Resize only if new dimensions are smaller, than original image.
 /// # NOTE: This is still experimental feature, and pretty slow.
+/// Shrinking removes the seams with the lowest backward energy, as it always has.
+/// Enlarging goes through `seam_carve_with_options` with backward energy, which also
+/// documents how seams are inserted and how regions can be protected or removed.
 ///
 /// # Arguments
 /// * `img` - A PhotonImage.
@@ -303,40 +576,150 @@ pub fn resize(
 /// ```
 #[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
 pub fn seam_carve(img: &PhotonImage, width: u32, height: u32) -> PhotonImage {
-    let mut img: RgbaImage = ImageBuffer::from_raw(
+    // An empty image has no seams to remove or duplicate.
+    if img.get_width() == 0 || img.get_height() == 0 {
+        return img.clone();
+    }
+
+    let mut carved: RgbaImage = ImageBuffer::from_raw(
         img.get_width(),
         img.get_height(),
         img.raw_pixels.to_vec(),
     )
     .unwrap();
-    let (w, h) = img.dimensions();
+    let (w, h) = carved.dimensions();
     let (diff_w, diff_h) = (w - w.min(width), h - h.min(height));
 
     for _ in 0..diff_w {
-        let vec_steam = imageproc::seam_carving::find_vertical_seam(&img);
-        img = imageproc::seam_carving::remove_vertical_seam(&img, &vec_steam);
+        let vec_steam = imageproc::seam_carving::find_vertical_seam(&carved);
+        carved = imageproc::seam_carving::remove_vertical_seam(&carved, &vec_steam);
     }
     if diff_h.ne(&0_u32) {
-        img = image::imageops::rotate90(&img);
+        carved = image::imageops::rotate90(&carved);
         for _ in 0..diff_h {
-            let vec_steam = imageproc::seam_carving::find_vertical_seam(&img);
-            img = imageproc::seam_carving::remove_vertical_seam(&img, &vec_steam);
+            let vec_steam = imageproc::seam_carving::find_vertical_seam(&carved);
+            carved = imageproc::seam_carving::remove_vertical_seam(&carved, &vec_steam);
         }
-        img = image::imageops::rotate270(&img);
+        carved = image::imageops::rotate270(&carved);
     }
 
-    let img = ImageRgba8(img);
+    let carved = ImageRgba8(carved);
+    let carved = PhotonImage {
+        width: carved.width(),
+        height: carved.height(),
+        raw_pixels: carved.into_bytes(),
+    };
+    if width <= carved.get_width() && height <= carved.get_height() {
+        return carved;
+    }
 
-    let width = img.width();
-    let height = img.height();
+    let mut options = SeamCarveOptions::new();
+    options.set_forward_energy(false);
+    seam_carve_with_options(&carved, width, height, &options)
+}
 
-    PhotonImage {
-        raw_pixels: img.into_bytes(),
-        width,
-        height,
+/// Options for content-aware resizing with `seam_carve_with_options`.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SeamCarveOptions {
+    protect_mask: Option<PhotonImage>,
+    remove_mask: Option<PhotonImage>,
+    forward_energy: bool,
+}
+
+impl Default for SeamCarveOptions {
+    fn default() -> Self {
+        SeamCarveOptions::new()
+    }
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl SeamCarveOptions {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create options with backward energy and no masks.
+    pub fn new() -> SeamCarveOptions {
+        SeamCarveOptions {
+            protect_mask: None,
+            remove_mask: None,
+            forward_energy: false,
+        }
+    }
+
+    /// Set a mask of regions to keep intact, such as faces, where it is white. It must be
+    /// the same size as the image.
+    pub fn set_protect_mask(&mut self, mask: PhotonImage) {
+        self.protect_mask = Some(mask);
+    }
+
+    /// Set a mask of regions to remove, such as an unwanted object, where it is white. It
+    /// must be the same size as the image.
+    pub fn set_remove_mask(&mut self, mask: PhotonImage) {
+        self.remove_mask = Some(mask);
+    }
+
+    /// Set whether seams are chosen by forward energy, the cost of the edges they create
+    /// when removed, which gives fewer artifacts than the image's own edges (backward
+    /// energy). Defaults to false.
+    pub fn set_forward_energy(&mut self, forward_energy: bool) {
+        self.forward_energy = forward_energy;
     }
 }
 
+/// Resize an image with content-aware seam carving, which removes or duplicates the least
+/// noticeable paths of pixels, keeping important content undistorted.
+///
+/// Seams are removed to shrink the image or duplicated to enlarge it, masked regions can be
+/// protected or removed, and only the energy of pixels next to each removed seam is updated.
+/// Regions in the remove mask are carved out first, then seams are removed or inserted to
+/// reach the requested size, so passing the original size removes an object while keeping
+/// the image's size.
+///
+/// # Arguments
+/// * `img` - A PhotonImage.
+/// * `width` - New width.
+/// * `height` - New height.
+/// * `options` - Masks and the energy function.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to remove an object from a photo, keeping its size:
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::{seam_carve_with_options, SeamCarveOptions};
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let mask = open_image("object_mask.png").expect("File should open");
+/// let mut options = SeamCarveOptions::new();
+/// options.set_remove_mask(mask);
+/// let result =
+///     seam_carve_with_options(&img, img.get_width(), img.get_height(), &options);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn seam_carve_with_options(
+    img: &PhotonImage,
+    width: u32,
+    height: u32,
+    options: &SeamCarveOptions,
+) -> PhotonImage {
+    // An empty image has no seams to remove or duplicate.
+    if img.get_width() == 0 || img.get_height() == 0 {
+        return img.clone();
+    }
+
+    let mut carver = Carver::new(
+        img,
+        options.protect_mask.as_ref(),
+        options.remove_mask.as_ref(),
+        options.forward_energy,
+    );
+    carver.remove_marked();
+    carver.resize_width(width as usize);
+    carver.transpose();
+    carver.resize_width(height as usize);
+    carver.transpose();
+    carver.into_image()
+}
+
 /// Shear the image along the X axis.
 /// A sheared PhotonImage is returned.
 ///
@@ -356,48 +739,13 @@ pub fn seam_carve(img: &PhotonImage, width: u32, height: u32) -> PhotonImage {
 /// ```
 #[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
 pub fn shearx(photon_img: &PhotonImage, shear: f32) -> PhotonImage {
-    let img = helpers::dyn_image_from_raw(photon_img);
-    let src_width = img.width();
-    let src_height = img.height();
-
-    let maxskew = shear * (src_height as f32);
-    let dst_width = maxskew.floor().abs() as u32 + src_width;
-
-    let mut delta = 0;
-    if shear < 0. {
-        delta = dst_width - src_width;
-    }
-
-    let mut sheared_image: RgbaImage = ImageBuffer::new(dst_width, src_height);
-
-    for old_y in 0..src_height {
-        let skew = shear * (old_y as f32 + 0.5);
-        let skewi = skew.floor() as i32 + delta as i32;
-        let skewf = skew.fract().abs();
-        let mut oleft = image::Rgba([0_u8, 0_u8, 0_u8, 0_u8]);
-        for old_x in (0..src_width).rev() {
-            let mut pixel = img.get_pixel(old_x, old_y);
-            let mut left = pixel.map(|val| (val as f32 * skewf) as u8);
-            if shear >= 0. {
-                left = pixel.map2(&left, |val1, val2| val1 - val2);
-            }
-            pixel = pixel.map2(&left, |val1, val2| val1 - val2);
-            pixel = pixel.map2(&oleft, |val1, val2| {
-                min(val1 as u16 + val2 as u16, 255_u16) as u8
-            });
-            let new_x = (old_x as i32 + skewi) as u32;
-            sheared_image.put_pixel(new_x, old_y, pixel);
-            oleft = left;
-        }
-        sheared_image.put_pixel(skewi as u32, old_y, oleft);
-    }
-
-    let dynimage = ImageRgba8(sheared_image);
-    let width = dynimage.width();
-    let height = dynimage.height();
-    let raw_pixels = dynimage.into_bytes();
-
-    PhotonImage::new(raw_pixels, width, height)
+    affine_transform(
+        photon_img,
+        &AffineMatrix::shearing(shear, 0.0),
+        Interpolation::Bilinear,
+        Rgba::new(0, 0, 0, 0),
+        CanvasSize::Expand,
+    )
 }
 
 /// Shear the image along the Y axis.
@@ -419,48 +767,13 @@ pub fn shearx(photon_img: &PhotonImage, shear: f32) -> PhotonImage {
 /// ```
 #[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
 pub fn sheary(photon_img: &PhotonImage, shear: f32) -> PhotonImage {
-    let img = helpers::dyn_image_from_raw(photon_img);
-    let src_width = img.width();
-    let src_height = img.height();
-
-    let maxskew = shear * (src_width as f32);
-    let dst_height = maxskew.floor().abs() as u32 + src_height;
-
-    let mut delta = 0;
-    if shear < 0. {
-        delta = dst_height - src_height;
-    }
-
-    let mut sheared_image: RgbaImage = ImageBuffer::new(src_width, dst_height);
-
-    for old_x in 0..src_width {
-        let skew = shear * (old_x as f32 + 0.5);
-        let skewi = skew.floor() as i32 + delta as i32;
-        let skewf = skew.fract().abs();
-        let mut oleft = image::Rgba([0_u8, 0_u8, 0_u8, 0_u8]);
-        for old_y in (0..src_height).rev() {
-            let mut pixel = img.get_pixel(old_x, old_y);
-            let mut left = pixel.map(|val| (val as f32 * skewf).floor() as u8);
-            if shear >= 0. {
-                left = pixel.map2(&left, |val1, val2| val1 - val2);
-            }
-            pixel = pixel.map2(&left, |val1, val2| val1 - val2);
-            pixel = pixel.map2(&oleft, |val1, val2| {
-                min(val1 as u16 + val2 as u16, 255_u16) as u8
-            });
-            let new_y = (old_y as i32 + skewi) as u32;
-            sheared_image.put_pixel(old_x, new_y, pixel);
-            oleft = left;
-        }
-        sheared_image.put_pixel(old_x, skewi as u32, oleft);
-    }
-
-    let dynimage = ImageRgba8(sheared_image);
-    let width = dynimage.width();
-    let height = dynimage.height();
-    let raw_pixels = dynimage.into_bytes();
-
-    PhotonImage::new(raw_pixels, width, height)
+    affine_transform(
+        photon_img,
+        &AffineMatrix::shearing(0.0, shear),
+        Interpolation::Bilinear,
+        Rgba::new(0, 0, 0, 0),
+        CanvasSize::Expand,
+    )
 }
 
 /// Apply uniform padding around the PhotonImage
@@ -717,8 +1030,8 @@ pub fn padding_bottom(
     PhotonImage::new(img_padded_buffer, img_width, height_padded)
 }
 
-/// Rotate the PhotonImage on an arbitrary angle
-/// A rotated PhotonImage is returned.
+/// Rotate the PhotonImage clockwise on an arbitrary angle, expanding the canvas to fit.
+/// A rotated PhotonImage is returned, with transparent corners.
 ///
 /// # Arguments
 /// * `img` - A PhotonImage. See the PhotonImage struct for details.
@@ -737,47 +1050,705 @@ pub fn padding_bottom(
 #[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
 #[must_use]
 pub fn rotate(photon_img: &PhotonImage, angle: f32) -> PhotonImage {
-    // 390, 750 and 30 degrees represent the same angle. Trim 360.
-    let full_circle_count = angle as i32 / 360;
-    let normalized_angle = angle as i32 - full_circle_count * 360;
-    if normalized_angle == 0 {
-        return photon_img.clone();
+    rotate_with_options(
+        photon_img,
+        angle,
+        Interpolation::Bilinear,
+        Rgba::new(0, 0, 0, 0),
+        CanvasSize::Expand,
+    )
+}
+
+/// Rotate the PhotonImage clockwise about its centre on an arbitrary angle.
+/// A rotated PhotonImage is returned.
+///
+/// # Arguments
+/// * `img` - A PhotonImage. See the PhotonImage struct for details.
+/// * `angle` - Rotation angle in degrees.
+/// * `interpolation` - How pixels are sampled.
+/// * `background` - Colour of the areas outside the original image.
+/// * `canvas` - Whether to expand the canvas to fit the rotated image, or keep the original size.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to straighten a photo by 2.5 degrees, keeping its size:
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::{rotate_with_options, CanvasSize, Interpolation};
+/// use photon_rs::Rgba;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let background = Rgba::new(255, 255, 255, 255);
+/// let rotated_img = rotate_with_options(
+///     &img,
+///     -2.5,
+///     Interpolation::Bicubic,
+///     background,
+///     CanvasSize::Original,
+/// );
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[must_use]
+pub fn rotate_with_options(
+    photon_img: &PhotonImage,
+    angle: f32,
+    interpolation: Interpolation,
+    background: Rgba,
+    canvas: CanvasSize,
+) -> PhotonImage {
+    let centre_x = photon_img.get_width() as f32 / 2.0;
+    let centre_y = photon_img.get_height() as f32 / 2.0;
+    let matrix = AffineMatrix::translation(-centre_x, -centre_y)
+        .then(&AffineMatrix::rotation(angle))
+        .then(&AffineMatrix::translation(centre_x, centre_y));
+    affine_transform(photon_img, &matrix, interpolation, background, canvas)
+}
+
+/// Scale the PhotonImage by a factor along each axis.
+/// A scaled PhotonImage is returned.
+///
+/// # Arguments
+/// * `img` - A PhotonImage. See the PhotonImage struct for details.
+/// * `scale_x` - Horizontal scale factor.
+/// * `scale_y` - Vertical scale factor.
+/// * `interpolation` - How pixels are sampled.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to make an image 50% wider:
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::{scale, Interpolation};
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let scaled_img = scale(&img, 1.5, 1.0, Interpolation::Bicubic);
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn scale(
+    photon_img: &PhotonImage,
+    scale_x: f32,
+    scale_y: f32,
+    interpolation: Interpolation,
+) -> PhotonImage {
+    affine_transform(
+        photon_img,
+        &AffineMatrix::scaling(scale_x, scale_y),
+        interpolation,
+        Rgba::new(0, 0, 0, 0),
+        CanvasSize::Expand,
+    )
+}
+
+/// How pixels are sampled when an image is warped.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// The nearest pixel. Fastest, and keeps hard edges, but jagged when rotating.
+    Nearest = 1,
+    /// Linear interpolation between the four nearest pixels.
+    Bilinear = 2,
+    /// Catmull-Rom interpolation between the sixteen nearest pixels. Sharper than bilinear.
+    Bicubic = 3,
+}
+
+/// Size of the canvas of a warped image.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CanvasSize {
+    /// Keep the size of the original image, cropping whatever is moved outside it.
+    Original = 1,
+    /// Expand (or shrink) the canvas to the bounding box of the warped image.
+    Expand = 2,
+}
+
+/// A 2D affine transformation, mapping a point `(x, y)` of the source image to
+/// `(a * x + b * y + c, d * x + e * y + f)` in the output image.
+///
+/// Coordinates are in pixels from the top left corner of the image, with the y axis pointing
+/// down. Transformations are combined with `then`.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct AffineMatrix {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+    f: f32,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl AffineMatrix {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create a matrix from its coefficients, in row order.
+    pub fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> AffineMatrix {
+        AffineMatrix { a, b, c, d, e, f }
     }
 
-    // Handle negative angles. -80 describes the same angle as 360 - 80 = 280.
-    let positive_angle = if normalized_angle < 0 {
-        normalized_angle + 360
-    } else {
-        normalized_angle
+    /// The identity transformation, which leaves the image unchanged.
+    pub fn identity() -> AffineMatrix {
+        AffineMatrix::new(1.0, 0.0, 0.0, 0.0, 1.0, 0.0)
+    }
+
+    /// Move the image by `x` pixels right and `y` pixels down.
+    pub fn translation(x: f32, y: f32) -> AffineMatrix {
+        AffineMatrix::new(1.0, 0.0, x, 0.0, 1.0, y)
+    }
+
+    /// Scale the image about the origin.
+    pub fn scaling(scale_x: f32, scale_y: f32) -> AffineMatrix {
+        AffineMatrix::new(scale_x, 0.0, 0.0, 0.0, scale_y, 0.0)
+    }
+
+    /// Rotate the image clockwise about the origin, by an angle in degrees.
+    pub fn rotation(angle: f32) -> AffineMatrix {
+        // Right angles are exact, so rotating by them doesn't resample the image.
+        let angle = angle.rem_euclid(360.0);
+        let (sin, cos) = if angle == 0.0 {
+            (0.0, 1.0)
+        } else if angle == 90.0 {
+            (1.0, 0.0)
+        } else if angle == 180.0 {
+            (0.0, -1.0)
+        } else if angle == 270.0 {
+            (-1.0, 0.0)
+        } else {
+            angle.to_radians().sin_cos()
+        };
+        AffineMatrix::new(cos, -sin, 0.0, sin, cos, 0.0)
+    }
+
+    /// Shear the image, moving each pixel right by `shear_x` times its y coordinate, and
+    /// down by `shear_y` times its x coordinate.
+    pub fn shearing(shear_x: f32, shear_y: f32) -> AffineMatrix {
+        AffineMatrix::new(1.0, shear_x, 0.0, shear_y, 1.0, 0.0)
+    }
+
+    /// The transformation which applies this one, followed by `next`.
+    pub fn then(&self, next: &AffineMatrix) -> AffineMatrix {
+        AffineMatrix::new(
+            next.a * self.a + next.b * self.d,
+            next.a * self.b + next.b * self.e,
+            next.a * self.c + next.b * self.f + next.c,
+            next.d * self.a + next.e * self.d,
+            next.d * self.b + next.e * self.e,
+            next.d * self.c + next.e * self.f + next.f,
+        )
+    }
+
+    /// The transformation which undoes this one, if it doesn't collapse the image to a line or
+    /// a point.
+    pub fn inverse(&self) -> Option<AffineMatrix> {
+        let det = self.a as f64 * self.e as f64 - self.b as f64 * self.d as f64;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let (a, b, c) = (self.a as f64, self.b as f64, self.c as f64);
+        let (d, e, f) = (self.d as f64, self.e as f64, self.f as f64);
+        Some(AffineMatrix::new(
+            (e / det) as f32,
+            (-b / det) as f32,
+            ((b * f - c * e) / det) as f32,
+            (-d / det) as f32,
+            (a / det) as f32,
+            ((c * d - a * f) / det) as f32,
+        ))
+    }
+}
+
+impl AffineMatrix {
+    pub(crate) fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.a * x + self.b * y + self.c,
+            self.d * x + self.e * y + self.f,
+        )
+    }
+}
+
+/// Catmull-Rom weights of the four pixels around a sample, at offsets -1 to 2 from the pixel
+/// before it, where `t` is the distance of the sample from that pixel.
+fn cubic_weights(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        (-t3 + 2.0 * t2 - t) / 2.0,
+        (3.0 * t3 - 5.0 * t2 + 2.0) / 2.0,
+        (-3.0 * t3 + 4.0 * t2 + t) / 2.0,
+        (t3 - t2) / 2.0,
+    ]
+}
+
+/// Sample an image at a point, in pixel index coordinates, returning a premultiplied colour.
+/// Points outside the image take the (premultiplied) background colour.
+pub(crate) fn sample_premultiplied(
+    photon_img: &PhotonImage,
+    x: f32,
+    y: f32,
+    interpolation: Interpolation,
+    background: [f32; 4],
+) -> [f32; 4] {
+    let width = photon_img.width as i64;
+    let height = photon_img.height as i64;
+    let fetch = |px: i64, py: i64| {
+        if px < 0 || py < 0 || px >= width || py >= height {
+            return background;
+        }
+        let idx = ((py * width + px) * 4) as usize;
+        let pixel = &photon_img.raw_pixels[idx..idx + 4];
+        let alpha = pixel[3] as f32 / 255.0;
+        [
+            pixel[0] as f32 * alpha,
+            pixel[1] as f32 * alpha,
+            pixel[2] as f32 * alpha,
+            pixel[3] as f32,
+        ]
     };
 
-    let right_angle_count = positive_angle / 90;
-    let mut rgba_img: RgbaImage = ImageBuffer::from_raw(
-        photon_img.get_width(),
-        photon_img.get_height(),
-        photon_img.get_raw_pixels().to_vec(),
-    )
-    .unwrap();
-    for _ in 0..right_angle_count {
-        rgba_img = image::imageops::rotate90(&rgba_img);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let mut colour = [0.0; 4];
+    let mut accumulate = |px: i64, py: i64, weight: f32| {
+        if weight != 0.0 {
+            let sample = fetch(px, py);
+            for chan in 0..4 {
+                colour[chan] += sample[chan] * weight;
+            }
+        }
+    };
+    match interpolation {
+        Interpolation::Nearest => return fetch(x.round() as i64, y.round() as i64),
+        Interpolation::Bilinear => {
+            accumulate(x0, y0, (1.0 - fx) * (1.0 - fy));
+            accumulate(x0 + 1, y0, fx * (1.0 - fy));
+            accumulate(x0, y0 + 1, (1.0 - fx) * fy);
+            accumulate(x0 + 1, y0 + 1, fx * fy);
+        }
+        Interpolation::Bicubic => {
+            let weights_x = cubic_weights(fx);
+            let weights_y = cubic_weights(fy);
+            for (dy, weight_y) in (-1..=2).zip(weights_y) {
+                for (dx, weight_x) in (-1..=2).zip(weights_x) {
+                    accumulate(x0 + dx, y0 + dy, weight_x * weight_y);
+                }
+            }
+        }
     }
+    colour
+}
 
-    let dynimage = ImageRgba8(rgba_img);
-    let src_width = dynimage.width();
-    let src_height = dynimage.height();
-    let raw_pixels = dynimage.into_bytes();
+/// Premultiply a colour by its alpha, for interpolation.
+pub(crate) fn premultiply(colour: &Rgba) -> [f32; 4] {
+    let alpha = colour.get_alpha() as f32 / 255.0;
+    [
+        colour.get_red() as f32 * alpha,
+        colour.get_green() as f32 * alpha,
+        colour.get_blue() as f32 * alpha,
+        colour.get_alpha() as f32,
+    ]
+}
+
+/// Convert a premultiplied colour back to an RGBA pixel.
+pub(crate) fn unpremultiply(colour: [f32; 4]) -> [u8; 4] {
+    let alpha = colour[3].clamp(0.0, 255.0);
+    if alpha < 0.5 {
+        return [0, 0, 0, 0];
+    }
+    let scale = 255.0 / alpha;
+    [
+        (colour[0] * scale).round().clamp(0.0, 255.0) as u8,
+        (colour[1] * scale).round().clamp(0.0, 255.0) as u8,
+        (colour[2] * scale).round().clamp(0.0, 255.0) as u8,
+        alpha.round() as u8,
+    ]
+}
+
+/// Warp the PhotonImage with an affine transformation, such as any combination of
+/// translation, rotation, scaling and shearing.
+/// A transformed PhotonImage is returned.
+///
+/// # Arguments
+/// * `img` - A PhotonImage. See the PhotonImage struct for details.
+/// * `matrix` - The transformation, from source image to output image coordinates.
+/// * `interpolation` - How pixels are sampled.
+/// * `background` - Colour of the areas outside the original image.
+/// * `canvas` - Whether to keep the original size, or fit the canvas to the transformed image.
+///   Expanding the canvas ignores any translation in the matrix.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to rotate an image by 10 degrees and squash it vertically:
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::{affine_transform, AffineMatrix, CanvasSize, Interpolation};
+/// use photon_rs::Rgba;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let matrix = AffineMatrix::rotation(10.0).then(&AffineMatrix::scaling(1.0, 0.5));
+/// let background = Rgba::new(0, 0, 0, 0);
+/// let warped = affine_transform(
+///     &img,
+///     &matrix,
+///     Interpolation::Bilinear,
+///     background,
+///     CanvasSize::Expand,
+/// );
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn affine_transform(
+    photon_img: &PhotonImage,
+    matrix: &AffineMatrix,
+    interpolation: Interpolation,
+    background: Rgba,
+    canvas: CanvasSize,
+) -> PhotonImage {
+    let width = photon_img.get_width();
+    let height = photon_img.get_height();
+
+    let (matrix, dst_width, dst_height) = match canvas {
+        CanvasSize::Original => (*matrix, width, height),
+        CanvasSize::Expand => {
+            let corners = [
+                matrix.apply(0.0, 0.0),
+                matrix.apply(width as f32, 0.0),
+                matrix.apply(0.0, height as f32),
+                matrix.apply(width as f32, height as f32),
+            ];
+            let min_x = corners.iter().map(|c| c.0).fold(f32::INFINITY, f32::min);
+            let max_x = corners
+                .iter()
+                .map(|c| c.0)
+                .fold(f32::NEG_INFINITY, f32::max);
+            let min_y = corners.iter().map(|c| c.1).fold(f32::INFINITY, f32::min);
+            let max_y = corners
+                .iter()
+                .map(|c| c.1)
+                .fold(f32::NEG_INFINITY, f32::max);
+            // Allow for rounding errors, so right angles don't add a row or column.
+            let dst_width = (max_x - min_x - 1e-3).ceil().max(1.0) as u32;
+            let dst_height = (max_y - min_y - 1e-3).ceil().max(1.0) as u32;
+            let origin = AffineMatrix::translation(-min_x, -min_y);
+            (matrix.then(&origin), dst_width, dst_height)
+        }
+    };
+
+    let background = premultiply(&background);
+    let mut raw_pixels = Vec::with_capacity((dst_width * dst_height * 4) as usize);
+    match matrix.inverse() {
+        Some(inverse) => {
+            for y in 0..dst_height {
+                for x in 0..dst_width {
+                    // Map the centre of each output pixel back to the source image.
+                    let (src_x, src_y) = inverse.apply(x as f32 + 0.5, y as f32 + 0.5);
+                    let colour = sample_premultiplied(
+                        photon_img,
+                        src_x - 0.5,
+                        src_y - 0.5,
+                        interpolation,
+                        background,
+                    );
+                    raw_pixels.extend_from_slice(&unpremultiply(colour));
+                }
+            }
+        }
+        None => {
+            let pixel = unpremultiply(background);
+            for _ in 0..dst_width * dst_height {
+                raw_pixels.extend_from_slice(&pixel);
+            }
+        }
+    }
+
+    PhotonImage::new(raw_pixels, dst_width, dst_height)
+}
 
-    let mut img_out = PhotonImage::new(raw_pixels, src_width, src_height);
+/// A perspective transformation (homography), mapping points of one plane to another, such
+/// as the corners of a photographed document to the corners of a rectangle.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Homography {
+    matrix: [f64; 9],
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl Homography {
+    /// Compute the homography which maps four source points to four destination points.
+    /// Returns `None` if either set doesn't hold exactly four points, or three of the points
+    /// in either set lie on a line.
+    ///
+    /// # Arguments
+    /// * `src` - The source points, as `[x1, y1, x2, y2, x3, y3, x4, y4]`.
+    /// * `dst` - The corresponding destination points, in the same layout.
+    pub fn from_points(src: Vec<f32>, dst: Vec<f32>) -> Option<Homography> {
+        if src.len() != 8 || dst.len() != 8 {
+            return None;
+        }
+        // Normalise both sets of points so solving for the homography is well conditioned.
+        let (src_norm, src_points) = normalise_points(&src);
+        let (dst_norm, dst_points) = normalise_points(&dst);
+
+        // Two equations per point correspondence, for the eight unknown coefficients.
+        let mut system = [[0.0_f64; 9]; 8];
+        for (i, ((x, y), (u, v))) in src_points.into_iter().zip(dst_points).enumerate() {
+            system[i * 2] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+            system[i * 2 + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+        }
+        for col in 0..8 {
+            let pivot = (col..8)
+                .max_by(|a, b| system[*a][col].abs().total_cmp(&system[*b][col].abs()))
+                .unwrap();
+            if system[pivot][col].abs() < 1e-9 {
+                return None;
+            }
+            system.swap(col, pivot);
+            let pivot_row = system[col];
+            for (idx, row) in system.iter_mut().enumerate() {
+                if idx != col {
+                    let factor = row[col] / pivot_row[col];
+                    for (val, pivot_val) in row.iter_mut().zip(pivot_row).skip(col) {
+                        *val -= factor * pivot_val;
+                    }
+                }
+            }
+        }
+        let mut normalised = [1.0; 9];
+        for (col, row) in system.iter().enumerate() {
+            normalised[col] = row[8] / row[col];
+        }
+
+        // Collinear points give a homography which collapses the plane to a line.
+        invert_3x3(&normalised)?;
+        let dst_denorm = invert_3x3(&dst_norm)?;
+        let matrix = mul_3x3(&dst_denorm, &mul_3x3(&normalised, &src_norm));
+        Some(Homography { matrix })
+    }
+
+    /// The homography which undoes this one.
+    pub fn inverse(&self) -> Option<Homography> {
+        invert_3x3(&self.matrix).map(|matrix| Homography { matrix })
+    }
 
-    let theta = ((angle % 360.) - (right_angle_count * 90) as f32).to_radians();
-    let beta = theta.sin();
-    let alpha = -1. * ((theta / 2.).tan());
+    /// The coefficients of the 3x3 matrix, in row order.
+    pub fn get_matrix(&self) -> Vec<f64> {
+        self.matrix.to_vec()
+    }
+}
+
+impl Homography {
+    /// Map a point, or `None` if it maps to infinity or behind the viewer.
+    pub(crate) fn apply(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let m = &self.matrix;
+        let (x, y) = (x as f64, y as f64);
+        let w = m[6] * x + m[7] * y + m[8];
+        if w <= 1e-12 {
+            return None;
+        }
+        Some((
+            ((m[0] * x + m[1] * y + m[2]) / w) as f32,
+            ((m[3] * x + m[4] * y + m[5]) / w) as f32,
+        ))
+    }
+}
 
-    img_out = shearx(&img_out, alpha);
-    img_out = sheary(&img_out, beta);
-    img_out = shearx(&img_out, alpha);
+/// Translate four points to have their centroid at the origin, and scale them to have an
+/// average distance of √2 from it. Returns the normalising matrix and the normalised points.
+fn normalise_points(points: &[f32]) -> ([f64; 9], Vec<(f64, f64)>) {
+    let points: Vec<(f64, f64)> = points
+        .chunks_exact(2)
+        .map(|point| (point[0] as f64, point[1] as f64))
+        .collect();
+    let centre_x = points.iter().map(|p| p.0).sum::<f64>() / 4.0;
+    let centre_y = points.iter().map(|p| p.1).sum::<f64>() / 4.0;
+    let distance = points
+        .iter()
+        .map(|p| ((p.0 - centre_x).powi(2) + (p.1 - centre_y).powi(2)).sqrt())
+        .sum::<f64>()
+        / 4.0;
+    let scale = if distance > 0.0 {
+        std::f64::consts::SQRT_2 / distance
+    } else {
+        1.0
+    };
+    let matrix = [
+        scale,
+        0.0,
+        -scale * centre_x,
+        0.0,
+        scale,
+        -scale * centre_y,
+        0.0,
+        0.0,
+        1.0,
+    ];
+    let normalised = points
+        .into_iter()
+        .map(|(x, y)| ((x - centre_x) * scale, (y - centre_y) * scale))
+        .collect();
+    (matrix, normalised)
+}
 
-    img_out
+fn mul_3x3(a: &[f64; 9], b: &[f64; 9]) -> [f64; 9] {
+    let mut out = [0.0; 9];
+    for row in 0..3 {
+        for col in 0..3 {
+            out[row * 3 + col] = (0..3).map(|k| a[row * 3 + k] * b[k * 3 + col]).sum();
+        }
+    }
+    out
+}
+
+fn invert_3x3(m: &[f64; 9]) -> Option<[f64; 9]> {
+    let cofactor = |r1: usize, r2: usize, c1: usize, c2: usize| {
+        m[r1 * 3 + c1] * m[r2 * 3 + c2] - m[r1 * 3 + c2] * m[r2 * 3 + c1]
+    };
+    let adjugate = [
+        cofactor(1, 2, 1, 2),
+        -cofactor(0, 2, 1, 2),
+        cofactor(0, 1, 1, 2),
+        -cofactor(1, 2, 0, 2),
+        cofactor(0, 2, 0, 2),
+        -cofactor(0, 1, 0, 2),
+        cofactor(1, 2, 0, 1),
+        -cofactor(0, 2, 0, 1),
+        cofactor(0, 1, 0, 1),
+    ];
+    let det = m[0] * adjugate[0] + m[1] * adjugate[3] + m[2] * adjugate[6];
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    Some(adjugate.map(|val| val / det))
+}
+
+/// Warp the PhotonImage with a perspective transformation.
+/// A transformed PhotonImage of the given size is returned.
+///
+/// # Arguments
+/// * `img` - A PhotonImage. See the PhotonImage struct for details.
+/// * `homography` - The transformation, from source image to output image coordinates.
+/// * `width` - Width of the output image.
+/// * `height` - Height of the output image.
+/// * `interpolation` - How pixels are sampled.
+/// * `background` - Colour of the areas outside the original image.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to tilt an image backwards:
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::{perspective_transform, Homography, Interpolation};
+/// use photon_rs::Rgba;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let (w, h) = (img.get_width() as f32, img.get_height() as f32);
+/// let homography = Homography::from_points(
+///     vec![0.0, 0.0, w, 0.0, w, h, 0.0, h],
+///     vec![w * 0.2, 0.0, w * 0.8, 0.0, w, h, 0.0, h],
+/// )
+/// .expect("Points should form a quadrilateral");
+/// let background = Rgba::new(0, 0, 0, 0);
+/// let tilted = perspective_transform(
+///     &img,
+///     &homography,
+///     img.get_width(),
+///     img.get_height(),
+///     Interpolation::Bilinear,
+///     background,
+/// );
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn perspective_transform(
+    photon_img: &PhotonImage,
+    homography: &Homography,
+    width: u32,
+    height: u32,
+    interpolation: Interpolation,
+    background: Rgba,
+) -> PhotonImage {
+    let background = premultiply(&background);
+    let inverse = homography.inverse();
+    let mut raw_pixels = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            // Map the centre of each output pixel back to the source image.
+            let src = inverse
+                .as_ref()
+                .and_then(|inverse| inverse.apply(x as f32 + 0.5, y as f32 + 0.5));
+            let colour = match src {
+                Some((src_x, src_y)) => sample_premultiplied(
+                    photon_img,
+                    src_x - 0.5,
+                    src_y - 0.5,
+                    interpolation,
+                    background,
+                ),
+                None => background,
+            };
+            raw_pixels.extend_from_slice(&unpremultiply(colour));
+        }
+    }
+
+    PhotonImage::new(raw_pixels, width, height)
+}
+
+/// Extract a quadrilateral from the PhotonImage into a rectangle, correcting its perspective,
+/// such as a document or whiteboard photographed at an angle.
+/// Returns `None` if there aren't exactly four corners, or three of them lie on a line.
+///
+/// # Arguments
+/// * `img` - A PhotonImage. See the PhotonImage struct for details.
+/// * `corners` - Corners of the quadrilateral, as `[x, y]` pairs for the top left, top right,
+///   bottom right and bottom left corners.
+/// * `width` - Width of the output image, or 0 to use the longer of the top and bottom edges.
+/// * `height` - Height of the output image, or 0 to use the longer of the left and right edges.
+/// * `interpolation` - How pixels are sampled.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to flatten a page photographed at an angle into an A4 sized image:
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::{correct_perspective, Interpolation};
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let corners = vec![120.0, 80.0, 890.0, 130.0, 940.0, 1210.0, 60.0, 1160.0];
+/// let page = correct_perspective(&img, corners, 1240, 1754, Interpolation::Bicubic)
+///     .expect("Corners should form a quadrilateral");
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn correct_perspective(
+    photon_img: &PhotonImage,
+    corners: Vec<f32>,
+    width: u32,
+    height: u32,
+    interpolation: Interpolation,
+) -> Option<PhotonImage> {
+    if corners.len() != 8 {
+        return None;
+    }
+    let edge = |from: usize, to: usize| {
+        let dx = corners[to * 2] - corners[from * 2];
+        let dy = corners[to * 2 + 1] - corners[from * 2 + 1];
+        (dx * dx + dy * dy).sqrt()
+    };
+    let width = match width {
+        0 => edge(0, 1).max(edge(3, 2)).round().max(1.0) as u32,
+        width => width,
+    };
+    let height = match height {
+        0 => edge(0, 3).max(edge(1, 2)).round().max(1.0) as u32,
+        height => height,
+    };
+    let (w, h) = (width as f32, height as f32);
+    let homography =
+        Homography::from_points(corners, vec![0.0, 0.0, w, 0.0, w, h, 0.0, h])?;
+    Some(perspective_transform(
+        photon_img,
+        &homography,
+        width,
+        height,
+        interpolation,
+        Rgba::new(0, 0, 0, 0),
+    ))
 }
 
 fn greatest_common_divisor(left_val: usize, right_val: usize) -> usize {
@@ -923,3 +1894,349 @@ pub fn compress(img: &PhotonImage, quality: u8) -> PhotonImage {
 
     PhotonImage::new_from_byteslice(bytes)
 }
+
+/// A rectangular region of an image.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CropRegion {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl CropRegion {
+    #[cfg_attr(feature = "enable_wasm", wasm_bindgen(constructor))]
+    /// Create a region from its top left corner and size.
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> CropRegion {
+        CropRegion {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Get the x coordinate of the left edge.
+    pub fn get_x(&self) -> u32 {
+        self.x
+    }
+
+    /// Get the y coordinate of the top edge.
+    pub fn get_y(&self) -> u32 {
+        self.y
+    }
+
+    /// Get the width.
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height.
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// The smallest region which contains both this region and another.
+    pub fn union(&self, other: &CropRegion) -> CropRegion {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        CropRegion::new(x, y, right - x, bottom - y)
+    }
+
+    /// Crop an image to this region.
+    pub fn crop(&self, photon_img: &PhotonImage) -> PhotonImage {
+        crop(
+            photon_img,
+            self.x,
+            self.y,
+            self.x + self.width,
+            self.y + self.height,
+        )
+    }
+}
+
+/// The result of a crop which chooses its own region, such as `smart_crop` or `trim`: the
+/// region of the original image, and the cropped image.
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CropResult {
+    region: CropRegion,
+    image: PhotonImage,
+}
+
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+impl CropResult {
+    /// Get the region of the original image which was chosen.
+    pub fn get_region(&self) -> CropRegion {
+        self.region
+    }
+
+    /// Get the cropped image.
+    pub fn get_image(&self) -> PhotonImage {
+        self.image.clone()
+    }
+}
+
+impl CropResult {
+    /// Take the cropped image, without copying it.
+    pub fn into_image(self) -> PhotonImage {
+        self.image
+    }
+}
+
+/// Importance of every pixel of an image, from its edges, saturation and skin tones.
+fn importance_map(photon_img: &PhotonImage) -> Vec<f32> {
+    let mut edges = photon_img.clone();
+    sobel_global(&mut edges);
+
+    photon_img
+        .raw_pixels
+        .chunks_exact(4)
+        .zip(edges.raw_pixels.chunks_exact(4))
+        .map(|(px, edge)| {
+            let (r, g, b) = (px[0] as f32, px[1] as f32, px[2] as f32);
+            let edge =
+                (edge[0] as f32 + edge[1] as f32 + edge[2] as f32) / (3.0 * 255.0);
+
+            let max = r.max(g).max(b);
+            let min = r.min(g).min(b);
+            let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+            // Saturation only stands out in colours which aren't nearly black.
+            let saturation = saturation * (max / 255.0);
+
+            // A simple RGB rule for skin tones in daylight (Kovač et al.).
+            let skin = r > 95.0
+                && g > 40.0
+                && b > 20.0
+                && max - min > 15.0
+                && (r - g).abs() > 15.0
+                && r > g
+                && r > b;
+            let skin = if skin { 1.0 } else { 0.0 };
+
+            let importance = edge * SMART_CROP_EDGE_WEIGHT
+                + saturation * SMART_CROP_SATURATION_WEIGHT
+                + skin * SMART_CROP_SKIN_WEIGHT;
+            importance * (px[3] as f32 / 255.0)
+        })
+        .collect()
+}
+
+/// Crop an image to an aspect ratio, keeping its most interesting part, and resize it.
+///
+/// The largest window with the aspect ratio of `width` and `height` is slid across the image,
+/// and the window with the most edges, saturated colours and skin tones is chosen. Ties are
+/// broken towards the centre of the image. An empty image is returned as is, with an empty
+/// region.
+///
+/// # Arguments
+/// * `img` - A PhotonImage.
+/// * `width` - Width of the cropped image.
+/// * `height` - Height of the cropped image.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to crop a social card of 1200x630:
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::smart_crop;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let result = smart_crop(&img, 1200, 630);
+/// let region = result.get_region();
+/// println!("Cropped at ({}, {})", region.get_x(), region.get_y());
+/// let card = result.into_image();
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn smart_crop(photon_img: &PhotonImage, width: u32, height: u32) -> CropResult {
+    let src_width = photon_img.get_width();
+    let src_height = photon_img.get_height();
+    let (width, height) = (width.max(1), height.max(1));
+    if src_width == 0 || src_height == 0 {
+        return CropResult {
+            region: CropRegion::new(0, 0, 0, 0),
+            image: photon_img.clone(),
+        };
+    }
+
+    // The largest window with the target aspect ratio spans the whole image in one
+    // dimension, so it only slides along the other.
+    let wider = src_width as u64 * height as u64 > width as u64 * src_height as u64;
+    let (crop_width, crop_height) = if wider {
+        let crop_width = (src_height as f64 * width as f64 / height as f64).round();
+        ((crop_width as u32).clamp(1, src_width), src_height)
+    } else {
+        let crop_height = (src_width as f64 * height as f64 / width as f64).round();
+        (src_width, (crop_height as u32).clamp(1, src_height))
+    };
+
+    let analysis = thumbnail(
+        photon_img,
+        SMART_CROP_ANALYSIS_SIZE,
+        SMART_CROP_ANALYSIS_SIZE,
+    );
+    let (analysis_width, analysis_height) = (
+        analysis.get_width() as usize,
+        analysis.get_height() as usize,
+    );
+    let importance = importance_map(&analysis);
+
+    // Total importance of each column (or row), and the window length, in analysis pixels.
+    let (profile, src_len, crop_len): (Vec<f32>, u32, u32) = if wider {
+        let columns = (0..analysis_width)
+            .map(|x| {
+                (0..analysis_height)
+                    .map(|y| importance[y * analysis_width + x])
+                    .sum()
+            })
+            .collect();
+        (columns, src_width, crop_width)
+    } else {
+        let rows = importance
+            .chunks_exact(analysis_width)
+            .map(|row| row.iter().sum())
+            .collect();
+        (rows, src_height, crop_height)
+    };
+    let scale = profile.len() as f64 / src_len as f64;
+    let window = ((crop_len as f64 * scale).round() as usize).clamp(1, profile.len());
+
+    let mut prefix = vec![0.0_f32; profile.len() + 1];
+    for (idx, val) in profile.iter().enumerate() {
+        prefix[idx + 1] = prefix[idx] + val;
+    }
+    let positions = profile.len() - window;
+    let centre = positions as f32 / 2.0;
+    let mut best = (f32::NEG_INFINITY, f32::INFINITY, 0);
+    for pos in 0..=positions {
+        let score = prefix[pos + window] - prefix[pos];
+        let distance = (pos as f32 - centre).abs();
+        let tolerance = best.0.abs() * 1e-4;
+        if score > best.0 + tolerance
+            || (score >= best.0 - tolerance && distance < best.1)
+        {
+            best = (score, distance, pos);
+        }
+    }
+    let offset = ((best.2 as f64 / scale).round() as u32).min(src_len - crop_len);
+
+    let region = if wider {
+        CropRegion::new(offset, 0, crop_width, crop_height)
+    } else {
+        CropRegion::new(0, offset, crop_width, crop_height)
+    };
+    let mut image = region.crop(photon_img);
+    if (crop_width, crop_height) != (width, height) {
+        image = resize(&image, width, height, SamplingFilter::Lanczos3);
+    }
+
+    CropResult { region, image }
+}
+
+/// Find the region of an image which differs from its background, for `trim`.
+///
+/// The background is the colour shared by most of the image's corners. If it is fully
+/// transparent, any pixel which isn't nearly transparent is content. Otherwise, any pixel
+/// with a channel which differs from the background by more than the tolerance is content.
+/// If the whole image is background, the region covers the whole image.
+///
+/// # Arguments
+/// * `img` - A PhotonImage.
+/// * `tolerance` - Largest difference in any channel from the background, from 0 to 255, which
+///   is still treated as background, to allow for noise and compression artifacts.
+///
+/// # Example
+///
+/// ```no_run
+/// // For example, to trim every frame of an animation to the same region:
+/// use photon_rs::animation::Animation;
+/// use photon_rs::transform::trim_region;
+///
+/// let bytes = std::fs::read("animation.gif").expect("File should open");
+/// let mut animation = Animation::decode(&bytes).expect("Animation should decode");
+/// let region = animation
+///     .frames()
+///     .iter()
+///     .map(|frame| trim_region(&frame.get_image(), 10))
+///     .reduce(|a, b| a.union(&b))
+///     .expect("Animation should have frames");
+/// animation.map_frames(|img| *img = region.crop(img));
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn trim_region(photon_img: &PhotonImage, tolerance: u8) -> CropRegion {
+    let width = photon_img.get_width();
+    let height = photon_img.get_height();
+    let full = CropRegion::new(0, 0, width, height);
+    if width == 0 || height == 0 {
+        return full;
+    }
+
+    let pixel = |x: u32, y: u32| {
+        let idx = ((y * width + x) * 4) as usize;
+        &photon_img.raw_pixels[idx..idx + 4]
+    };
+    let corners = [
+        pixel(0, 0),
+        pixel(width - 1, 0),
+        pixel(0, height - 1),
+        pixel(width - 1, height - 1),
+    ];
+    let background = *corners
+        .iter()
+        .max_by_key(|corner| corners.iter().filter(|other| other == corner).count())
+        .unwrap();
+    let is_content = |px: &[u8]| {
+        if background[3] == 0 {
+            px[3] > tolerance
+        } else {
+            px.iter()
+                .zip(background)
+                .any(|(val, bg)| val.abs_diff(*bg) > tolerance)
+        }
+    };
+
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+    for y in 0..height {
+        for x in 0..width {
+            if is_content(pixel(x, y)) {
+                min_x = min_x.min(x);
+                max_x = max_x.max(x);
+                min_y = min_y.min(y);
+                max_y = max_y.max(y);
+            }
+        }
+    }
+    if min_x > max_x {
+        return full;
+    }
+    CropRegion::new(min_x, min_y, max_x - min_x + 1, max_y - min_y + 1)
+}
+
+/// Trim uniform margins from an image, such as the white border of a scan or the transparent
+/// padding of a product shot. See `trim_region` for how the background is detected.
+///
+/// # Arguments
+/// * `img` - A PhotonImage.
+/// * `tolerance` - Largest difference in any channel from the background, from 0 to 255, which
+///   is still treated as background.
+///
+/// # Example
+///
+/// ```no_run
+/// use photon_rs::native::open_image;
+/// use photon_rs::transform::trim;
+///
+/// let img = open_image("img.jpg").expect("File should open");
+/// let result = trim(&img, 16);
+/// let trimmed = result.get_image();
+/// ```
+#[cfg_attr(feature = "enable_wasm", wasm_bindgen)]
+pub fn trim(photon_img: &PhotonImage, tolerance: u8) -> CropResult {
+    let region = trim_region(photon_img, tolerance);
+    let image = region.crop(photon_img);
+    CropResult { region, image }
+}